@@ -87,19 +87,14 @@
 
 use serde::{ser::Error, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
 pub enum Alternative<T> {
-    None,    // No value was specified
+    #[default]
+    None, // No value was specified
     Null,    // `null` was specified.
     Some(T), // A different value was specified.
 }
 
-impl<T> Default for Alternative<T> {
-    fn default() -> Self {
-        Alternative::None
-    }
-}
-
 impl<T> From<Option<T>> for Alternative<T> {
     fn from(option: Option<T>) -> Alternative<T> {
         match option {
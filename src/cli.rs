@@ -0,0 +1,158 @@
+// This module is the entry point of the program, which `main.rs` calls.
+// It reads the settings and starts the monitor, or runs the commands
+// passed as arguments.
+
+use crate::{
+    client, config, control, default, digest, dry_run::DryRun, event::Events, feed, hidden,
+    history, important, metrics, monitor, replay, sample, server, shutdown, stores,
+};
+
+use colored::*;
+use std::{env, io::stdin, sync::Arc};
+use tokio::task;
+
+pub async fn main() {
+    // If the program is run with any arguments, it's being used to
+    // control a monitor that's already running, so it sends the command
+    // and quits instead of starting a new one. The only exception is
+    // `--dry-run`, optionally followed by the directory the messages
+    // should be saved to, which starts the monitor without sending them.
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let dry_run = match args.as_slice() {
+        [flag] if flag == "--dry-run" => Some(None),
+        [flag, directory] if flag == "--dry-run" => Some(Some(directory.clone())),
+        _ => None,
+    };
+
+    if dry_run.is_none() && !args.is_empty() {
+        // The history is read directly from the database, and so are the
+        // recordings, so they don't need the monitor to be running.
+        match args[0].as_str() {
+            "history" => history::run(args[1..].to_vec()),
+            "export" => history::export(args[1..].to_vec()),
+            "replay" => replay::run(args[1..].to_vec()),
+            "digest" => digest::now(config::read()).await,
+            "test" => sample::run(args[1..].to_vec()).await,
+            _ => client::run(args).await,
+        }
+
+        return;
+    }
+
+    hidden!("Starting Program...");
+
+    // This weird-looking string is an "Ascii-Art Font" representation
+    // of "Shopify Monitor", with "Shopify" printed green, using the
+    // `colored` crate, to somewhat resemble the company logo.
+    println!(
+        "  {}         __  __             _ _\n {}       |  \\/  |           (_) |\n{}  | \\  / | ___  _ __  _| |_ ___  _ __\n {} | |\\/| |/ _ \\| '_ \\| | __/ _ \\| '__|\n {} | |  | | (_) | | | | | || (_) | |\n{} |_|  |_|\\___/|_| |_|_|\\__\\___/|_|\n                   {}\n                   {}{}\n",
+        "_____ _                 _  __".green(),
+        "/ ____| |               (_)/ _|".green(),
+        "| (___ | |__   ___  _ __  _| |_ _   _".green(),
+        "\\___ \\| '_ \\ / _ \\| '_ \\| |  _| | | |".green(),
+        "____) | | | | (_) | |_) | | | | |_| |".green(),
+        "|_____/|_| |_|\\___/| .__/|_|_|  \\__, |".green(),
+        "| |           __/ |".green(),
+        "|_|          |___/".green(),
+
+        // This code block allows for the version number of the program
+        // to be always up-to-date, as it will check the value indicated
+        // in `Cargo.toml` and dynamically adjust the number of spaces
+        // used so that the text is always aligned properly.
+        {
+            let version = env!("CARGO_PKG_VERSION");
+            format!("{}VERSION {}", " ".repeat(27 - version.len()), version.green())
+        }
+    );
+
+    // The output will look like this:
+    //   _____ _                 _  __         __  __             _ _
+    //  / ____| |               (_)/ _|       |  \/  |           (_) |
+    // | (___ | |__   ___  _ __  _| |_ _   _  | \  / | ___  _ __  _| |_ ___  _ __
+    //  \___ \| '_ \ / _ \| '_ \| |  _| | | | | |\/| |/ _ \| '_ \| | __/ _ \| '__|
+    //  ____) | | | | (_) | |_) | | | | |_| | | |  | | (_) | | | | | || (_) | |
+    // |_____/|_| |_|\___/| .__/|_|_|  \__, | |_|  |_|\___/|_| |_|_|\__\___/|_|
+    //                    | |           __/ |
+    //                    |_|          |___/                      VERSION X.X.X
+
+    important!("LOADING SETTINGS");
+
+    // This function calls for `config.json` to be loaded by `config`,
+    // then be deserialized and sent over to `stores` to be used the
+    // generate the settings for each monitored website.
+    let config = config::read();
+    let http = config.http.clone();
+    let history = Arc::new(history::History::new(config.history.clone()));
+    let path = history::path(config.history.clone());
+    let recorder = replay::Recorder::new(config.recording.clone());
+    let dry_run = dry_run.map(DryRun::new);
+    let digests = digest::get(&config);
+    let (settings, builder) = stores::get(config);
+
+    important!("STARTING MONITOR");
+
+    // The metrics are always collected, as keeping track of them is
+    // cheap, but they're only exposed if the HTTP server is enabled.
+    let metrics = Arc::new(metrics::Metrics::default());
+
+    // Sites can be added, paused and removed through the control API
+    // while the monitor is running.
+    let (controller, added) = control::Controller::new(builder);
+
+    // Every event detected by the monitor is published here, and the
+    // latest ones are kept in the feed for the dashboard.
+    let events = Arc::new(Events::default());
+    let feed = Arc::new(feed::Feed::new());
+
+    // The subscription has to be created before the monitor starts, so
+    // that no events are missed.
+    let subscription = events.subscribe();
+    {
+        let feed = feed.clone();
+        task::spawn(async move { feed.follow(subscription).await });
+    }
+
+    if let Some(http) = http {
+        task::spawn(server::run(
+            http,
+            metrics.clone(),
+            controller.clone(),
+            feed.clone(),
+        ));
+    }
+
+    // Digests are sent from their own tasks, once a day, unless this is
+    // a dry run, as they're sent to the same channels.
+    if dry_run.is_none() {
+        digest::run(digests, path).await;
+    }
+
+    // Ctrl-C and `SIGTERM` stop the monitor, which then finishes sending
+    // the webhooks for the events it already detected.
+    let shutdown = shutdown::listen();
+
+    // Once the `settings` are returned, the monitor can start running.
+    let stopped = monitor::run(
+        settings, metrics, controller, events, history, recorder, dry_run, added, shutdown,
+    )
+    .await;
+
+    // If there aren't any issues, the program should run indefinitely.
+    // If the monitor is stopped, however, the function will return and
+    // the following code will run. At the moment, the only cause for
+    // `run()` to end is if all provided webhook links are invalid, or
+    // if every store was removed through the control API.
+    important!("STOPPED MONITOR");
+
+    // If the program was asked to stop, nobody is waiting to read the
+    // logs, so it quits straight away.
+    if stopped {
+        return;
+    }
+
+    default!("The monitor has stopped running. Press `Enter` to quit.");
+    stdin()
+        .read_line(&mut String::new())
+        .expect("Failed to read input.");
+}
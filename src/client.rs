@@ -0,0 +1,187 @@
+// This module contains the commands that can be used to control a
+// running monitor from the terminal, such as `shopify-monitor pause
+// Kith`. They simply send requests to the control API exposed by
+// `server`, using the address (and token) included in the config file.
+
+use crate::{config, control::Status, default, error, success, warning};
+use reqwest::{Client, Method};
+use serde_json::{json, Value};
+use std::process;
+
+const USAGE: &str = "Usage: shopify-monitor [COMMAND]
+
+Running the program without a command starts the monitor. The following
+commands control a monitor that's already running, and require `http`
+to be set in the config file:
+
+    stores                        List the stores being monitored
+    pause <store>                 Stop polling a store
+    resume <store>                Start polling a paused store again
+    poll <store>                  Poll a store immediately
+    delay <store> <ms>            Change how often a store is polled
+    add <name> <url> [logo] [ms]  Start monitoring a site
+    remove <store>                Stop monitoring a store
+    mute <server> <channel>       Stop sending events to a channel
+    unmute <server> <channel>     Start sending events to a channel again
+    muted                         List the muted channels
+
+The following commands don't need the monitor to be running:
+
+    history                       Query the recorded events
+    export <file>                 Export the recorded events
+    replay [directory]            Replay the recorded responses
+    digest                        Send every channel's digest now
+    test [store]                  Send sample events to every channel";
+
+pub async fn run(args: Vec<String>) {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let (method, path, body) = match args.as_slice() {
+        ["stores"] => (Method::GET, "stores".into(), None),
+        ["pause", store] => (
+            Method::POST,
+            format!("stores/{}/pause", encode(store)),
+            None,
+        ),
+        ["resume", store] => (
+            Method::POST,
+            format!("stores/{}/resume", encode(store)),
+            None,
+        ),
+        ["poll", store] => (Method::POST, format!("stores/{}/poll", encode(store)), None),
+        ["delay", store, delay] => {
+            let delay = delay.parse::<u64>().unwrap_or_else(|_| {
+                error!("Invalid delay: `{}`!", delay);
+                process::exit(1);
+            });
+
+            (
+                Method::POST,
+                format!("stores/{}/delay", encode(store)),
+                Some(json!({ "delay": delay })),
+            )
+        }
+        ["add", name, url, rest @ ..] if rest.len() <= 2 => (
+            Method::POST,
+            "stores".into(),
+            Some(json!({
+                "name": name,
+                "url": url,
+                "logo": rest.first().unwrap_or(&"shopify"),
+                "delay": rest.get(1).and_then(|delay| delay.parse::<u64>().ok()),
+            })),
+        ),
+        ["remove", store] => (Method::DELETE, format!("stores/{}", encode(store)), None),
+        ["mute", server, channel] => (
+            Method::POST,
+            format!("channels/{}/{}/mute", encode(server), encode(channel)),
+            None,
+        ),
+        ["unmute", server, channel] => (
+            Method::POST,
+            format!("channels/{}/{}/unmute", encode(server), encode(channel)),
+            None,
+        ),
+        ["muted"] => (Method::GET, "channels".into(), None),
+        _ => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    let http = if let Some(http) = config::read().http {
+        http
+    } else {
+        error!("The `http` field isn't set in the config file!");
+        process::exit(1);
+    };
+
+    let mut req = Client::new().request(method, format!("http://{}/api/{}", http.address, path));
+
+    if let Some(token) = &http.token {
+        req = req.bearer_auth(token);
+    }
+
+    if let Some(body) = &body {
+        req = req.json(body);
+    }
+
+    let res = match req.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            error!("Failed to reach the monitor on {}: {}", http.address, e);
+            default!("Make sure the monitor is running.");
+            process::exit(1);
+        }
+    };
+
+    let ok = res.status().is_success();
+    let value = res.json::<Value>().await.unwrap_or(Value::Null);
+
+    if !ok {
+        error!(
+            "{}",
+            value["error"].as_str().unwrap_or("The request failed!")
+        );
+        process::exit(1);
+    }
+
+    match args[0] {
+        "stores" => {
+            let stores = serde_json::from_value::<Vec<Status>>(value).unwrap_or_default();
+
+            if stores.is_empty() {
+                warning!("No stores are being monitored.");
+            }
+
+            for store in stores {
+                default!(
+                    "{} ({}): every {}ms, {}, {}, {} products{}",
+                    store.name,
+                    store.url,
+                    store.delay,
+                    if store.paused { "paused" } else { "running" },
+                    if store.online { "online" } else { "offline" },
+                    store.products,
+                    if store.password {
+                        ", password page up"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        "muted" => {
+            let channels = value["muted"].as_array().cloned().unwrap_or_default();
+
+            if channels.is_empty() {
+                default!("No channels are muted.");
+            }
+
+            for channel in channels {
+                default!(
+                    "`{}` > `{}`",
+                    channel["server"].as_str().unwrap_or_default(),
+                    channel["channel"].as_str().unwrap_or_default()
+                );
+            }
+        }
+        _ => {
+            success!("{}", value["message"].as_str().unwrap_or("Done!"));
+        }
+    }
+}
+
+// Names are included in the URL, so any character other than letters
+// and numbers is percent-encoded.
+fn encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+// This module runs the programs set up as `command` channels, allowing
+// the monitor to trigger local automation instead of (or alongside)
+// sending webhooks.
+
+use crate::{config, hidden, warning};
+use serde_json::Value;
+use std::{process::Stdio, sync::Arc, time::Duration};
+use tokio::{io::AsyncWriteExt, process, sync::Semaphore, time::timeout};
+
+// These are used when the config file doesn't include a value.
+const TIMEOUT: u64 = 10000;
+const CONCURRENCY: usize = 4;
+
+#[derive(Debug)]
+pub struct Command {
+    pub program: String,
+    pub args: Vec<String>,
+    pub timeout: Duration,
+
+    // Every channel running the same command shares this, so that the
+    // concurrency limit applies to all sites at once.
+    pub limit: Arc<Semaphore>,
+}
+
+impl From<&config::Command> for Command {
+    fn from(command: &config::Command) -> Command {
+        Command {
+            program: command.program.clone(),
+            args: command.args.clone().unwrap_or_default(),
+            timeout: Duration::from_millis(command.timeout.unwrap_or(TIMEOUT)),
+
+            // A limit of 0 would prevent the program from ever running.
+            limit: Arc::new(Semaphore::new(
+                command.concurrency.unwrap_or(CONCURRENCY).max(1),
+            )),
+        }
+    }
+}
+
+// The program is started once a "slot" is available, and the event is
+// written to its standard input. If it's still running once the timeout
+// is reached, it's killed, so that a program that hangs can't block the
+// ones started after it.
+pub async fn run(command: Arc<Command>, event: Value) {
+    let _permit = if let Ok(permit) = command.limit.acquire().await {
+        permit
+    } else {
+        return;
+    };
+
+    let child = process::Command::new(&command.program)
+        .args(&command.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warning!("Failed to run `{}`: {}", command.program, e);
+            return;
+        }
+    };
+
+    // The program's input is closed once the event has been written, as
+    // `stdin` is dropped at the end of its block, so that programs
+    // reading until the end of it don't wait forever. Writing it counts
+    // towards the timeout too, as a program that never reads its input
+    // would otherwise block once the pipe is full.
+    let input = event.to_string();
+    let stdin = child.stdin.take();
+
+    let finished = timeout(command.timeout, async {
+        if let Some(mut stdin) = stdin {
+            if let Err(e) = stdin.write_all(input.as_bytes()).await {
+                hidden!("Failed to write to `{}`: {}", command.program, e);
+            }
+        }
+
+        child.wait().await
+    })
+    .await;
+
+    match finished {
+        Ok(Ok(status)) if status.success() => {
+            hidden!("Successfully ran `{}`!", command.program);
+        }
+        Ok(Ok(status)) => {
+            warning!("`{}` failed ({})!", command.program, status);
+        }
+        Ok(Err(e)) => {
+            warning!("Failed to run `{}`: {}", command.program, e);
+        }
+        Err(_) => {
+            warning!(
+                "`{}` took longer than {}ms and was stopped!",
+                command.program,
+                command.timeout.as_millis()
+            );
+
+            let _ = child.kill().await;
+        }
+    }
+}
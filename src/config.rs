@@ -129,10 +129,10 @@ pub fn write(config: &Config) {
     }
 }
 
-// This brief function is only used twice, as it simply provides a link
+// This brief function is only used a few times, as it simply provides a link
 // to the project's GitHub repository where the user can find the
 // documentation to help properly configure the monitor.
-fn suggest_instructions() {
+pub fn suggest_instructions() {
     default!("Please follow the instructions on https://github.com/subreme/shopify-monitor to complete the configuration process.");
     error!("Press `Enter` to close the program...");
 
@@ -182,6 +182,40 @@ pub struct Config {
     // While the types allow for different structures too, these are the
     // two intended and recommended versions.
     pub proxies: Option<VecMap<ProxyList, VecMap<String, String>>>,
+
+    // The embedded HTTP server, which exposes the monitor's metrics, is
+    // only started if this field is included.
+    pub http: Option<Http>,
+
+    // Events are only recorded to a database if this field is included.
+    pub history: Option<History>,
+
+    // Every response to `/products.json` is saved, so that it can be
+    // replayed later on, only if this field is included.
+    pub recording: Option<Recording>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Http {
+    // The address the server listens on, such as `127.0.0.1:9090`.
+    pub address: String,
+
+    // If this is set, requests to the control API have to include it in
+    // their `Authorization` header.
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct History {
+    // The path of the SQLite database, which is `history.db` by default.
+    pub path: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Recording {
+    // The directory the responses are saved to, which is `recordings` by
+    // default. Each store gets its own folder inside of it.
+    pub directory: Option<String>,
 }
 
 // While on a "quest" to create the best possible config file structure
@@ -232,6 +266,27 @@ pub struct Site {
     // unnecessary, as its benefits only apply to cases where a `null`
     // value is significant
     pub delay: Option<u64>,
+
+    // The schedule changes the delay depending on the time of day, and
+    // is explained in more detail in `crate::schedule`.
+    pub schedule: Option<Schedule>,
+
+    // If this is set, only the products of these collections are
+    // monitored, rather than the whole catalog.
+    pub collections: Option<Vec<String>>,
+
+    // These products are polled through their own page, which is often
+    // live before they're added to `/products.json`.
+    pub watchlist: Option<Watchlist>,
+
+    // New products can also be found through the store's Atom feed and
+    // sitemap, for stores that hide them from `/products.json`.
+    pub discovery: Option<Discovery>,
+
+    // By default, products are only parsed in full if one of the site's
+    // channels filters them or shows their details, but this can force
+    // either mode.
+    pub parsing: Option<Parsing>,
 }
 
 // For a `VecMap<T, U>` to work, a second type `U` must be defined,
@@ -242,6 +297,11 @@ pub struct SiteHM {
     pub url: String,
     pub logo: String,
     pub delay: Option<u64>,
+    pub schedule: Option<Schedule>,
+    pub collections: Option<Vec<String>>,
+    pub watchlist: Option<Watchlist>,
+    pub discovery: Option<Discovery>,
+    pub parsing: Option<Parsing>,
 }
 
 // Implementing `IntoIterator` for each `VecMap` type used is easier
@@ -265,6 +325,11 @@ impl IntoIterator for VecMap<Site, SiteHM> {
                     url: sitehm.url,
                     logo: sitehm.logo,
                     delay: sitehm.delay,
+                    schedule: sitehm.schedule,
+                    collections: sitehm.collections,
+                    watchlist: sitehm.watchlist,
+                    discovery: sitehm.discovery,
+                    parsing: sitehm.parsing,
                 });
             }
 
@@ -277,6 +342,96 @@ impl IntoIterator for VecMap<Site, SiteHM> {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Watchlist {
+    // Each product is either its handle, such as `air-jordan-1`, or a
+    // link to it, such as `https://kith.com/products/air-jordan-1`.
+    pub products: Vec<String>,
+
+    // The products are all polled once every `delay`, which is the
+    // same as the site's one if it isn't set.
+    pub delay: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Discovery {
+    // Both sources are used if this isn't set.
+    pub sources: Option<Vec<Source>>,
+
+    // This is `backup` by default.
+    pub mode: Option<Mode>,
+
+    // The sources are fetched once every `delay`, which is the same as
+    // the site's one if it isn't set.
+    pub delay: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    // `/collections/all.atom`
+    Atom,
+
+    // `/sitemap_products_1.xml`
+    Sitemap,
+}
+
+// As a backup, the sources only announce the products `/products.json`
+// doesn't list, while as the primary source, they announce every new
+// product, and `/products.json` is only used for restocks.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Backup,
+    Primary,
+}
+
+// Lean parsing skips the fields of `products.json` the monitor doesn't
+// always need, such as tags, which saves some time on large catalogs.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Parsing {
+    Lean,
+    Full,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Schedule {
+    // The times in the windows are in this UTC offset, such as `-05:00`,
+    // so that they can follow the store's timezone, while the computer's
+    // own timezone is used if it isn't set.
+    pub offset: Option<String>,
+    pub windows: Option<Vec<Window>>,
+    pub boost: Option<Boost>,
+
+    // This is the percentage by which every delay is randomly made
+    // longer or shorter, so that the requests aren't perfectly regular.
+    pub jitter: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Window {
+    // The days are written like `friday` or `fri`, and the window
+    // applies every day if they aren't set.
+    pub days: Option<Vec<String>>,
+
+    // These are written like `10:00`, and a window can end after
+    // midnight, such as one going from `23:00` to `07:00`.
+    pub from: String,
+    pub to: String,
+    pub delay: u64,
+}
+
+// After an event is detected, the store is polled faster for a while,
+// as more are likely to follow.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Boost {
+    pub delay: u64,
+
+    // This is in seconds.
+    pub duration: u64,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Server {
     // If the user chooses to use a `Vec` (or rather, an array) to list
@@ -453,10 +608,41 @@ pub struct Settings {
     // length of `Vec`s, which this value is compared to.
     pub minimum: Alt<usize>,
 
+    // This is how many sizes have to become available for a restock to
+    // be sent, so that a single size coming back doesn't ping anyone.
+    #[serde(default)]
+    pub restocked: Alt<usize>,
+
+    // This is how many seconds a size has to wait, after being sent to
+    // a channel, before it can be sent to it again, which keeps stores
+    // that keep toggling a size's availability from spamming it.
+    #[serde(default)]
+    pub cooldown: Alt<u64>,
+
+    // When this is enabled, instead of sending a new message every time
+    // a product restocks, the monitor edits the one it already sent for
+    // it in the same channel, and updates it when it sells out.
+    #[serde(default)]
+    pub edit: Alt<bool>,
+
+    // Templates replace the default layout of the embeds, and are
+    // explained in more detail in `crate::template`.
+    #[serde(default)]
+    pub template: Alt<Template>,
+
+    // The roles and users that should be pinged when a message is sent.
+    #[serde(default)]
+    pub mention: Alt<Mention>,
+
     // For this field, a `HashMap` could help keep track of what each
     // "keyword group" is targeting, if that helps.
     #[serde(default)]
     pub keywords: Alt<VecMap<Keyword, Keyword>>,
+
+    // Unlike keywords, which only look at a product's title, filters use
+    // its tags and type, which requires its store to be parsed in full.
+    #[serde(default)]
+    pub filter: Alt<Filter>,
 }
 
 // I was planning on calling this `Keywords`, as each one of these
@@ -529,6 +715,94 @@ pub struct Keyword {
     // the default value of `true` anyway. It's the only setting which
     // defaults to true, as users are encouraged to "stack" settings.
     pub combine: Option<bool>,
+
+    // Products matching this group's keywords will ping these roles
+    // and users instead of the ones selected in the `mention` setting,
+    // so that, for example, a "jordan" group can ping a Jordan role. It
+    // can be set to `null` to avoid pinging anyone for them.
+    #[serde(default)]
+    pub mention: Alt<Mention>,
+}
+
+impl IntoIterator for VecMap<Keyword, Keyword> {
+    type Item = Keyword;
+    type IntoIter = IntoIter<Keyword>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        if let VecMap::Vec(keywords) = self {
+            keywords.into_iter()
+        } else if let VecMap::Map(keywords) = self {
+            keywords.into_values().collect::<Vec<Keyword>>().into_iter()
+        } else {
+            vec![].into_iter()
+        }
+    }
+}
+
+// A product has to have at least one of `tags` and be one of `types`,
+// if they're set, and mustn't have any of `exclude_tags`. They're all
+// case insensitive.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Filter {
+    pub tags: Option<VecMap<String, String>>,
+    pub exclude_tags: Option<VecMap<String, String>>,
+    pub types: Option<VecMap<String, String>>,
+}
+
+// Role and user IDs are written as strings, as that's how Discord
+// displays them when they're copied, and they're too large to be
+// handled properly by some JSON tools.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Mention {
+    pub roles: Option<VecMap<String, String>>,
+    pub users: Option<VecMap<String, String>>,
+
+    // These ping every online member of the channel and every member
+    // of the channel, respectively.
+    pub here: Option<bool>,
+    pub everyone: Option<bool>,
+}
+
+// Lists of strings don't have a `name` field, so if they're written as
+// objects, their keys are ignored.
+impl IntoIterator for VecMap<String, String> {
+    type Item = String;
+    type IntoIter = IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        if let VecMap::Vec(strings) = self {
+            strings.into_iter()
+        } else if let VecMap::Map(strings) = self {
+            strings.into_values().collect::<Vec<String>>().into_iter()
+        } else {
+            vec![].into_iter()
+        }
+    }
+}
+
+// Every part of a template is optional, as the parts that aren't
+// included will look the same as they normally do.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Template {
+    pub content: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub description: Option<String>,
+
+    // Unlike every other list in the config file, this one can't be
+    // replaced with an object, as the order of the fields matters and
+    // `HashMap`s don't keep track of it.
+    pub fields: Option<Vec<TemplateField>>,
+
+    // This field is repeated for every available size of a product.
+    pub size: Option<TemplateField>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TemplateField {
+    pub name: String,
+    pub value: String,
+    pub inline: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -541,10 +815,18 @@ pub struct Channel {
     // for logging and debugging purposes, and the ID wasn't ever used,
     // so it was removed.
     /* pub id: u64, */
+    // The URL can be left out if the channel runs a `command` instead
+    // of sending webhooks.
+    #[serde(default)]
     pub url: String,
+    pub command: Option<Command>,
     #[serde(default)]
     pub settings: Alt<Settings>,
 
+    // If this is set, the channel also receives a summary of the events
+    // of its sites every day.
+    pub digest: Option<Digest>,
+
     // By having the field names correspond to a site, it's impossible
     // for users to accidentally configure the same site twice for the
     // same channel.
@@ -569,12 +851,45 @@ pub struct ChannelHM {
     // however I decided against it. Users are still allowed to include
     // they field if they choose to, but its value will be ignored.
     /* pub name: Option<String>, */
+    #[serde(default)]
     pub url: String,
+    pub command: Option<Command>,
     #[serde(default)]
     pub settings: Alt<Settings>,
+    pub digest: Option<Digest>,
     pub sites: VecMap<Store, StoreHM>,
 }
 
+// Instead of sending webhooks, a "channel" can run a program whenever an
+// event occurs, such as a checkout tool or a script playing a sound. The
+// event is written to the program's standard input as JSON.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Command {
+    pub program: String,
+    pub args: Option<Vec<String>>,
+
+    // The maximum number of milliseconds the program is allowed to run
+    // for before it's killed.
+    pub timeout: Option<u64>,
+
+    // The maximum number of instances of the program that can be run at
+    // the same time, as a drop could otherwise start dozens of them.
+    pub concurrency: Option<usize>,
+}
+
+// The digest is sent through the channel's webhook, using its username
+// and avatar, and requires the history to be recorded, as that's where
+// its statistics come from.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Digest {
+    // The time of day the digest is sent at, in the `HH:MM` format and
+    // in the local timezone. It's `09:00` by default.
+    pub time: Option<String>,
+
+    // The number of products listed in the "top restocks" field.
+    pub top: Option<usize>,
+}
+
 impl IntoIterator for VecMap<Channel, ChannelHM> {
     type Item = Channel;
     type IntoIter = IntoIter<Channel>;
@@ -589,6 +904,8 @@ impl IntoIterator for VecMap<Channel, ChannelHM> {
                 channels.push(Channel {
                     name,
                     url: channelhm.url,
+                    command: channelhm.command,
+                    digest: channelhm.digest,
                     settings: channelhm.settings,
                     sites: channelhm.sites,
                 });
@@ -0,0 +1,266 @@
+// This module allows the monitor to be controlled while it's running,
+// through the API exposed by `server` (and the CLI commands in
+// `client`, which use it). Each store's task receives `Control`
+// messages through its own channel, while `Controller` keeps track of
+// every running store so that the server can find them.
+
+use crate::{
+    config,
+    stores::{self, Builder, Store},
+};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU16, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+#[derive(Debug)]
+pub enum Control {
+    Pause,
+    Resume,
+    Delay(u64),
+    Poll,
+    Remove,
+}
+
+// Each store's task updates these values, so that they can be listed
+// without having to ask it.
+#[derive(Debug)]
+pub struct Handle {
+    pub url: String,
+    pub delay: AtomicU64,
+    pub paused: AtomicBool,
+    pub online: AtomicBool,
+
+    // This is a Unix timestamp, in milliseconds, which is 0 until the
+    // store is polled for the first time.
+    pub last_poll: AtomicI64,
+
+    // The status code of the last response, or 0 if the store couldn't
+    // be reached.
+    pub status: AtomicU16,
+    pub password: AtomicBool,
+    pub products: AtomicUsize,
+    pub tx: Sender<Control>,
+}
+
+// This is what's returned by `GET /api/stores` and `GET /status`.
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+    pub name: String,
+    pub url: String,
+    pub delay: u64,
+    pub paused: bool,
+    pub online: bool,
+    pub last_poll: Option<String>,
+    pub status: Option<u16>,
+    pub password: bool,
+    pub products: usize,
+}
+
+#[derive(Debug)]
+pub struct Controller {
+    // A `BTreeMap` is used so that the stores are listed alphabetically.
+    stores: RwLock<BTreeMap<String, Arc<Handle>>>,
+
+    // These are the names of the stores that were added, but whose tasks
+    // haven't registered them yet, which are reserved so that the same
+    // store can't be added twice in the meantime.
+    adding: Mutex<HashSet<String>>,
+
+    // Channels are muted by the name of their server and their own, as
+    // that's how they're referred to in the config file and the logs,
+    // and channels in different servers can share the same name.
+    muted: RwLock<HashSet<(String, String)>>,
+    builder: Mutex<Builder>,
+
+    // New stores are sent to `monitor::run()`, which starts monitoring
+    // them like the ones included in the config file.
+    added: Sender<Store>,
+}
+
+impl Controller {
+    pub fn new(builder: Builder) -> (Arc<Controller>, Receiver<Store>) {
+        let (added, rx) = mpsc::channel(8);
+
+        (
+            Arc::new(Controller {
+                stores: RwLock::new(BTreeMap::new()),
+                adding: Mutex::new(HashSet::new()),
+                muted: RwLock::new(HashSet::new()),
+                builder: Mutex::new(builder),
+                added,
+            }),
+            rx,
+        )
+    }
+
+    // This is called when a store starts being monitored, and returns
+    // the receiver its task will get its `Control` messages from.
+    pub fn register(&self, store: &Store) -> (Arc<Handle>, Receiver<Control>) {
+        let (tx, rx) = mpsc::channel(8);
+
+        let handle = Arc::new(Handle {
+            url: store.url.clone(),
+            delay: AtomicU64::new(store.delay),
+            paused: AtomicBool::new(false),
+            online: AtomicBool::new(true),
+            last_poll: AtomicI64::new(0),
+            status: AtomicU16::new(0),
+            password: AtomicBool::new(false),
+            products: AtomicUsize::new(0),
+            tx,
+        });
+
+        self.stores
+            .write()
+            .expect("Failed to lock stores.")
+            .insert(store.name.clone(), handle.clone());
+
+        // The name is only released once the store is registered, so
+        // that it's always taken by one or the other.
+        self.adding
+            .lock()
+            .expect("Failed to lock stores.")
+            .remove(&store.name);
+
+        (handle, rx)
+    }
+
+    pub fn unregister(&self, name: &str) {
+        self.stores
+            .write()
+            .expect("Failed to lock stores.")
+            .remove(name);
+    }
+
+    pub fn list(&self) -> Vec<Status> {
+        self.stores
+            .read()
+            .expect("Failed to lock stores.")
+            .iter()
+            .map(|(name, handle)| Status {
+                name: name.clone(),
+                url: handle.url.clone(),
+                delay: handle.delay.load(Ordering::Relaxed),
+                paused: handle.paused.load(Ordering::Relaxed),
+                online: handle.online.load(Ordering::Relaxed),
+                last_poll: match handle.last_poll.load(Ordering::Relaxed) {
+                    0 => None,
+                    millis => Utc
+                        .timestamp_millis_opt(millis)
+                        .single()
+                        .map(|time| time.to_rfc3339()),
+                },
+                status: match handle.status.load(Ordering::Relaxed) {
+                    0 => None,
+                    status => Some(status),
+                },
+                password: handle.password.load(Ordering::Relaxed),
+                products: handle.products.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    // The message is sent to the store's task, returning `false` if the
+    // store isn't being monitored.
+    pub async fn send(&self, name: &str, control: Control) -> bool {
+        let tx = if let Some(handle) = self
+            .stores
+            .read()
+            .expect("Failed to lock stores.")
+            .get(name)
+        {
+            handle.tx.clone()
+        } else {
+            return false;
+        };
+
+        tx.send(control).await.is_ok()
+    }
+
+    pub async fn add(&self, site: config::Site) -> Result<(), String> {
+        let name = site.name.clone();
+
+        // The name is checked and reserved while holding the same lock,
+        // so that two requests adding the same store can't both succeed.
+        {
+            let mut adding = self.adding.lock().expect("Failed to lock stores.");
+
+            if adding.contains(&name)
+                || self
+                    .stores
+                    .read()
+                    .expect("Failed to lock stores.")
+                    .contains_key(&name)
+            {
+                return Err(format!("`{}` is already being monitored!", name));
+            }
+
+            adding.insert(name.clone());
+        }
+
+        let store = stores::build(
+            vec![site],
+            &mut self.builder.lock().expect("Failed to lock builder."),
+        )
+        .pop();
+
+        // Sites are only monitored if a channel in the config file
+        // receives events for them, which in this case means that one
+        // of the channels has to list the site's name.
+        let result = if let Some(store) = store {
+            self.added
+                .send(store)
+                .await
+                .map_err(|_| "The monitor isn't running!".to_string())
+        } else {
+            Err(format!("No channel receives events for `{}`!", name))
+        };
+
+        if result.is_err() {
+            self.adding
+                .lock()
+                .expect("Failed to lock stores.")
+                .remove(&name);
+        }
+
+        result
+    }
+
+    pub fn mute(&self, server: &str, channel: &str, muted: bool) {
+        let mut channels = self.muted.write().expect("Failed to lock channels.");
+        let key = (server.to_string(), channel.to_string());
+
+        if muted {
+            channels.insert(key);
+        } else {
+            channels.remove(&key);
+        }
+    }
+
+    pub fn muted(&self, server: &str, channel: &str) -> bool {
+        self.muted
+            .read()
+            .expect("Failed to lock channels.")
+            .contains(&(server.to_string(), channel.to_string()))
+    }
+
+    pub fn muted_channels(&self) -> Vec<(String, String)> {
+        let mut channels = self
+            .muted
+            .read()
+            .expect("Failed to lock channels.")
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        channels.sort();
+        channels
+    }
+}
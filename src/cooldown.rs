@@ -0,0 +1,87 @@
+// This module keeps stores that toggle the availability of their sizes
+// back and forth, which often happens for a few seconds while their
+// inventory syncs, from sending the same restock over and over. Every
+// size that's announced to a channel starts a cooldown, set by the
+// channel's `cooldown` setting, and restocks whose sizes are all still
+// cooling down aren't sent to it.
+
+use crate::{event::Event, stores::Channel};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// The sizes are identified by the channel they were sent to, and by the
+// store, product and variant they belong to. Channels are told apart by
+// their server, name and URL together, as channels running a command
+// don't have a URL, and names can be repeated in different servers.
+type Key = ((String, String, String), String, u64, u64);
+
+#[derive(Default)]
+pub struct Cooldowns {
+    // This holds when each size's cooldown ends.
+    until: Mutex<HashMap<Key, Instant>>,
+}
+
+impl Cooldowns {
+    // This returns whether the event should be sent to the channel. New
+    // products are always sent, but their sizes start cooling down too,
+    // as they're often followed by a restock straight away.
+    pub fn allow(&self, channel: &Channel, event: &Event) -> bool {
+        let cooldown = Duration::from_secs(channel.settings.cooldown);
+
+        if cooldown.as_secs() == 0 {
+            return true;
+        }
+
+        let (product, variants) = match event {
+            Event::NewProduct { product, .. } => (
+                product,
+                product
+                    .variants
+                    .iter()
+                    .filter(|variant| variant.available)
+                    .map(|variant| variant.id)
+                    .collect(),
+            ),
+            Event::Restock {
+                product, restocked, ..
+            } => (product, restocked.clone()),
+            _ => return true,
+        };
+
+        let store = &event.store().name;
+        let now = Instant::now();
+        let mut until = self.until.lock().expect("Failed to lock cooldowns.");
+
+        // The cooldowns that ended are removed, so that the map doesn't
+        // keep growing.
+        until.retain(|_, until| *until > now);
+
+        let channel = (
+            channel.server.clone(),
+            channel.name.clone(),
+            channel.url.clone(),
+        );
+        let key = |variant: u64| (channel.clone(), store.clone(), product.id, variant);
+
+        // The cooldowns aren't extended by the restocks they suppress, or
+        // by the ones that are sent because some of their sizes weren't
+        // cooling down, as otherwise a size that keeps flapping would
+        // never be sent again.
+        if matches!(event, Event::Restock { .. })
+            && variants
+                .iter()
+                .all(|variant| until.contains_key(&key(*variant)))
+        {
+            return false;
+        }
+
+        for variant in variants {
+            until.entry(key(variant)).or_insert(now + cooldown);
+        }
+
+        true
+    }
+}
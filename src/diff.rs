@@ -0,0 +1,205 @@
+// This module compares the products a store listed the last time it was
+// polled with the current ones, finding the ones that were added,
+// restocked or sold out. It doesn't send anything, so that the same
+// rules can be used by the monitor, by anyone embedding the library and
+// to replay the responses that were recorded.
+
+use crate::{
+    event::{Event, Store},
+    products::Product,
+};
+
+// When previous products and variants are compared against the current
+// ones to find any changes, only their id (used to identify them) and
+// whether each variant was available are compared. This new struct,
+// holding the minimum amount of data, can be used to reduce memory usage
+// so that products don't have to be saved to a database.
+#[derive(Clone, Debug)]
+pub struct MinimalProduct {
+    pub id: u64,
+    pub variants: Vec<MinimalVariant>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MinimalVariant {
+    pub id: u64,
+    pub available: bool,
+    // While the program could check when each variant was last updated,
+    // ignoring that value and only checking its availability is faster,
+    // and removing its field results in lower memory usage.
+    // updated_at: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change<'a> {
+    New(&'a Product),
+
+    // The IDs are the ones of the variants that became available, while
+    // the product's other available variants were already in stock.
+    Restock(&'a Product, Vec<u64>),
+
+    // At least one of the product's available variants sold out, which
+    // is only sent to the channels that edit their messages.
+    SoldOut(&'a Product),
+}
+
+impl Change<'_> {
+    // This function turns the change into the event it causes on the
+    // store.
+    pub fn event(self, store: Store) -> Event {
+        match self {
+            Change::New(product) => Event::NewProduct {
+                store,
+                product: product.clone(),
+                collection: None,
+            },
+            Change::Restock(product, restocked) => Event::Restock {
+                store,
+                product: product.clone(),
+                restocked,
+                collection: None,
+            },
+            Change::SoldOut(product) => Event::SoldOut {
+                store,
+                product: product.clone(),
+                collection: None,
+            },
+        }
+    }
+}
+
+// This function keeps what's needed to compare the products with the
+// next ones.
+pub fn minimal(products: &[Product]) -> Vec<MinimalProduct> {
+    products
+        .iter()
+        .map(|product| MinimalProduct {
+            id: product.id,
+            variants: product
+                .variants
+                .iter()
+                .map(|variant| MinimalVariant {
+                    id: variant.id,
+                    available: variant.available,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+// The changes are returned in the order the products are listed in.
+pub fn diff<'a>(previous: &[MinimalProduct], current: &'a [Product]) -> Vec<Change<'a>> {
+    let mut changes = vec![];
+
+    // Cycling through each current product the program finds the same
+    // item in the previous ones by matching IDs.
+    for curr in current {
+        // Originally, the program only checked the variants of products
+        // whose `updated_at` had changed, and then sent every available
+        // variant, which missed restocks that didn't update it and listed
+        // sizes that had never sold out. Now, a product restocked if any
+        // of its variants went from unavailable to available, and those
+        // are the variants the restock is about. Variants that weren't
+        // listed before aren't counted, as it isn't known whether they
+        // were ever out of stock.
+        if let Some(prev) = previous.iter().find(|prev| prev.id == curr.id) {
+            let restocked = curr
+                .variants
+                .iter()
+                .filter(|curr| {
+                    curr.available
+                        && prev
+                            .variants
+                            .iter()
+                            .any(|prev| prev.id == curr.id && !prev.available)
+                })
+                .map(|curr| curr.id)
+                .collect::<Vec<_>>();
+
+            if !restocked.is_empty() {
+                changes.push(Change::Restock(curr, restocked));
+
+            // If any of the variants that were available went out of
+            // stock, the messages that were sent for the product are
+            // edited to reflect it.
+            } else if curr.variants.iter().any(|curr| {
+                prev.variants
+                    .iter()
+                    .any(|prev| prev.id == curr.id && prev.available && !curr.available)
+            }) {
+                changes.push(Change::SoldOut(curr));
+            }
+
+        // This code will run if a product is found that wasn't present
+        // among the previous ones, meaning it's a new one.
+        } else {
+            changes.push(Change::New(curr));
+        }
+    }
+
+    changes
+}
+
+// This keeps track of what a store looked like the last time it was
+// polled, turning each response into the events it causes: the password
+// page going up or down, and the changes found by `diff()`.
+#[derive(Clone, Debug)]
+pub struct Detector {
+    store: Store,
+    previous: Option<Vec<MinimalProduct>>,
+    password: bool,
+}
+
+impl Detector {
+    pub fn new(store: Store) -> Detector {
+        Detector {
+            store,
+            previous: None,
+            password: false,
+        }
+    }
+
+    // These are the products found by the last response that could be
+    // parsed, which aren't set until the store is polled successfully.
+    pub fn previous(&self) -> Option<&[MinimalProduct]> {
+        self.previous.as_deref()
+    }
+
+    pub fn password(&self) -> bool {
+        self.password
+    }
+
+    // The products are only included if the response could be parsed,
+    // as otherwise the store's products can't be compared.
+    pub fn detect(&mut self, status: u16, products: Option<&[Product]>) -> Vec<Event> {
+        let mut events = vec![];
+
+        // The password page is removed as soon as the products can be
+        // fetched again, while it's up when they require a password.
+        if status == 200 && self.password {
+            self.password = false;
+            events.push(Event::PasswordDown {
+                store: self.store.clone(),
+            });
+        } else if status == 401 && !self.password {
+            self.password = true;
+            events.push(Event::PasswordUp {
+                store: self.store.clone(),
+            });
+        }
+
+        if let Some(current) = products {
+            // On the first successful request, there won't be a previous
+            // value to compare the products to, so they're only saved.
+            if let Some(previous) = &self.previous {
+                for change in diff(previous, current) {
+                    events.push(change.event(self.store.clone()));
+                }
+            }
+
+            self.previous = Some(minimal(current));
+        }
+
+        events
+    }
+}
@@ -0,0 +1,334 @@
+// This module sends a daily digest to the channels that ask for one,
+// summarizing the events of their sites over the last day: how many new
+// products and restocks each store had, how long its password page was
+// up for, and which products restocked the most. Its statistics come
+// from the database recorded by `crate::history`.
+
+use crate::{
+    alternative::Alternative as Alt,
+    config, default, error, hidden, history,
+    message::{Embed, Field, Message},
+    monitor::elapsed,
+    stores, success, warning,
+    webhook::{self, Status},
+};
+use chrono::prelude::*;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{task, time};
+
+// These are used when the digest doesn't set its own.
+const TIME: &str = "09:00";
+const TOP: usize = 5;
+
+// Each digest covers the day before it's sent, and the password page's
+// uptime is shown as a percentage of it.
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct Digest {
+    channel: String,
+    url: String,
+    username: Option<String>,
+    avatar: Option<String>,
+    color: Option<u32>,
+
+    // The sites the channel receives events for, as `(name, url)`.
+    sites: Vec<(String, String)>,
+    time: NaiveTime,
+    top: usize,
+}
+
+// This function finds the channels that should receive a digest. Unlike
+// the rest of the config file, mistakes here don't stop the monitor, as
+// they don't affect the events it sends.
+pub fn get(config: &config::Config) -> Vec<Digest> {
+    let urls = config
+        .sites
+        .clone()
+        .into_iter()
+        .map(|site| (site.name, site.url.trim_end_matches('/').to_string()))
+        .collect::<HashMap<_, _>>();
+
+    let mut digests = vec![];
+
+    for server in config.servers.clone() {
+        for channel in server.channels {
+            let digest = if let Some(digest) = &channel.digest {
+                digest
+            } else {
+                continue;
+            };
+
+            if !channel.url.contains("https://discord.com/api/webhooks/") {
+                warning!(
+                    "`{}` > `{}` can't receive a digest without a webhook URL!",
+                    server.name,
+                    channel.name
+                );
+                continue;
+            }
+
+            let time = digest.time.as_deref().unwrap_or(TIME);
+
+            let time = if let Ok(time) = NaiveTime::parse_from_str(time, "%H:%M") {
+                time
+            } else {
+                warning!(
+                    "Invalid digest time in `{}` > `{}`: `{}`!",
+                    server.name,
+                    channel.name,
+                    time
+                );
+                continue;
+            };
+
+            // Only the settings that apply to the whole message are used,
+            // following the same rules as the other messages.
+            let mut username = None;
+            let mut avatar = None;
+            let mut color = None;
+
+            for settings in [&server.settings, &channel.settings] {
+                if let Alt::Some(settings) = settings {
+                    match &settings.username {
+                        Alt::Some(value) => username = Some(value.clone()),
+                        Alt::Null => username = None,
+                        Alt::None => {}
+                    }
+
+                    match &settings.avatar {
+                        Alt::Some(value) => avatar = Some(value.clone()),
+                        Alt::Null => avatar = None,
+                        Alt::None => {}
+                    }
+
+                    match &settings.color {
+                        Alt::Some(value) => color = stores::parse_color(&Some(value.clone())),
+                        Alt::Null => color = None,
+                        Alt::None => {}
+                    }
+                }
+            }
+
+            let sites = channel
+                .sites
+                .into_iter()
+                .map(|store| {
+                    let url = urls.get(&store.name).cloned().unwrap_or_default();
+                    (store.name, url)
+                })
+                .collect();
+
+            digests.push(Digest {
+                channel: channel.name,
+                url: channel.url.trim_end_matches('/').into(),
+                username,
+                avatar,
+                color,
+                sites,
+                time,
+                top: digest.top.unwrap_or(TOP),
+            });
+        }
+    }
+
+    if !digests.is_empty() && config.history.is_none() {
+        warning!("Digests require `history` to be set in the config file!");
+        default!("They won't be sent.");
+        return vec![];
+    }
+
+    digests
+}
+
+// Each digest waits for its own time of day, then is sent every day at
+// the same time.
+pub async fn run(digests: Vec<Digest>, path: String) {
+    let path = Arc::new(path);
+
+    for digest in digests {
+        let path = path.clone();
+
+        task::spawn(async move {
+            loop {
+                let next = next(digest.time);
+                hidden!("Sending the digest to `{}` at {}.", digest.channel, next);
+
+                time::sleep((next - Local::now()).to_std().unwrap_or_default()).await;
+                send(&digest, &path).await;
+            }
+        });
+    }
+}
+
+// This function sends every digest straight away, for the `digest`
+// command.
+pub async fn now(config: config::Config) {
+    let path = history::path(config.history.clone());
+    let digests = get(&config);
+
+    if digests.is_empty() {
+        warning!("No channel has a digest!");
+    }
+
+    for digest in digests {
+        send(&digest, &path).await;
+    }
+}
+
+fn next(at: NaiveTime) -> DateTime<Local> {
+    let now = Local::now();
+    let today = now.naive_local().date().and_time(at);
+
+    [today, today + chrono::Duration::days(1)]
+        .iter()
+        .filter_map(|time| Local.from_local_datetime(time).earliest())
+        .find(|time| *time > now)
+        // If the time doesn't exist today and tomorrow, which can only
+        // happen around a change to daylight saving time, the digest is
+        // simply sent a day later.
+        .unwrap_or_else(|| now + chrono::Duration::days(1))
+}
+
+async fn send(digest: &Digest, path: &str) {
+    let to = Utc::now();
+    let from = to - chrono::Duration::days(1);
+
+    let summary = {
+        let path = path.to_string();
+        let stores = digest
+            .sites
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let top = digest.top;
+
+        // The database is read with a blocking API, so it's queried from
+        // a separate thread.
+        task::spawn_blocking(move || {
+            history::read(&path).and_then(|connection| {
+                history::summary(&connection, &stores, from, to, top).map_err(|e| e.to_string())
+            })
+        })
+        .await
+    };
+
+    let summary = match summary {
+        Ok(Ok(summary)) => summary,
+        Ok(Err(e)) => {
+            error!(
+                "Failed to create the digest for `{}`: {}",
+                digest.channel, e
+            );
+            return;
+        }
+        Err(e) => {
+            error!(
+                "Failed to create the digest for `{}`: {}",
+                digest.channel, e
+            );
+            return;
+        }
+    };
+
+    let mut fields = summary
+        .stores
+        .iter()
+        .map(|store| Field {
+            name: store.name.clone(),
+            value: format!(
+                "New Products: {}\nRestocks: {}\nPassword Page: {}",
+                store.new,
+                store.restocks,
+                if store.password.as_secs() == 0 {
+                    "Down".into()
+                } else {
+                    format!(
+                        "Up for {} ({}%)",
+                        elapsed(store.password),
+                        store.password.as_secs() * 100 / DAY.as_secs()
+                    )
+                }
+            ),
+            inline: Some(true),
+        })
+        .collect::<Vec<_>>();
+
+    let urls = digest.sites.iter().cloned().collect::<HashMap<_, _>>();
+
+    fields.push(Field {
+        name: "Top Restocks".into(),
+        value: if summary.restocked.is_empty() {
+            "No products restocked.".into()
+        } else {
+            summary
+                .restocked
+                .iter()
+                .enumerate()
+                .map(|(i, product)| {
+                    format!(
+                        "{}. [{}]({}/products/{}) on {} ({} {})",
+                        i + 1,
+                        product.title,
+                        urls.get(&product.store).cloned().unwrap_or_default(),
+                        product.handle,
+                        product.store,
+                        product.restocks,
+                        if product.restocks == 1 {
+                            "restock"
+                        } else {
+                            "restocks"
+                        }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        },
+        inline: Some(false),
+    });
+
+    let message = Message {
+        content: None,
+        embeds: Some(vec![Embed {
+            title: Some("Daily Digest".into()),
+            description: Some(format!(
+                "Events from {} to {}.",
+                from.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                to.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            )),
+            url: None,
+            color: digest.color,
+            fields: Some(fields),
+            author: None,
+            footer: None,
+            timestamp: Some(to.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            image: None,
+            thumbnail: None,
+        }]),
+        username: digest.username.clone(),
+        avatar_url: digest.avatar.clone(),
+        allowed_mentions: None,
+    }
+    .fit();
+
+    match webhook::send(digest.url.clone(), Arc::new(message)).await {
+        Status::Success(_) => {
+            success!("Sent the daily digest to `{}`!", digest.channel);
+        }
+        Status::RateLimit(_) => {
+            warning!(
+                "Failed to send the digest to `{}`: rate limited!",
+                digest.channel
+            );
+        }
+        Status::Invalid => {
+            warning!(
+                "Failed to send the digest to `{}`: invalid webhook!",
+                digest.channel
+            );
+        }
+        Status::Unknown => {
+            warning!("Failed to send the digest to `{}`!", digest.channel);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// This module finds new products through a store's Atom feed and its
+// sitemap, rather than `/products.json`, which some stores hide products
+// from, or cache for so long that new products show up late. Both list
+// the links to the store's products, so the ones that weren't there the
+// last time are looked up through `/products/{handle}.js`, and announced
+// as new products, exactly like the ones found in `/products.json`.
+
+// Discovery is used either as a backup, in which case it only announces
+// the products `/products.json` doesn't list, or as the primary source
+// of new products, in which case the ones found by `/products.json` are
+// ignored, and it's only used for restocks. Either way, the IDs of the
+// products that were listed or announced are shared with the store's
+// task, so that none of them is announced twice.
+
+use crate::{
+    config::Source,
+    event::{Event, Store},
+    hidden,
+    poller::Poller,
+    stores,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+// This is how long a product whose link was found keeps being looked up
+// before giving up on its page going live, regardless of how often the
+// sources are fetched.
+pub const GIVE_UP: Duration = Duration::from_secs(60 * 60);
+
+pub struct Discovery {
+    poller: Poller,
+    store: Store,
+    feeds: Vec<Feed>,
+    primary: bool,
+
+    // These are the products that were already listed by `/products.json`
+    // or announced, using their IDs.
+    listed: Arc<Mutex<HashSet<u64>>>,
+
+    // These are the handles found by any of the sources, which are only
+    // looked up the first time they're found. The products on the
+    // store's watchlist are included from the start, as they're already
+    // announced by it.
+    handles: HashSet<String>,
+
+    // Links can be added to the sitemap before the product's page goes
+    // live, so they're looked up again every time until it does, or
+    // until `GIVE_UP` has passed since they were found, which is when
+    // they were added.
+    pending: HashMap<String, Instant>,
+}
+
+struct Feed {
+    source: Source,
+
+    // This is `None` until the source is fetched successfully for the
+    // first time, as the products it lists then aren't new.
+    seen: Option<HashSet<String>>,
+}
+
+impl Discovery {
+    pub fn new(
+        url: &str,
+        store: Store,
+        sources: &[Source],
+        primary: bool,
+        listed: Arc<Mutex<HashSet<u64>>>,
+        watchlist: &[String],
+    ) -> Discovery {
+        Discovery {
+            poller: Poller::new(url),
+            store,
+            feeds: sources
+                .iter()
+                .map(|source| Feed {
+                    source: *source,
+                    seen: None,
+                })
+                .collect(),
+            primary,
+            listed,
+            handles: watchlist.iter().cloned().collect(),
+            pending: HashMap::new(),
+        }
+    }
+
+    // Every source is fetched once, returning the new products they led
+    // to.
+    pub async fn poll(&mut self) -> Vec<Event> {
+        for feed in self.feeds.iter_mut() {
+            let path = match feed.source {
+                Source::Atom => "/collections/all.atom",
+                Source::Sitemap => "/sitemap_products_1.xml",
+            };
+
+            let found = match self.poller.page(path).await {
+                Ok(body) => handles(&body, feed.source),
+                Err(e) => {
+                    hidden!("Failed to GET {}{}: {}", self.store.url, path, e);
+                    continue;
+                }
+            };
+
+            match &mut feed.seen {
+                Some(seen) => {
+                    for handle in found {
+                        if seen.insert(handle.clone()) && self.handles.insert(handle.clone()) {
+                            self.pending.insert(handle, Instant::now());
+                        }
+                    }
+                }
+                None => {
+                    self.handles.extend(found.iter().cloned());
+                    feed.seen = Some(found);
+                }
+            }
+        }
+
+        let mut events = vec![];
+
+        for handle in self.pending.keys().cloned().collect::<Vec<_>>() {
+            match self.poller.product(&handle).await {
+                Ok(Some(product)) => {
+                    self.pending.remove(&handle);
+
+                    // As a backup, products that `/products.json` already
+                    // lists are left to it.
+                    let new = self
+                        .listed
+                        .lock()
+                        .expect("Failed to lock products.")
+                        .insert(product.id);
+
+                    if new || self.primary {
+                        events.push(Event::NewProduct {
+                            store: self.store.clone(),
+                            product,
+                            collection: None,
+                        });
+                    }
+
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    hidden!(
+                        "Failed to GET {}/products/{}.js: {}",
+                        self.store.url,
+                        handle,
+                        e
+                    );
+                }
+            }
+
+            if self.pending[&handle].elapsed() >= GIVE_UP {
+                hidden!(
+                    "Stopped looking up {}/products/{}.js, which never went live.",
+                    self.store.url,
+                    handle
+                );
+                self.pending.remove(&handle);
+            }
+        }
+
+        events
+    }
+}
+
+// This function returns the handles of the products linked to by the
+// feed. Only the links of the Atom feed's entries and the locations in
+// the sitemap are read, as both also include links to images, which can
+// contain `/products/` too.
+pub fn handles(body: &str, source: Source) -> HashSet<String> {
+    let (start, end) = match source {
+        Source::Atom => ("<link", ">"),
+        Source::Sitemap => ("<loc>", "</loc>"),
+    };
+
+    body.split(start)
+        .skip(1)
+        .filter_map(|tag| tag.split(end).next())
+        .filter_map(|tag| match source {
+            Source::Atom => tag.split("href=\"").nth(1)?.split('"').next(),
+            Source::Sitemap => Some(tag.trim()),
+        })
+        .filter(|link| link.contains("/products/"))
+        .map(|link| stores::handle(link, "products"))
+        .filter(|handle| !handle.is_empty())
+        .collect()
+}
@@ -0,0 +1,150 @@
+// This module is used instead of Discord when the monitor is started with
+// `--dry-run`, so that a new config file can be tried against the real
+// stores without sending anything to the channels it includes. Every
+// message that would have been sent is printed as JSON, along with a
+// link to preview it on Discohook, or saved to a directory if one was
+// given, in which case only the link is printed.
+
+use crate::{default, message::Message, warning};
+use base64::{encode_config, URL_SAFE};
+use chrono::prelude::*;
+use serde_json::{json, Value};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+pub struct DryRun {
+    directory: Option<PathBuf>,
+
+    // This is used to number the messages, which also gives each of them
+    // an ID, so that channels that edit their messages still do so.
+    count: AtomicUsize,
+}
+
+impl DryRun {
+    pub fn new(directory: Option<String>) -> Arc<DryRun> {
+        let directory = directory.map(PathBuf::from);
+
+        if let Some(directory) = &directory {
+            if let Err(e) = std::fs::create_dir_all(directory) {
+                warning!("Failed to create `{}`: {}", directory.display(), e);
+            }
+
+            default!(
+                "Dry run: messages will be saved to `{}` instead of being sent.",
+                directory.display()
+            );
+        } else {
+            default!("Dry run: messages will be printed instead of being sent.");
+        }
+
+        Arc::new(DryRun {
+            directory,
+            count: AtomicUsize::new(0),
+        })
+    }
+
+    // This takes the place of sending the message to the webhook, or of
+    // editing the message with the given ID, returning the message's ID
+    // like Discord would.
+    pub async fn message(&self, url: &str, id: Option<&str>, msg: &Message) -> String {
+        let n = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let action = if id.is_some() { "Editing" } else { "Sending" };
+        let preposition = if id.is_some() { "on" } else { "to" };
+        let id = id.map_or_else(|| format!("dry-run-{}", n), String::from);
+        let url = redact(url);
+
+        let value = json!({
+            "url": url,
+            "id": id,
+            "message": msg,
+        });
+
+        self.output(
+            n,
+            &format!("{} message `{}` {} {}", action, id, preposition, url),
+            &value,
+            Some(preview(msg)),
+        )
+        .await;
+
+        id
+    }
+
+    // Commands aren't run either, as they could send the event anywhere,
+    // so the JSON they would have received is shown instead.
+    pub async fn command(&self, channel: &str, payload: &Value) {
+        let n = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let value = json!({
+            "channel": channel,
+            "command": payload,
+        });
+
+        self.output(
+            n,
+            &format!("Running the command of {}", channel),
+            &value,
+            None,
+        )
+        .await;
+    }
+
+    async fn output(&self, n: usize, title: &str, value: &Value, preview: Option<String>) {
+        let json = serde_json::to_string_pretty(value).expect("Failed to serialize JSON.");
+        let preview = preview.map_or_else(String::new, |preview| format!("\nPreview: {}", preview));
+
+        if let Some(directory) = &self.directory {
+            let path = directory.join(format!("{}-{}.json", Utc::now().timestamp_millis(), n));
+
+            match tokio::fs::write(&path, json).await {
+                Ok(()) => {
+                    default!("{}: saved to `{}`.{}", title, path.display(), preview);
+                }
+                Err(e) => {
+                    warning!("Failed to save `{}`: {}", path.display(), e);
+                }
+            }
+        } else {
+            // The message is printed all at once, so that the ones sent
+            // at the same time by different tasks don't get mixed up.
+            default!("{}:\n{}{}", title, json, preview);
+        }
+    }
+}
+
+// The webhook's token is enough to send messages to its channel, so it's
+// hidden from the output, which could be shared to show what the config
+// file does. The webhook's ID is kept so that channels can be told apart.
+pub fn redact(url: &str) -> String {
+    if let Some(start) = url.find("/webhooks/").map(|i| i + "/webhooks/".len()) {
+        if let Some(slash) = url[start..].find('/').map(|i| start + i + 1) {
+            let end = url[slash..]
+                .find(['/', '?'])
+                .map_or(url.len(), |i| slash + i);
+
+            return format!("{}[token]{}", &url[..slash], &url[end..]);
+        }
+    }
+
+    url.into()
+}
+
+// This returns a link to Discohook which shows what the message would
+// look like on Discord, and allows it to be edited.
+pub fn preview(msg: &Message) -> String {
+    format!(
+        "https://discohook.org/?data={}",
+        encode_config(
+            format!(
+                "{{\"messages\":[{{\"data\":{}}}]}}",
+                serde_json::to_string(msg).expect("Failed to serialize JSON.")
+            ),
+            URL_SAFE
+        )
+    )
+}
@@ -0,0 +1,157 @@
+// This module contains the events detected by the monitor, which are
+// published to every subscriber as they happen. The program uses them to
+// fill the dashboard's feed, while other programs embedding the library
+// can receive them directly instead of through webhooks.
+
+use crate::products::Product;
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError, Sender};
+
+// Subscribers that fall this many events behind start missing them, so
+// it's large enough to hold every event of a big drop.
+const BUFFER: usize = 1024;
+
+// This is the store an event occurred on. It can also be deserialized,
+// as it's saved next to the responses that are recorded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Store {
+    pub name: String,
+    pub url: String,
+    pub logo: String,
+}
+
+// The events are serialized with their name in the `event` field, using
+// the same names as the rest of the program.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    #[serde(rename = "new")]
+    NewProduct {
+        store: Store,
+        product: Product,
+
+        // This is the handle of the collection the product was found in,
+        // if the store is only monitored through some of its collections.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        collection: Option<String>,
+    },
+    Restock {
+        store: Store,
+        product: Product,
+
+        // These are the IDs of the variants that became available, while
+        // the rest of the available ones were already in stock.
+        restocked: Vec<u64>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        collection: Option<String>,
+    },
+    SoldOut {
+        store: Store,
+        product: Product,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        collection: Option<String>,
+    },
+    PasswordUp {
+        store: Store,
+    },
+    PasswordDown {
+        store: Store,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::NewProduct { .. } => "new",
+            Event::Restock { .. } => "restock",
+            Event::SoldOut { .. } => "sold_out",
+            Event::PasswordUp { .. } => "password_up",
+            Event::PasswordDown { .. } => "password_down",
+        }
+    }
+
+    pub fn store(&self) -> &Store {
+        match self {
+            Event::NewProduct { store, .. }
+            | Event::Restock { store, .. }
+            | Event::SoldOut { store, .. }
+            | Event::PasswordUp { store }
+            | Event::PasswordDown { store } => store,
+        }
+    }
+
+    // Password page events don't refer to a product.
+    pub fn product(&self) -> Option<&Product> {
+        match self {
+            Event::NewProduct { product, .. }
+            | Event::Restock { product, .. }
+            | Event::SoldOut { product, .. } => Some(product),
+            Event::PasswordUp { .. } | Event::PasswordDown { .. } => None,
+        }
+    }
+
+    // Only restocks have variants that became available.
+    pub fn restocked(&self) -> &[u64] {
+        match self {
+            Event::Restock { restocked, .. } => restocked,
+            _ => &[],
+        }
+    }
+
+    pub fn collection(&self) -> Option<&str> {
+        match self {
+            Event::NewProduct { collection, .. }
+            | Event::Restock { collection, .. }
+            | Event::SoldOut { collection, .. } => collection.as_deref(),
+            Event::PasswordUp { .. } | Event::PasswordDown { .. } => None,
+        }
+    }
+
+    // The collection is set once the event is detected, as the detector
+    // only compares the products.
+    pub fn set_collection(&mut self, name: String) {
+        match self {
+            Event::NewProduct { collection, .. }
+            | Event::Restock { collection, .. }
+            | Event::SoldOut { collection, .. } => *collection = Some(name),
+            Event::PasswordUp { .. } | Event::PasswordDown { .. } => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Events {
+    tx: Sender<Event>,
+}
+
+impl Default for Events {
+    fn default() -> Events {
+        let (tx, _) = broadcast::channel(BUFFER);
+        Events { tx }
+    }
+}
+
+impl Events {
+    pub fn publish(&self, event: Event) {
+        // This only fails if nobody is subscribed.
+        let _ = self.tx.send(event);
+    }
+
+    // The stream receives every event published after it's created, and
+    // ends once the `Events` it came from is dropped. If it falls too far
+    // behind, the events it missed are skipped.
+    pub fn subscribe(&self) -> impl Stream<Item = Event> {
+        stream::unfold(self.tx.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
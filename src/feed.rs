@@ -0,0 +1,123 @@
+// This module keeps track of the most recent events detected by the
+// monitor, so that they can be shown on the dashboard served by
+// `server`. New events are also broadcast to every open dashboard, which
+// receives them through server-sent events.
+
+use crate::event::Event;
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use std::{collections::VecDeque, sync::Mutex};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+// This is the number of events a dashboard shows when it's opened.
+const RECENT: usize = 50;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Entry {
+    pub time: String,
+    pub event: &'static str,
+    pub store: String,
+
+    // Password page events don't refer to a product, so they only
+    // include the store's URL.
+    pub title: Option<String>,
+    pub url: String,
+    pub image: Option<String>,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Variant {
+    pub title: String,
+    pub atc_url: String,
+}
+
+impl From<&Event> for Entry {
+    fn from(event: &Event) -> Entry {
+        let store = event.store();
+        let product = event.product();
+
+        Entry {
+            time: Utc::now().to_rfc3339(),
+            event: event.name(),
+            store: store.name.clone(),
+            title: product.map(|product| product.title.clone()),
+            url: if let Some(product) = product {
+                format!("{}/products/{}", store.url, product.handle)
+            } else {
+                store.url.clone()
+            },
+            image: product
+                .and_then(|product| product.images.first().map(|image| image.src.clone())),
+
+            // Only the sizes that can be bought are shown.
+            variants: product
+                .map(|product| {
+                    product
+                        .variants
+                        .iter()
+                        .filter(|variant| variant.available)
+                        .map(|variant| Variant {
+                            title: variant.name(),
+                            atc_url: format!("{}/cart/add?id={}", store.url, variant.id),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Feed {
+    recent: Mutex<VecDeque<Entry>>,
+    tx: Sender<Entry>,
+}
+
+impl Feed {
+    pub fn new() -> Feed {
+        // The buffer only has to hold the events sent while a dashboard
+        // is being written to, so it doesn't have to be large.
+        let (tx, _) = broadcast::channel(RECENT);
+
+        Feed {
+            recent: Mutex::new(VecDeque::with_capacity(RECENT)),
+            tx,
+        }
+    }
+
+    // The feed is filled with the events published by the monitor, until
+    // it stops. Sizes selling out aren't shown, as the dashboard only
+    // lists products that can be bought.
+    pub async fn follow(&self, events: impl Stream<Item = Event>) {
+        futures::pin_mut!(events);
+
+        while let Some(event) = events.next().await {
+            if !matches!(event, Event::SoldOut { .. }) {
+                self.publish(Entry::from(&event));
+            }
+        }
+    }
+
+    pub fn publish(&self, entry: Entry) {
+        let mut recent = self.recent.lock().expect("Failed to lock feed.");
+
+        if recent.len() == RECENT {
+            recent.pop_front();
+        }
+
+        recent.push_back(entry.clone());
+
+        // This only fails if no dashboards are open.
+        let _ = self.tx.send(entry);
+    }
+
+    // The recent events are returned along with the receiver, while the
+    // lock is held, so that no event is either skipped or repeated.
+    pub fn subscribe(&self) -> (Vec<Entry>, Receiver<Entry>) {
+        let recent = self.recent.lock().expect("Failed to lock feed.");
+
+        (recent.iter().cloned().collect(), self.tx.subscribe())
+    }
+}
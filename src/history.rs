@@ -0,0 +1,930 @@
+// This module records every event detected by the monitor, along with
+// the availability of every variant over time, in a SQLite database, so
+// that drops can be analyzed afterwards with the `history` command,
+// exported with the `export` command or summarized in the daily digests
+// sent by `crate::digest`.
+
+// SQLite is accessed through a blocking API, so the database is written
+// to from its own thread, which receives the records from the stores'
+// tasks through a channel and saves them in batches.
+
+use crate::{config, default, error, hidden, monitor::elapsed, success, warning};
+use chrono::prelude::*;
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    process,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+// This is the database used when the config file doesn't include a path.
+const PATH: &str = "history.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    time TEXT NOT NULL,
+    store TEXT NOT NULL,
+    event TEXT NOT NULL,
+    product_id INTEGER,
+    handle TEXT,
+    title TEXT,
+    price TEXT,
+    variants TEXT
+);
+CREATE INDEX IF NOT EXISTS events_product ON events (store, handle, time);
+
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    time TEXT NOT NULL,
+    store TEXT NOT NULL,
+    product_id INTEGER NOT NULL,
+    handle TEXT NOT NULL,
+    variant_id INTEGER NOT NULL,
+    variant TEXT NOT NULL,
+    available INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshots_product ON snapshots (store, handle, time);
+";
+
+#[derive(Debug)]
+pub enum Record {
+    Event(Event),
+    Snapshot(Snapshot),
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub time: DateTime<Utc>,
+    pub store: String,
+    pub event: &'static str,
+
+    // Password page events don't refer to a product.
+    pub product: Option<Product>,
+}
+
+#[derive(Debug)]
+pub struct Product {
+    pub id: u64,
+    pub handle: String,
+    pub title: String,
+    pub price: String,
+
+    // These are the variants that were available, as `(id, title)`.
+    pub variants: Vec<(u64, String)>,
+}
+
+// A snapshot is recorded whenever a variant's availability changes, as
+// well as the first time it's seen, rather than after every request,
+// which would make the database grow way too quickly.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub time: DateTime<Utc>,
+    pub store: String,
+    pub product_id: u64,
+    pub handle: String,
+    pub variant_id: u64,
+    pub variant: String,
+    pub available: bool,
+}
+
+// This is how long `flush()` waits for the records to be saved.
+const FLUSH: Duration = Duration::from_secs(5);
+
+// If the history is disabled, records are simply discarded.
+#[derive(Debug)]
+pub struct History {
+    tx: Option<Sender<Request>>,
+}
+
+// Besides the records, the thread writing to the database receives the
+// requests to let the monitor know once everything was saved.
+#[derive(Debug)]
+enum Request {
+    Record(Record),
+    Flush(Sender<()>),
+}
+
+impl History {
+    pub fn new(history: Option<config::History>) -> History {
+        let history = if let Some(history) = history {
+            history
+        } else {
+            return History { tx: None };
+        };
+
+        let path = path(Some(history));
+
+        let connection = match open(&path) {
+            Ok(connection) => connection,
+            Err(e) => {
+                warning!("Failed to open `{}`: {}", path, e);
+                default!("Events won't be recorded.");
+                return History { tx: None };
+            }
+        };
+
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || write(connection, rx));
+
+        default!("Recording events to `{}`...", path);
+
+        History { tx: Some(tx) }
+    }
+
+    pub fn record(&self, record: Record) {
+        if let Some(tx) = &self.tx {
+            // This can only fail if the thread writing to the database
+            // stopped, which it already warned about.
+            let _ = tx.send(Request::Record(record));
+        }
+    }
+
+    // This function waits until every record sent before it was saved,
+    // so that the latest events aren't lost when the program quits. It
+    // blocks the thread it's called from.
+    pub fn flush(&self) -> bool {
+        if let Some(tx) = &self.tx {
+            let (reply, done) = mpsc::channel();
+
+            tx.send(Request::Flush(reply)).is_ok() && done.recv_timeout(FLUSH).is_ok()
+        } else {
+            true
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+}
+
+pub fn open(path: &str) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+
+    // The database is read by the commands and the digests while the
+    // monitor writes to it, so they wait for each other's transactions
+    // to end instead of failing straight away.
+    connection.busy_timeout(Duration::from_secs(10))?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+fn write(mut connection: Connection, rx: Receiver<Request>) {
+    // The thread waits for a record, then saves it along with any others
+    // that were sent in the meantime in a single transaction, which is
+    // much faster than saving them one by one.
+    while let Ok(request) = rx.recv() {
+        let mut flushes = vec![];
+
+        let result = (|| -> rusqlite::Result<()> {
+            let transaction = connection.transaction()?;
+
+            for request in std::iter::once(request).chain(rx.try_iter()) {
+                match request {
+                    Request::Record(record) => insert(&transaction, record)?,
+                    Request::Flush(reply) => flushes.push(reply),
+                }
+            }
+
+            transaction.commit()
+        })();
+
+        if let Err(e) = result {
+            warning!("Failed to record events: {}", e);
+        } else {
+            for reply in flushes {
+                let _ = reply.send(());
+            }
+        }
+    }
+
+    hidden!("Stopped recording events.");
+}
+
+pub fn insert(connection: &Connection, record: Record) -> rusqlite::Result<()> {
+    match record {
+        Record::Event(event) => {
+            let product = event.product.as_ref();
+
+            connection.execute(
+                "INSERT INTO events (time, store, event, product_id, handle, title, price, variants)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    event.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    event.store,
+                    event.event,
+                    product.map(|product| product.id as i64),
+                    product.map(|product| &product.handle),
+                    product.map(|product| &product.title),
+                    product.map(|product| &product.price),
+                    product.map(|product| {
+                        serde_json::to_string(
+                            &product
+                                .variants
+                                .iter()
+                                .map(|(id, title)| json!({ "id": id, "title": title }))
+                                .collect::<Vec<_>>(),
+                        )
+                        .unwrap_or_default()
+                    }),
+                ],
+            )?;
+        }
+        Record::Snapshot(snapshot) => {
+            connection.execute(
+                "INSERT INTO snapshots (time, store, product_id, handle, variant_id, variant, available)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    snapshot.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    snapshot.store,
+                    snapshot.product_id as i64,
+                    snapshot.handle,
+                    snapshot.variant_id as i64,
+                    snapshot.variant,
+                    snapshot.available,
+                ],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+const USAGE: &str = "Usage: shopify-monitor history <QUERY>
+
+These queries read the database the monitor records events to, which
+requires `history` to be set in the config file:
+
+    events [store] [handle]           List the latest events
+    last <store> <handle> [event]     Show when a product last restocked
+                                      (or had another event)
+    stock <store> <handle> [variant]  Show how long each size stayed in
+                                      stock";
+
+// This function runs the `history` command, which queries the database
+// directly, so the monitor doesn't have to be running.
+pub fn run(args: Vec<String>) {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let query = match args.as_slice() {
+        ["events", ..]
+        | ["last", _, _]
+        | ["last", _, _, _]
+        | ["stock", _, _]
+        | ["stock", _, _, _] => args.as_slice(),
+        _ => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    let (path, connection) = connect();
+
+    let result = match query {
+        ["events", rest @ ..] => events(&connection, rest.first(), rest.get(1)),
+        ["last", store, handle, rest @ ..] => last(
+            &connection,
+            store,
+            handle,
+            rest.first().copied().unwrap_or("restock"),
+        ),
+        ["stock", store, handle, rest @ ..] => stock(&connection, store, handle, rest.first()),
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        error!("Failed to query `{}`: {}", path, e);
+        process::exit(1);
+    }
+}
+
+// This function opens the database selected in the config file for the
+// commands, which can't do anything without it.
+fn connect() -> (String, Connection) {
+    let path = path(config::read().history);
+
+    match read(&path) {
+        Ok(connection) => (path, connection),
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// The commands and the digests only read from the database, so it's
+// opened as read-only, which also keeps them from creating an empty
+// database when the path is wrong.
+pub fn read(path: &str) -> Result<Connection, String> {
+    if !Path::new(path).exists() {
+        return Err(format!(
+            "`{}` doesn't exist! Is `history` set in the config file, and has the monitor been run since?",
+            path
+        ));
+    }
+
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|connection| {
+            connection.busy_timeout(Duration::from_secs(10))?;
+            Ok(connection)
+        })
+        .map_err(|e| format!("Failed to open `{}`: {}", path, e))
+}
+
+pub fn path(history: Option<config::History>) -> String {
+    history
+        .and_then(|history| history.path)
+        .unwrap_or_else(|| PATH.into())
+}
+
+fn events(
+    connection: &Connection,
+    store: Option<&&str>,
+    handle: Option<&&str>,
+) -> rusqlite::Result<()> {
+    let mut statement = connection.prepare(
+        "SELECT time, store, event, title, price, variants FROM events
+         WHERE (?1 IS NULL OR store = ?1 COLLATE NOCASE)
+           AND (?2 IS NULL OR handle = ?2 COLLATE NOCASE)
+         ORDER BY time DESC LIMIT 25",
+    )?;
+
+    let rows = statement.query_map(params![store, handle], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+
+    let mut found = false;
+
+    for row in rows {
+        let (time, store, event, title, price, variants) = row?;
+        found = true;
+
+        if let Some(title) = title {
+            let sizes = variants.as_deref().map(sizes).unwrap_or_default();
+
+            default!(
+                "{} | {} | {} | {} ({}) | {}",
+                local(&time),
+                store,
+                event,
+                title,
+                price.unwrap_or_default(),
+                sizes.join(", ")
+            );
+        } else {
+            default!("{} | {} | {}", local(&time), store, event);
+        }
+    }
+
+    if !found {
+        warning!("No events were found.");
+    }
+
+    Ok(())
+}
+
+fn last(connection: &Connection, store: &str, handle: &str, event: &str) -> rusqlite::Result<()> {
+    let mut statement = connection.prepare(
+        "SELECT time, title FROM events
+         WHERE store = ?1 COLLATE NOCASE AND handle = ?2 COLLATE NOCASE AND event = ?3
+         ORDER BY time DESC LIMIT 1",
+    )?;
+
+    let mut rows = statement.query(params![store, handle, event])?;
+
+    if let Some(row) = rows.next()? {
+        let time = row.get::<_, String>(0)?;
+        let title = row
+            .get::<_, Option<String>>(1)?
+            .unwrap_or_else(|| handle.into());
+
+        default!(
+            "The last `{}` event for `{}` on {} was on {} ({} ago).",
+            event,
+            title,
+            store,
+            local(&time),
+            since(&time)
+        );
+    } else {
+        warning!(
+            "No `{}` events were found for `{}` on {}.",
+            event,
+            handle,
+            store
+        );
+    }
+
+    Ok(())
+}
+
+// A period during which a variant was in stock, which hasn't ended if
+// it still is.
+pub type Period = (String, Option<String>);
+
+fn stock(
+    connection: &Connection,
+    store: &str,
+    handle: &str,
+    variant: Option<&&str>,
+) -> rusqlite::Result<()> {
+    let variants = periods(connection, store, handle, variant.copied())?;
+
+    if variants.is_empty() {
+        warning!("No stock changes were found for `{}` on {}.", handle, store);
+    }
+
+    for (name, periods) in variants {
+        if periods.is_empty() {
+            default!("Size {}: never in stock.", name);
+            continue;
+        }
+
+        default!("Size {}:", name);
+
+        for (start, end) in periods {
+            if let Some(end) = end {
+                default!(
+                    "  {} -> {} ({})",
+                    local(&start),
+                    local(&end),
+                    between(&start, &end)
+                );
+            } else {
+                default!("  {} -> still in stock ({})", local(&start), since(&start));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The snapshots of each variant are read in order, and every time it
+// becomes available a period starts, which ends when it becomes
+// unavailable again.
+pub fn periods(
+    connection: &Connection,
+    store: &str,
+    handle: &str,
+    variant: Option<&str>,
+) -> rusqlite::Result<Vec<(String, Vec<Period>)>> {
+    let mut statement = connection.prepare(
+        "SELECT time, variant, available FROM snapshots
+         WHERE store = ?1 COLLATE NOCASE AND handle = ?2 COLLATE NOCASE
+           AND (?3 IS NULL OR variant = ?3 COLLATE NOCASE)
+         ORDER BY variant_id, time",
+    )?;
+
+    let rows = statement.query_map(params![store, handle, variant], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, bool>(2)?,
+        ))
+    })?;
+
+    // Each variant's periods are collected, in the order the variants
+    // are listed on the store.
+    let mut variants: Vec<(String, Vec<Period>)> = vec![];
+
+    for row in rows {
+        let (time, name, available) = row?;
+
+        if variants.last().map(|(last, _)| last) != Some(&name) {
+            variants.push((name, vec![]));
+        }
+
+        let periods = &mut variants.last_mut().expect("A variant was just added.").1;
+        let open = periods.last().is_some_and(|(_, end)| end.is_none());
+
+        if available && !open {
+            periods.push((time, None));
+        } else if !available && open {
+            if let Some(period) = periods.last_mut() {
+                period.1 = Some(time);
+            }
+        }
+    }
+
+    Ok(variants)
+}
+
+fn parse(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Times are saved in UTC, but shown in the user's timezone.
+fn local(time: &str) -> String {
+    parse(time).map_or_else(
+        || time.into(),
+        |time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        },
+    )
+}
+
+fn between(start: &str, end: &str) -> String {
+    match (parse(start), parse(end)) {
+        (Some(start), Some(end)) => elapsed((end - start).to_std().unwrap_or_default()),
+        _ => "?".into(),
+    }
+}
+
+fn since(time: &str) -> String {
+    between(time, &Utc::now().to_rfc3339())
+}
+
+// The variants of an event are saved as a JSON array, but only their
+// titles are shown.
+fn sizes(variants: &str) -> Vec<String> {
+    serde_json::from_str::<Vec<Value>>(variants)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|variant| variant["title"].as_str().map(String::from))
+        .collect()
+}
+
+const EXPORT_USAGE: &str = "Usage: shopify-monitor export <FILE> [OPTIONS]
+
+Writes the recorded events to a file, as CSV or JSON Lines depending on
+its extension (`.csv` or `.jsonl`), which requires `history` to be set in
+the config file:
+
+    --from <time>     Only export the events that occurred after this
+    --to <time>       Only export the events that occurred before this
+    --store <store>   Only export the events of a store
+    --keyword <word>  Only export the events of products whose title or
+                      handle contains this word
+
+Times can be dates, such as `2021-12-24`, which are in your timezone and
+include the whole day, RFC 3339 timestamps, or durations counted back
+from now, such as `7d` or `12h`.";
+
+#[derive(PartialEq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+// These are the options that select which events are exported. The
+// times are RFC 3339 timestamps in UTC, as they're saved in the database.
+#[derive(Default)]
+pub struct Filters {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub store: Option<String>,
+    pub keyword: Option<String>,
+}
+
+// This function runs the `export` command, which writes the events
+// matching the given filters to a file, so that they can be opened in a
+// spreadsheet or processed by another program.
+pub fn export(args: Vec<String>) {
+    let (file, options) = if let Some((file, options)) = args.split_first() {
+        (file, options)
+    } else {
+        println!("{}", EXPORT_USAGE);
+        return;
+    };
+
+    let format = if file.to_lowercase().ends_with(".csv") {
+        Format::Csv
+    } else if file.to_lowercase().ends_with(".jsonl") {
+        Format::Jsonl
+    } else {
+        error!("`{}` should end with `.csv` or `.jsonl`!", file);
+        return;
+    };
+
+    let mut filters = Filters::default();
+
+    for pair in options.chunks(2) {
+        let (option, value) = match pair {
+            [option, value] => (option.as_str(), value),
+            _ => {
+                println!("{}", EXPORT_USAGE);
+                return;
+            }
+        };
+
+        match option {
+            "--from" | "--to" => {
+                let time = if let Some(time) = bound(value, option == "--to") {
+                    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+                } else {
+                    error!("Invalid time: `{}`!", value);
+                    return;
+                };
+
+                if option == "--from" {
+                    filters.from = Some(time);
+                } else {
+                    filters.to = Some(time);
+                }
+            }
+            "--store" => filters.store = Some(value.clone()),
+            "--keyword" => filters.keyword = Some(value.clone()),
+            _ => {
+                println!("{}", EXPORT_USAGE);
+                return;
+            }
+        }
+    }
+
+    let (path, connection) = connect();
+
+    let result = File::create(file)
+        .map_err(Box::from)
+        .and_then(|file| write_events(&connection, &filters, &format, file));
+
+    match result {
+        Ok(count) => success!("Exported {} events from `{}` to `{}`.", count, path, file),
+        Err(e) => {
+            error!("Failed to export `{}` to `{}`: {}", path, file, e);
+            process::exit(1);
+        }
+    }
+}
+
+// This function writes the events matching the filters in the given
+// format, returning how many of them there were.
+pub fn write_events(
+    connection: &Connection,
+    filters: &Filters,
+    format: &Format,
+    writer: impl Write,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    // The keyword is matched literally, so the characters `LIKE` treats
+    // as wildcards are escaped.
+    let keyword = filters.keyword.as_ref().map(|keyword| {
+        keyword
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    });
+
+    let mut statement = connection.prepare(
+        "SELECT time, store, event, product_id, handle, title, price, variants FROM events
+         WHERE (?1 IS NULL OR time >= ?1) AND (?2 IS NULL OR time < ?2)
+           AND (?3 IS NULL OR store = ?3 COLLATE NOCASE)
+           AND (?4 IS NULL OR title LIKE '%' || ?4 || '%' ESCAPE '\\'
+                OR handle LIKE '%' || ?4 || '%' ESCAPE '\\')
+         ORDER BY time",
+    )?;
+
+    let mut rows = statement.query(params![filters.from, filters.to, filters.store, keyword])?;
+    let mut writer = BufWriter::new(writer);
+    let mut count = 0;
+
+    if *format == Format::Csv {
+        writeln!(
+            writer,
+            "time,store,event,product_id,handle,title,price,sizes"
+        )?;
+    }
+
+    while let Some(row) = rows.next()? {
+        let time = row.get::<_, String>(0)?;
+        let store = row.get::<_, String>(1)?;
+        let event = row.get::<_, String>(2)?;
+        let id = row.get::<_, Option<i64>>(3)?;
+        let handle = row.get::<_, Option<String>>(4)?;
+        let title = row.get::<_, Option<String>>(5)?;
+        let price = row.get::<_, Option<String>>(6)?;
+        let variants = row.get::<_, Option<String>>(7)?;
+
+        if *format == Format::Csv {
+            let fields = [
+                local(&time),
+                store,
+                event,
+                id.map(|id| id.to_string()).unwrap_or_default(),
+                handle.unwrap_or_default(),
+                title.unwrap_or_default(),
+                price.unwrap_or_default(),
+                variants
+                    .as_deref()
+                    .map(sizes)
+                    .unwrap_or_default()
+                    .join(", "),
+            ];
+
+            writeln!(
+                writer,
+                "{}",
+                fields
+                    .iter()
+                    .map(|field| csv(field))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )?;
+        } else {
+            // Unlike the CSV file, which is meant to be read by
+            // people, the JSON one keeps the times in UTC and the
+            // variants' IDs.
+            let line = json!({
+                "time": time,
+                "store": store,
+                "event": event,
+                "product_id": id,
+                "handle": handle,
+                "title": title,
+                "price": price,
+                "variants": variants
+                    .and_then(|variants| serde_json::from_str::<Value>(&variants).ok())
+                    .unwrap_or_else(|| json!([])),
+            });
+
+            writeln!(writer, "{}", line)?;
+        }
+
+        count += 1;
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
+
+// Fields containing commas, quotes or line breaks have to be quoted, and
+// the quotes within them doubled.
+pub fn csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+// This function parses the times passed to `--from` and `--to`. If a
+// date is passed to `--to`, the whole day is included, so the events are
+// exported until the start of the next one.
+pub fn bound(value: &str, end: bool) -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+
+    if let Some(days) = value.strip_suffix('d').and_then(|n| n.parse().ok()) {
+        return Some(now - chrono::Duration::days(days));
+    }
+
+    if let Some(hours) = value.strip_suffix('h').and_then(|n| n.parse().ok()) {
+        return Some(now - chrono::Duration::hours(hours));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if end { date.succ_opt()? } else { date };
+
+        return Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc));
+    }
+
+    parse(value)
+}
+
+// These are the statistics sent in a channel's daily digest, which only
+// include its stores.
+#[derive(Debug)]
+pub struct Summary {
+    pub stores: Vec<StoreSummary>,
+    pub restocked: Vec<Restocked>,
+}
+
+#[derive(Debug)]
+pub struct StoreSummary {
+    pub name: String,
+    pub new: u64,
+    pub restocks: u64,
+
+    // How long the password page was up for during the period.
+    pub password: Duration,
+}
+
+#[derive(Debug)]
+pub struct Restocked {
+    pub store: String,
+    pub handle: String,
+    pub title: String,
+    pub restocks: u64,
+}
+
+// This function summarizes the events that occurred on the given stores
+// between `from` and `to`, including the `top` products that restocked
+// the most times.
+pub fn summary(
+    connection: &Connection,
+    stores: &[String],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    top: usize,
+) -> rusqlite::Result<Summary> {
+    let start = from.to_rfc3339_opts(SecondsFormat::Millis, true);
+    let end = to.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    let mut summary = Summary {
+        stores: vec![],
+        restocked: vec![],
+    };
+
+    for store in stores {
+        let mut statement = connection.prepare_cached(
+            "SELECT event, COUNT(*) FROM events
+             WHERE store = ?1 AND time >= ?2 AND time < ?3 GROUP BY event",
+        )?;
+
+        let mut new = 0;
+        let mut restocks = 0;
+
+        for row in statement.query_map(params![store, start, end], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+        })? {
+            match row? {
+                (event, count) if event == "new" => new = count,
+                (event, count) if event == "restock" => restocks = count,
+                _ => {}
+            }
+        }
+
+        // The password page's state at the start of the period is the
+        // one set by the last event before it, and every event during it
+        // ends or starts a period during which it was up.
+        let mut statement = connection.prepare_cached(
+            "SELECT time, event FROM events
+             WHERE store = ?1 AND event IN ('password_up', 'password_down')
+               AND time < ?3 AND (time >= ?2 OR id = (
+                   SELECT id FROM events
+                   WHERE store = ?1 AND event IN ('password_up', 'password_down') AND time < ?2
+                   ORDER BY time DESC LIMIT 1
+               ))
+             ORDER BY time",
+        )?;
+
+        let mut password = chrono::Duration::zero();
+        let mut up: Option<DateTime<Utc>> = None;
+
+        for row in statement.query_map(params![store, start, end], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (time, event) = row?;
+            let time = parse(&time).unwrap_or(from).max(from);
+
+            if event == "password_up" {
+                up = up.or(Some(time));
+            } else if let Some(since) = up.take() {
+                password += time - since;
+            }
+        }
+
+        if let Some(since) = up {
+            password += to - since;
+        }
+
+        summary.stores.push(StoreSummary {
+            name: store.clone(),
+            new,
+            restocks,
+            password: password.to_std().unwrap_or_default(),
+        });
+    }
+
+    // SQLite doesn't support binding lists, so the stores are passed as a
+    // JSON array instead.
+    let mut statement = connection.prepare(
+        "SELECT store, handle, MAX(title), COUNT(*) AS restocks FROM events
+         WHERE event = 'restock' AND time >= ?1 AND time < ?2
+           AND store IN (SELECT value FROM json_each(?3))
+         GROUP BY store, handle ORDER BY restocks DESC, MAX(time) DESC LIMIT ?4",
+    )?;
+
+    for row in statement.query_map(
+        params![start, end, json!(stores).to_string(), top as i64],
+        |row| {
+            Ok(Restocked {
+                store: row.get(0)?,
+                handle: row.get(1)?,
+                title: row.get(2)?,
+                restocks: row.get(3)?,
+            })
+        },
+    )? {
+        summary.restocked.push(row?);
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,53 @@
+// This is the library behind the monitor, which can also be used by
+// other programs that only need to poll Shopify stores and find out what
+// changed, without sending any webhooks. The public API is made up of
+// the following modules:
+//
+// - `poller`, which fetches a store's products, and can poll it
+//   repeatedly through `watch()`, returning a stream of events;
+// - `diff`, which compares two lists of products using the same rules
+//   as the monitor;
+// - `event`, containing the events detected by the monitor, and the
+//   `Events` type used to subscribe to them;
+// - `products`, with the data returned by Shopify's `/products.json`
+//   endpoint.
+
+pub mod diff;
+pub mod event;
+pub mod poller;
+pub mod products;
+
+// `cli` is the entry point used by the binary, in `main.rs`, to run the
+// monitor and its commands.
+pub mod cli;
+
+// The rest of the modules are internal to the monitor, and can change at
+// any time.
+pub(crate) mod alternative;
+pub(crate) mod client;
+pub(crate) mod command;
+pub(crate) mod config;
+pub(crate) mod control;
+pub(crate) mod cooldown;
+pub(crate) mod digest;
+pub(crate) mod discovery;
+pub(crate) mod dry_run;
+pub(crate) mod feed;
+pub(crate) mod history;
+pub(crate) mod message;
+pub(crate) mod metrics;
+pub(crate) mod monitor;
+pub(crate) mod render;
+pub(crate) mod replay;
+pub(crate) mod router;
+pub(crate) mod sample;
+pub(crate) mod schedule;
+pub(crate) mod server;
+pub(crate) mod shutdown;
+pub(crate) mod stores;
+pub(crate) mod template;
+pub(crate) mod watchlist;
+pub(crate) mod webhook;
+
+mod log;
+mod tests;
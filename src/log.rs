@@ -77,8 +77,8 @@ macro_rules! default {
         // and the program stopped working. This little function, whose
         // absence hadn't caused any issues until now, is my new hero.
         let msg = std::format_args!($($arg)*).to_string();
-        crate::log!(msg);
-        crate::file!("[DEFAULT] {}", msg);
+        $crate::log!(msg);
+        $crate::file!("[DEFAULT] {}", msg);
     };
 }
 
@@ -94,8 +94,8 @@ macro_rules! success {
         use colored::Colorize;
 
         let msg = std::format_args!($($arg)*).to_string();
-        crate::log!(msg.green());
-        crate::file!("[SUCCESS] {}", msg);
+        $crate::log!(msg.green());
+        $crate::file!("[SUCCESS] {}", msg);
     });
 }
 
@@ -107,8 +107,8 @@ macro_rules! warning {
         use colored::Colorize;
 
         let msg = std::format_args!($($arg)*).to_string();
-        crate::log!(msg.yellow());
-        crate::file!("[WARNING] {}", msg);
+        $crate::log!(msg.yellow());
+        $crate::file!("[WARNING] {}", msg);
     });
 }
 
@@ -121,8 +121,8 @@ macro_rules! error {
         use colored::Colorize;
 
         let msg = std::format_args!($($arg)*).to_string();
-        crate::log!(msg.red());
-        crate::file!("[ERROR] {}", msg);
+        $crate::log!(msg.red());
+        $crate::file!("[ERROR] {}", msg);
     });
 }
 
@@ -136,8 +136,8 @@ macro_rules! important {
         use colored::Colorize;
 
         let msg = std::format_args!($($arg)*).to_string();
-        crate::log!(msg.blue());
-        crate::file!("[IMPORTANT] {}", msg);
+        $crate::log!(msg.blue());
+        $crate::file!("[IMPORTANT] {}", msg);
     });
 }
 
@@ -163,9 +163,9 @@ macro_rules! hidden {
             // monitor is run on.
             /* crate::log!(msg.truecolor(255, 170, 0)); */
 
-            crate::log!(msg.purple());
+            $crate::log!(msg.purple());
         }
 
-        crate::file!("[HIDDEN] {}", std::format_args!($($arg)*));
+        $crate::file!("[HIDDEN] {}", std::format_args!($($arg)*));
     };
 }
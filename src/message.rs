@@ -25,6 +25,20 @@ pub struct Message {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub avatar_url: Option<String>,
+
+    // If this isn't included, Discord pings everyone mentioned in the
+    // message's content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mentions: Option<AllowedMentions>,
+}
+
+// Only the roles and users listed here are actually pinged, while
+// `parse` can contain "everyone" to allow `@everyone` and `@here`.
+#[derive(Serialize)]
+pub struct AllowedMentions {
+    pub parse: Vec<String>,
+    pub roles: Vec<String>,
+    pub users: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -101,3 +115,259 @@ pub struct Image {
 pub struct Thumbnail {
     pub url: String,
 }
+
+// Discord rejects any message that exceeds one of these limits with a
+// `400 Bad Request` status, so they have to be enforced before sending
+// it. Their values can be found in Discord's documentation:
+// https://discord.com/developers/docs/resources/channel#embed-object-embed-limits
+pub const CONTENT_LIMIT: usize = 2000;
+pub const USERNAME_LIMIT: usize = 80;
+pub const EMBEDS_LIMIT: usize = 10;
+pub const TITLE_LIMIT: usize = 256;
+pub const DESCRIPTION_LIMIT: usize = 4096;
+pub const FIELDS_LIMIT: usize = 25;
+pub const FIELD_NAME_LIMIT: usize = 256;
+pub const FIELD_VALUE_LIMIT: usize = 1024;
+pub const FOOTER_LIMIT: usize = 2048;
+pub const AUTHOR_LIMIT: usize = 256;
+
+// The sum of the characters in every embed's title, description, field
+// names and values, footer text and author name can't exceed this.
+pub const TOTAL_LIMIT: usize = 6000;
+
+// When a product has too many sizes to fit in one embed, they're split
+// into multiple ones with this many fields each. It's lower than the
+// actual limit so that the rows of three inline fields stay aligned.
+const FIELDS_PER_EMBED: usize = 24;
+
+impl Message {
+    // This function makes sure that the message can be sent, shortening
+    // any text that's too long and moving the fields that don't fit in
+    // an embed to additional ones. If the message is still too long,
+    // the last fields are removed and replaced with one that says how
+    // many were left out.
+    pub fn fit(mut self) -> Message {
+        self.content = self.content.map(|text| truncate(text, CONTENT_LIMIT));
+        self.username = self.username.map(|text| truncate(text, USERNAME_LIMIT));
+
+        if let Some(embeds) = self.embeds.take() {
+            let mut fitted = vec![];
+
+            for embed in embeds {
+                fitted.append(&mut embed.fit());
+            }
+
+            // Fields are removed starting from the last embed, as the
+            // first ones hold the most important information.
+            let mut removed = 0;
+
+            while fitted.len() > EMBEDS_LIMIT {
+                removed += pop(&mut fitted);
+            }
+
+            while fitted.iter().map(Embed::length).sum::<usize>() > TOTAL_LIMIT {
+                let last = fitted.len() - 1;
+
+                if let Some(fields) = &mut fitted[last].fields {
+                    if fields.pop().is_some() {
+                        removed += 1;
+
+                        if fields.is_empty() && last > 0 {
+                            pop(&mut fitted);
+                        }
+
+                        continue;
+                    }
+                }
+
+                // If there aren't any fields left to remove, the embed's
+                // description is shortened as a last resort.
+                let excess = fitted.iter().map(Embed::length).sum::<usize>() - TOTAL_LIMIT;
+
+                if let Some(text) = fitted[last].description.take() {
+                    let length = text.chars().count();
+
+                    if length > excess {
+                        fitted[last].description = Some(truncate(text, length - excess));
+                    }
+                } else if last > 0 {
+                    removed += pop(&mut fitted);
+                } else {
+                    break;
+                }
+            }
+
+            if removed > 0 {
+                let note = Field {
+                    name: '⠀'.into(),
+                    value: format!("+{} more", removed),
+                    inline: Some(false),
+                };
+
+                let last = fitted.len() - 1;
+
+                // The note is only added if there's room for it.
+                if fitted.iter().map(Embed::length).sum::<usize>() + note.length() <= TOTAL_LIMIT {
+                    fitted[last].fields.get_or_insert_with(Vec::new).push(note);
+                }
+            }
+
+            self.embeds = Some(fitted);
+        }
+
+        self
+    }
+}
+
+// This removes the last embed, returning how many fields it had. Since
+// only the last embed has a footer, timestamp and image, they're moved
+// to the one before it, unless it already has its own.
+fn pop(embeds: &mut Vec<Embed>) -> usize {
+    let removed = match embeds.pop() {
+        Some(embed) => embed,
+        None => return 0,
+    };
+
+    if let Some(last) = embeds.last_mut() {
+        last.footer = last.footer.take().or(removed.footer);
+        last.timestamp = last.timestamp.take().or(removed.timestamp);
+        last.image = last.image.take().or(removed.image);
+    }
+
+    removed.fields.map_or(0, |fields| fields.len())
+}
+
+impl Embed {
+    // An embed is split into multiple ones if it contains too many
+    // fields. The title, author and thumbnail are only kept in the
+    // first one, while the footer, timestamp and image are moved to the
+    // last one, so that the embeds look like a single, longer one.
+    fn fit(mut self) -> Vec<Embed> {
+        self.title = self.title.map(|text| truncate(text, TITLE_LIMIT));
+        self.description = self
+            .description
+            .map(|text| truncate(text, DESCRIPTION_LIMIT));
+
+        if let Some(author) = &mut self.author {
+            author.name = truncate(author.name.clone(), AUTHOR_LIMIT);
+        }
+
+        if let Some(footer) = &mut self.footer {
+            footer.text = footer.text.take().map(|text| truncate(text, FOOTER_LIMIT));
+        }
+
+        let mut fields = self.fields.take().unwrap_or_default();
+
+        for field in fields.iter_mut() {
+            field.name = truncate(field.name.clone(), FIELD_NAME_LIMIT);
+            field.value = truncate(field.value.clone(), FIELD_VALUE_LIMIT);
+        }
+
+        if fields.len() <= FIELDS_LIMIT {
+            self.fields = if fields.is_empty() {
+                None
+            } else {
+                Some(fields)
+            };
+            return vec![self];
+        }
+
+        let mut chunks = vec![];
+
+        while !fields.is_empty() {
+            let rest = fields.split_off(FIELDS_PER_EMBED.min(fields.len()));
+            chunks.push(fields);
+            fields = rest;
+        }
+
+        let footer = self.footer.take();
+        let timestamp = self.timestamp.take();
+        let image = self.image.take();
+        let color = self.color;
+
+        let mut chunks = chunks.into_iter();
+        self.fields = chunks.next();
+
+        let mut embeds = vec![self];
+
+        for chunk in chunks {
+            embeds.push(Embed {
+                fields: Some(chunk),
+                ..Embed::empty(color)
+            });
+        }
+
+        if let Some(last) = embeds.last_mut() {
+            last.footer = footer;
+            last.timestamp = timestamp;
+            last.image = image;
+        }
+
+        embeds
+    }
+
+    fn empty(color: Option<u32>) -> Embed {
+        Embed {
+            title: None,
+            description: None,
+            url: None,
+            color,
+            fields: None,
+            author: None,
+            footer: None,
+            timestamp: None,
+            image: None,
+            thumbnail: None,
+        }
+    }
+
+    // This is the number of characters that count towards the
+    // `TOTAL_LIMIT`.
+    pub fn length(&self) -> usize {
+        let mut length = 0;
+
+        if let Some(title) = &self.title {
+            length += title.chars().count();
+        }
+
+        if let Some(description) = &self.description {
+            length += description.chars().count();
+        }
+
+        if let Some(fields) = &self.fields {
+            length += fields.iter().map(Field::length).sum::<usize>();
+        }
+
+        if let Some(Footer {
+            text: Some(text), ..
+        }) = &self.footer
+        {
+            length += text.chars().count();
+        }
+
+        if let Some(author) = &self.author {
+            length += author.name.chars().count();
+        }
+
+        length
+    }
+}
+
+impl Field {
+    fn length(&self) -> usize {
+        self.name.chars().count() + self.value.chars().count()
+    }
+}
+
+// Text that's too long is cut short and ends with an ellipsis, so that
+// it's clear that it was shortened. Characters are counted instead of
+// bytes, as that's what Discord does.
+pub fn truncate(text: String, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text;
+    }
+
+    let mut short = text.chars().take(limit - 1).collect::<String>();
+    short.push('…');
+    short
+}
@@ -0,0 +1,391 @@
+// This module keeps track of what the monitor is doing, so that it can
+// be exposed in the Prometheus text format through the `/metrics`
+// endpoint, and used to alert users if a store stops being polled.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+// The upper bounds, in seconds, of the buckets of the latency histogram.
+// Shopify usually replies within a few hundred milliseconds, so most of
+// the buckets are concentrated below one second.
+const BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0, 10.0];
+
+// Most values are updated by each store's task, and only read when the
+// endpoint is requested, so a `Mutex` is used for the ones that are
+// grouped by store, while the global counters are atomic.
+#[derive(Default)]
+pub struct Metrics {
+    // A `BTreeMap` is used so that the stores are always listed in the
+    // same order.
+    stores: Mutex<BTreeMap<String, Store>>,
+
+    // These mirror the counters kept by the background task in
+    // `monitor::run()`.
+    pub amount: AtomicUsize,
+    pub offline: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Store {
+    polls: u64,
+    buckets: [u64; BUCKETS.len()],
+    latency: f64,
+    statuses: BTreeMap<u16, u64>,
+    rate_limits: u64,
+    online: bool,
+
+    // This is a Unix timestamp, in seconds.
+    last_poll: f64,
+    events: HashMap<&'static str, u64>,
+
+    // These are the messages that weren't sent because the sizes they
+    // were about were still cooling down.
+    suppressed: u64,
+
+    bytes: u64,
+
+    // These are the responses that weren't parsed, as the store's
+    // products hadn't changed.
+    unchanged: u64,
+
+    // These are the webhooks sent for the store's events, by result.
+    webhooks: BTreeMap<&'static str, u64>,
+}
+
+impl Metrics {
+    // Stores are registered when they start being monitored, so that
+    // they're listed even before they're polled for the first time.
+    pub fn register(&self, store: &str) {
+        self.stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .entry(store.into())
+            .or_default()
+            .online = true;
+    }
+
+    pub fn unregister(&self, store: &str) {
+        self.stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .remove(store);
+    }
+
+    // This is called after every request, with the response's status
+    // code, or `None` if the store couldn't be reached.
+    pub fn poll(&self, store: &str, latency: Duration, status: Option<u16>) {
+        let mut stores = self.stores.lock().expect("Failed to lock metrics.");
+        let metrics = stores.entry(store.into()).or_default();
+        let seconds = latency.as_secs_f64();
+
+        metrics.polls += 1;
+        metrics.latency += seconds;
+        metrics.last_poll = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+
+        for (i, bound) in BUCKETS.iter().enumerate() {
+            if seconds <= *bound {
+                metrics.buckets[i] += 1;
+            }
+        }
+
+        if let Some(status) = status {
+            metrics.online = true;
+            *metrics.statuses.entry(status).or_default() += 1;
+
+            if status == 429 {
+                metrics.rate_limits += 1;
+            }
+        } else {
+            metrics.online = false;
+        }
+    }
+
+    pub fn event(&self, store: &str, event: &'static str) {
+        *self
+            .stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .entry(store.into())
+            .or_default()
+            .events
+            .entry(event)
+            .or_default() += 1;
+    }
+
+    // This is called for every response, with the size of its body.
+    pub fn download(&self, store: &str, bytes: usize, unchanged: bool) {
+        let mut stores = self.stores.lock().expect("Failed to lock metrics.");
+        let metrics = stores.entry(store.into()).or_default();
+
+        metrics.bytes += bytes as u64;
+
+        if unchanged {
+            metrics.unchanged += 1;
+        }
+    }
+
+    pub fn suppress(&self, store: &str, amount: u64) {
+        self.stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .entry(store.into())
+            .or_default()
+            .suppressed += amount;
+    }
+
+    // This is called once for every request sent to a webhook, with
+    // its result (`sent`, `failed`, `rate_limited` or `invalid`).
+    pub fn webhook(&self, store: &str, result: &'static str) {
+        *self
+            .stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .entry(store.into())
+            .or_default()
+            .webhooks
+            .entry(result)
+            .or_default() += 1;
+    }
+
+    // The monitor is considered healthy as long as at least one store
+    // can be reached, which is the same check used by the background
+    // task to detect when the program is offline.
+    pub fn healthy(&self) -> bool {
+        let amount = self.amount.load(Ordering::Relaxed);
+        amount > 0 && self.offline.load(Ordering::Relaxed) < amount
+    }
+
+    // These totals are shown when the monitor shuts down. They don't
+    // include the stores that were removed through the control API, as
+    // their metrics are discarded.
+    pub fn stores(&self) -> usize {
+        self.stores.lock().expect("Failed to lock metrics.").len()
+    }
+
+    pub fn events(&self) -> u64 {
+        self.stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .values()
+            .flat_map(|store| store.events.values())
+            .sum()
+    }
+
+    pub fn suppressed(&self) -> u64 {
+        self.stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .values()
+            .map(|store| store.suppressed)
+            .sum()
+    }
+
+    pub fn webhooks(&self, result: &str) -> u64 {
+        self.stores
+            .lock()
+            .expect("Failed to lock metrics.")
+            .values()
+            .filter_map(|store| store.webhooks.get(result))
+            .sum()
+    }
+
+    pub fn render(&self) -> String {
+        let stores = self.stores.lock().expect("Failed to lock metrics.");
+        let mut text = String::new();
+
+        // Writing to a `String` can't fail, so the results are ignored.
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_polls_total Requests sent to each store.\n# TYPE shopify_monitor_polls_total counter"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_polls_total{{store=\"{}\"}} {}",
+                escape(name),
+                store.polls
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_poll_duration_seconds Time taken by each store to respond.\n# TYPE shopify_monitor_poll_duration_seconds histogram"
+        );
+        for (name, store) in stores.iter() {
+            let name = escape(name);
+
+            for (i, bound) in BUCKETS.iter().enumerate() {
+                let _ = writeln!(
+                    text,
+                    "shopify_monitor_poll_duration_seconds_bucket{{store=\"{}\",le=\"{}\"}} {}",
+                    name, bound, store.buckets[i]
+                );
+            }
+
+            let _ = writeln!(
+                text,
+                "shopify_monitor_poll_duration_seconds_bucket{{store=\"{}\",le=\"+Inf\"}} {}\nshopify_monitor_poll_duration_seconds_sum{{store=\"{}\"}} {}\nshopify_monitor_poll_duration_seconds_count{{store=\"{}\"}} {}",
+                name, store.polls, name, store.latency, name, store.polls
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_responses_total Responses received from each store, by status code.\n# TYPE shopify_monitor_responses_total counter"
+        );
+        for (name, store) in stores.iter() {
+            for (status, count) in store.statuses.iter() {
+                let _ = writeln!(
+                    text,
+                    "shopify_monitor_responses_total{{store=\"{}\",status=\"{}\"}} {}",
+                    escape(name),
+                    status,
+                    count
+                );
+            }
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_rate_limits_total Requests rejected by each store with a 429 status code.\n# TYPE shopify_monitor_rate_limits_total counter"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_rate_limits_total{{store=\"{}\"}} {}",
+                escape(name),
+                store.rate_limits
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_downloaded_bytes_total Bytes received from each store.\n# TYPE shopify_monitor_downloaded_bytes_total counter"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_downloaded_bytes_total{{store=\"{}\"}} {}",
+                escape(name),
+                store.bytes
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_unchanged_total Responses from each store whose products hadn't changed, which weren't parsed.\n# TYPE shopify_monitor_unchanged_total counter"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_unchanged_total{{store=\"{}\"}} {}",
+                escape(name),
+                store.unchanged
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_store_online Whether each store could be reached the last time it was polled.\n# TYPE shopify_monitor_store_online gauge"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_store_online{{store=\"{}\"}} {}",
+                escape(name),
+                store.online as u8
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_last_poll_timestamp_seconds When each store was last polled.\n# TYPE shopify_monitor_last_poll_timestamp_seconds gauge"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_last_poll_timestamp_seconds{{store=\"{}\"}} {}",
+                escape(name),
+                store.last_poll
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_events_total Events detected on each store.\n# TYPE shopify_monitor_events_total counter"
+        );
+        for (name, store) in stores.iter() {
+            // The events are sorted so that the output doesn't change
+            // order between requests.
+            let mut events = store.events.iter().collect::<Vec<_>>();
+            events.sort();
+
+            for (event, count) in events {
+                let _ = writeln!(
+                    text,
+                    "shopify_monitor_events_total{{store=\"{}\",event=\"{}\"}} {}",
+                    escape(name),
+                    event,
+                    count
+                );
+            }
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_suppressed_total Messages not sent for each store because their sizes were cooling down.\n# TYPE shopify_monitor_suppressed_total counter"
+        );
+        for (name, store) in stores.iter() {
+            let _ = writeln!(
+                text,
+                "shopify_monitor_suppressed_total{{store=\"{}\"}} {}",
+                escape(name),
+                store.suppressed
+            );
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_webhooks_total Webhooks sent for each store's events, by result.\n# TYPE shopify_monitor_webhooks_total counter"
+        );
+        for (name, store) in stores.iter() {
+            for (result, count) in store.webhooks.iter() {
+                let _ = writeln!(
+                    text,
+                    "shopify_monitor_webhooks_total{{store=\"{}\",result=\"{}\"}} {}",
+                    escape(name),
+                    result,
+                    count
+                );
+            }
+        }
+
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_stores Stores being monitored.\n# TYPE shopify_monitor_stores gauge\nshopify_monitor_stores {}",
+            self.amount.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            text,
+            "# HELP shopify_monitor_stores_offline Stores that can't currently be reached.\n# TYPE shopify_monitor_stores_offline gauge\nshopify_monitor_stores_offline {}",
+            self.offline.load(Ordering::Relaxed)
+        );
+
+        text
+    }
+}
+
+// Label values have to escape backslashes, quotes and new lines.
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
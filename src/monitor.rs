@@ -1,32 +1,70 @@
 // This is where the logic for the actual monitor will be.
 
 use crate::{
-    default, error, hidden,
+    command,
+    control::{Control, Controller, Handle},
+    cooldown::Cooldowns,
+    default,
+    diff::Detector,
+    discovery::Discovery,
+    dry_run::DryRun,
+    error,
+    event::{self, Event, Events},
+    hidden,
+    history::{self, History, Record, Snapshot},
     message::*,
-    products::{File, Product},
-    stores::Store,
+    metrics::Metrics,
+    poller::Poller,
+    render::{self, available_product, Item, Posted},
+    replay::Recorder,
+    router::Router,
+    stores::{Channel, Store},
     success, warning,
+    watchlist::Watchlist,
     webhook::{self, Status},
 };
-use base64::{encode_config, URL_SAFE};
 use chrono::prelude::*;
 use futures::future::join_all;
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
 use tokio::{
     sync::{
-        mpsc::{self, Sender},
-        oneshot, RwLock,
+        mpsc::{self, Receiver, Sender},
+        oneshot, watch, RwLock,
     },
     task,
-    time::{self, sleep},
+    time::{self, sleep, Interval},
 };
 
-pub async fn run(stores: Vec<Store>) {
+// This is how long the monitor waits for the stores to stop and for the
+// webhooks that are being sent to finish when it's shut down.
+const DRAIN: Duration = Duration::from_secs(15);
+
+// This function returns `true` if the monitor was stopped by `shutdown`,
+// rather than because it had nothing left to monitor.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    stores: Vec<Store>,
+    metrics: Arc<Metrics>,
+    controller: Arc<Controller>,
+    events: Arc<Events>,
+    history: Arc<History>,
+    recorder: Option<Arc<Recorder>>,
+    dry_run: Option<Arc<DryRun>>,
+    mut added: Receiver<Store>,
+    mut shutdown: watch::Receiver<bool>,
+) -> bool {
+    let started = Instant::now();
+
     // This variable will keep track of the number of stores being
     // monitored so that the program can quit if it drops to zero.
     let amount = stores.len();
 
     default!("Monitoring {} stores...", amount);
+    metrics.amount.store(amount, Ordering::Relaxed);
 
     // While after some testing I realized that collecting tasks in a
     // vector and `join`ing them is not necessary for them to run, the
@@ -51,6 +89,17 @@ pub async fn run(stores: Vec<Store>) {
     // immediately.
     let broken_webhooks = Arc::new(RwLock::new(vec![]));
 
+    // The messages sent to channels which have `edit` enabled are kept
+    // track of here, so that they can be edited later on instead of
+    // sending new ones. Since the same product could be listed in
+    // multiple channels, each message is identified by both the webhook
+    // URL and the product ID.
+    let messages: Messages = Arc::new(RwLock::new(HashMap::new()));
+
+    // The sizes that were sent to each channel cool down for as long as
+    // its `cooldown` setting says, across every store.
+    let cooldowns = Arc::new(Cooldowns::default());
+
     // This channel will be used to allow monitoring tasks to
     // communicate with a background task. While more functionality will
     // be added to it in the future (such as detecting any updates to
@@ -77,6 +126,7 @@ pub async fn run(stores: Vec<Store>) {
     {
         let amount = amount.clone();
         let broken_webhooks = broken_webhooks.clone();
+        let metrics = metrics.clone();
 
         // This "background task" receives updates from the other tasks
         // through a `mpsc` channel and handles them so that the monitor
@@ -93,25 +143,48 @@ pub async fn run(stores: Vec<Store>) {
                     }
 
                     // Site Updates
-                    Update::Site(SiteUpdate::Online, _) => {
+                    Update::Site(SiteUpdate::Online, name) => {
+                        hidden!("{} is no longer offline.", name);
                         offline -= 1;
+                        metrics.offline.store(offline, Ordering::Relaxed);
 
                         if offline == 0 {
                             success!("All sites are are back online!");
                         }
                     }
 
-                    Update::Site(SiteUpdate::Offline, _) => {
+                    Update::Site(SiteUpdate::Offline, name) => {
+                        hidden!("{} went offline.", name);
                         offline += 1;
+                        metrics.offline.store(offline, Ordering::Relaxed);
 
                         if offline == *amount.read().await {
                             error!("The program seems not to be connected to the Internet!");
                         }
                     }
 
+                    Update::Site(SiteUpdate::Stopped, name) => {
+                        hidden!("{} stopped while offline.", name);
+                        offline -= 1;
+                        metrics.offline.store(offline, Ordering::Relaxed);
+                    }
+
                     // Webhook Updates
-                    Update::Webhook(_, url) => {
+                    Update::Webhook(WebhookUpdate::Sent, _, store) => {
+                        metrics.webhook(&store, "sent");
+                    }
+
+                    Update::Webhook(WebhookUpdate::RateLimited, _, store) => {
+                        metrics.webhook(&store, "rate_limited");
+                    }
+
+                    Update::Webhook(WebhookUpdate::Failed, _, store) => {
+                        metrics.webhook(&store, "failed");
+                    }
+
+                    Update::Webhook(WebhookUpdate::Invalid, url, store) => {
                         warning!("Invalid webhook: {}!", url);
+                        metrics.webhook(&store, "invalid");
 
                         // If the webhook is invalid, it's added to this vector so
                         // that the program will stop sending requests to it.
@@ -129,31 +202,103 @@ pub async fn run(stores: Vec<Store>) {
         }));
     }
 
-    for store in stores {
+    // Each store stops when this receiver's value changes, as it's a
+    // copy of the one `shutdown` is received from.
+    let stop = shutdown.clone();
+
+    // Every store is monitored by its own task, which is started by this
+    // closure, as it's used both for the stores included in the config
+    // file and for the ones added through the control API later on.
+    let watch = |store: Store| {
         let broken_webhooks = broken_webhooks.clone();
         let tx = tx.clone();
         let amount = amount.clone();
+        let metrics = metrics.clone();
+        let controller = controller.clone();
+        let history = history.clone();
+        let recorder = recorder.clone();
+        let mut stop = stop.clone();
+
+        metrics.register(&store.name);
+
+        // The store's task receives the messages sent through the
+        // control API from here.
+        let (handle, mut control) = controller.register(&store);
+
+        // Once they're detected, events are handled by the dispatcher,
+        // which finds the channels they should be sent to through the
+        // store's router.
+        let router = Router::new(&store);
+        let dispatcher = Arc::new(Dispatcher {
+            router: router.clone(),
+            controller: controller.clone(),
+            metrics: metrics.clone(),
+            events: events.clone(),
+            history: history.clone(),
+            messages: messages.clone(),
+            cooldowns: cooldowns.clone(),
+            broken_webhooks: broken_webhooks.clone(),
+            dry_run: dry_run.clone(),
+            tx: tx.clone(),
+        });
+
+        task::spawn(async move {
+            let mut poller = Poller::new(&store.url)
+                .collections(store.collections.clone())
+                .full(store.full);
+
+            // The products on the store's watchlist, and the sources used
+            // to discover new products, are polled by their own tasks, at
+            // their own delay, which are stopped along with this one.
+            let mut secondary = vec![];
+
+            if !store.watchlist.is_empty() {
+                secondary.push(task::spawn(poll_secondary(
+                    Secondary::Watchlist(Watchlist::new(
+                        &store.url,
+                        site(&store),
+                        store.watchlist.clone(),
+                    )),
+                    store.watchlist_delay,
+                    dispatcher.clone(),
+                    handle.clone(),
+                )));
+            }
 
-        // These vectors contain all channels the monitor should send
-        // webhooks to, divided by the type of events included.
-        let restock = Arc::clone(&store.restock);
-        let password_up = Arc::clone(&store.password_up);
-        let password_down = Arc::clone(&store.password_down);
-
-        tasks.push(task::spawn(async move {
-            let client = reqwest::Client::new();
+            // The products listed by `/products.json` are shared with the
+            // discovery task, so that they aren't announced twice.
+            let listed = Arc::new(std::sync::Mutex::new(HashSet::new()));
+
+            if let Some(discovery) = &store.discovery {
+                secondary.push(task::spawn(poll_secondary(
+                    Secondary::Discovery(Discovery::new(
+                        &store.url,
+                        site(&store),
+                        &discovery.sources,
+                        discovery.primary,
+                        listed.clone(),
+                        &store.watchlist,
+                    )),
+                    discovery.delay,
+                    dispatcher.clone(),
+                    handle.clone(),
+                )));
+            }
 
             // By tracking the value of these variables, the monitor can
             // detect any changes and send out webhooks accordingly.
-            let mut previous: Option<Vec<MinimalProduct>> = None;
-            let mut password_page = false;
+            let mut detector = Detector::new(site(&store));
             let mut rate_limit = false;
             let mut online = true;
             let mut broken_prev = 0;
 
-            // This will be used to return `Future`s that complete at
-            // intervals as long as the `delay` specified by the user.
-            let mut interval = time::interval(Duration::from_millis(store.delay));
+            // This is set if the store is removed through the control
+            // API, rather than because its webhooks are all invalid.
+            let mut removed = false;
+
+            // While this is in the future, the store is polled using the
+            // delay of its schedule's boost, if it's faster.
+            let mut boosted_until: Option<Instant> = None;
 
             // This `loop` is named so that it can be `break`ed out of
             // from within another loop.
@@ -178,30 +323,9 @@ pub async fn run(stores: Vec<Store>) {
                         // vector, it isn't possible for the length of
                         // `broken_webhooks` to decrease, so it's safe
                         // to access the elements using square brackets.
+                        router.remove(&broken_curr[i]).await;
 
-                        // The URL is checked instead of the channel
-                        // itself because if two user-created "channels"
-                        // were to share the same link, both should be
-                        // removed.
-                        restock
-                            .write()
-                            .await
-                            .retain(|c| c.url != broken_curr[i]);
-                        password_up
-                            .write()
-                            .await
-                            .retain(|c| c.url != broken_curr[i]);
-                        password_down
-                            .write()
-                            .await
-                            .retain(|c| c.url != broken_curr[i]);
-
-                        // If nothing is being monitored (as there
-                        // aren't any valid webhooks to send updates to)
-                        // the task is killed.
-                        if restock.read().await.is_empty() &&
-                            password_up.read().await.is_empty() &&
-                            password_down.read().await.is_empty() {
+                        if router.is_empty().await {
                             break 'main;
                         }
                     }
@@ -212,568 +336,788 @@ pub async fn run(stores: Vec<Store>) {
                     broken_prev = broken_curr.len();
                 }
 
-                // The endpoint for all Shopify store is
-                // `/products.json`, so it has to be added to the
-                // website's URL to get the link to it.
-                let req = client.get(
-                    /* format!("{}/products.json?limit=100", */
-                    format!("{}/products.json",
-                    &store.url.clone()
-                ))
+                // The time taken by the store to respond is tracked
+                // for the latency histogram in `/metrics`.
+                let start = Instant::now();
+
+                let req = poller.poll().await;
 
-                    // For this first version, I simply "borrowed" the "Safe
-                    // Headers" used in his JavaScript Shopify Monitor, however I
-                    // will experiment with more techniques to avoid bot detection
-                    // later. Here's the link to his repository:
-                    // https://github.com/aarock1234/shopify-monitor/blob/master/src/class/monitor.js.
-                    .header("pragma", "no-cache")
-                    .header("cache-control", "no-cache")
-                    .header("upgrade-insecure-requests", "1")
-                    .header("user-agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/86.0.4240.198 Safari/537.36") 
-                    .header("accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9") 
-                    .header("sec-fetch-site", "none")
-                    .header("sec-fetch-mode", "navigate")
-                    .header("sec-fetch-user", "?1")
-                    .header("sec-fetch-dest", "document")
-                    .header("accept-language", "en-US,en;q=0.9")
-
-                    .send()
-                    .await;
+                metrics.poll(
+                    &store.name,
+                    start.elapsed(),
+                    req.as_ref().ok().map(|res| res.status),
+                );
+                handle
+                    .last_poll
+                    .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+                handle
+                    .status
+                    .store(req.as_ref().map_or(0, |res| res.status), Ordering::Relaxed);
 
                 if let Ok(res) = req {
-                    /* hidden!("Fetched {}! Status: {}!", res.url(), res.status()); */
+                    metrics.download(&store.name, res.bytes, res.unchanged);
+
+                    /* hidden!("Fetched {}! Status: {}!", store.url, res.status); */
 
                     if !online {
                         default!("`{}` is back online!", store.name);
-                        tx.send(Update::Site(SiteUpdate::Online, "".into())).await.expect("Failed to send update.");
+                        tx.send(Update::Site(SiteUpdate::Online, store.name.clone()))
+                            .await
+                            .expect("Failed to send update.");
                         online = true;
+                        handle.online.store(true, Ordering::Relaxed);
                     }
 
-                    if res.status() == 200 {
-                        // In this case, a webhook saying the password
-                        // page is down will be sent.
-                        if password_page {
-                            password_page = false;
-
-                            hidden!("Password page raised on {}!", store.url);
-                            success!("{}: Password Page Up!", store.name);
-
-                            // This variable keeps track of the number
-                            // of webhooks sent for each store update.
-                            let mut quantity = 0;
-
-                            // The program will cycle through each
-                            // channel that should be notified and send
-                            // out a webhook.
-                            for channel in password_down.read().await.iter() {
-                                task::spawn(password(PasswordSettings {
-                                    kind: Password::Down,
-                                    url: channel.url.clone(),
-                                    username: channel.settings.username.clone(),
-                                    avatar: channel.settings.avatar.clone(),
-                                    color: channel.settings.color,
-                                    footer_text: channel.settings.footer_text.clone(),
-                                    footer_image: channel.settings.footer_image.clone(),
-                                    timestamp: channel.settings.timestamp,
-                                    store_name: store.name.clone(),
-                                    store_url: store.url.clone(),
-                                    store_logo: store.logo.clone(),
-                                    broken_webhooks: broken_webhooks.clone(),
-                                    tx: tx.clone(),
-                                }));
-
-                                // I think that using a counter should
-                                // be faster than accessing the
-                                // `password_down` vector to check its
-                                // length, but I may be wrong.
-                                quantity += 1;
-                            }
-
-                            default!(
-                                "Sending {} webhook{}...",
-                                quantity,
-                                // This conditional statement appends an
-                                // "s" to the word "webhook" if more
-                                // than one is sent. I'm using `\0`, a
-                                // null character, instead of an empty
-                                // character as the latter doesn't
-                                // exist.
-                                // https://stackoverflow.com/questions/3670505/why-is-there-no-char-empty-like-string-empty
-                                if quantity == 1 { '\0' } else { 's' }
-                            );
-                        }
+                    // Every response is saved as it was received if
+                    // recording is enabled, so that it can be replayed
+                    // later on. The ones whose products haven't changed
+                    // wouldn't cause any events, so they're skipped.
+                    if let Some(recorder) = recorder.as_ref().filter(|_| !res.unchanged) {
+                        recorder.save(&site(&store), res.status, &res.body).await;
+                    }
 
+                    if res.status == 200 || res.status == 304 {
                         if rate_limit {
                             rate_limit = false;
                         }
 
-                        let json = res.json::<File>().await;
-
-                        if let Ok(current) = json {
-                            // The current products have to allow for
-                            // multiple owners as they are consumed by
-                            // iterators when checking their contents,
-                            // but need to be used again later to update
-                            // the previous ones.
-                            let current_products = Arc::new(current.products);
-
-                            // If there's a previous value for the
-                            // response to be compared to, the stock
-                            // changes can be extracted and restock
-                            // webhooks can be sent out.
-                            if let Some(previous) = previous {
-                                // Cycling through each current
-                                // product the program finds the
-                                // same item in the previous ones by
-                                // matching IDs.
-                                for curr in (*current_products).iter() {
-                                    // Originally, when the program
-                                    // found a product that had been
-                                    // updated, it would also check if
-                                    // the availability of any variant
-                                    // has changed (becoming available).
-                                    // I changed how it functions,
-                                    // though, as it's usually more
-                                    // useful to have links to every
-                                    // available variant (often the
-                                    // "size" of a product), rather than
-                                    // only the ones that were
-                                    // previously unavailable.
-
-                                    // By removing this logic, however,
-                                    // a problem emerged: a "duplicate"
-                                    // webhook would be sent whenever a
-                                    // single variant went out of stock,
-                                    // as that counts as a product
-                                    // update.
-
-                                    // To counter this, the program now
-                                    // also checks that at least one
-                                    // unavailable variant has become
-                                    // available (or rather, that one of
-                                    // the available variants used to
-                                    // be unavailable), before sending a
-                                    // webhook.
-                                    if let Some(prev) =
-                                        previous.iter().find(|prev| prev.id == curr.id)
-                                    {
-                                        if curr.updated_at != prev.updated_at
-                                            && curr.variants.iter().any(|curr| {
-                                                prev.variants.iter().any(|prev| {
-                                                    prev.id == curr.id
-                                                        && !prev.available
-                                                        && curr.available
-                                                })
-                                            })
-                                        {
-                                            /* hidden!("Product {} Updated At: {}", curr.id, curr.updated_at); */
-
-                                            hidden!("{}/product/{} restocked!", store.url, curr.id);
-                                            success!("{}: `{}` restocked!", store.name, curr.title);
-
-                                            let mut quantity = 0;
-
-                                            let ap = available_product(curr);
-
-                                            for channel in restock.read().await.iter() {
-                                                if curr
-                                                    .variants
+                        match &res.products {
+                            Ok(current) => {
+                                handle.products.store(current.len(), Ordering::Relaxed);
+
+                                // Every variant whose availability
+                                // changed since the previous request is
+                                // recorded, as well as every variant on
+                                // the first one, so that the history
+                                // shows how long each size stayed in
+                                // stock, unless this is a dry run.
+                                if history.enabled() && dispatcher.dry_run.is_none() {
+                                    let time = Utc::now();
+
+                                    for curr in current.iter() {
+                                        let prev = detector.previous().and_then(|previous| {
+                                            previous.iter().find(|prev| prev.id == curr.id)
+                                        });
+
+                                        for variant in curr.variants.iter() {
+                                            let changed = prev.is_none_or(|prev| {
+                                                prev.variants
                                                     .iter()
-                                                    .filter(|v| v.available)
-                                                    .count()
-                                                    >= channel.settings.minimum
-                                                {
-                                                    // Although it may
-                                                    // not seem like it
-                                                    // at first glance,
-                                                    // `item()` is a
-                                                    // function, and
-                                                    // `webhooks`
-                                                    // contains an
-                                                    // asynchronous
-                                                    // function for each
-                                                    // webhook that
-                                                    // should be sent.
-                                                    task::spawn(item(ItemSettings {
-                                                        kind: Item::Restock,
-                                                        product: ap.clone(),
-                                                        url: channel.url.clone(),
-                                                        username: channel.settings.username.clone(),
-                                                        avatar: channel.settings.avatar.clone(),
-                                                        color: channel.settings.color,
-                                                        sizes: channel.settings.sizes,
-                                                        thumbnail: channel.settings.thumbnail,
-                                                        image: channel.settings.image,
-                                                        footer_text: channel
-                                                            .settings
-                                                            .footer_text
-                                                            .clone(),
-                                                        footer_image: channel
-                                                            .settings
-                                                            .footer_image
-                                                            .clone(),
-                                                        timestamp: channel.settings.timestamp,
-                                                        store_name: store.name.clone(),
-                                                        store_url: store.url.clone(),
-                                                        store_logo: store.logo.clone(),
-                                                        broken_webhooks: broken_webhooks.clone(),
-                                                        tx: tx.clone(),
-                                                    }));
-
-                                                    /* hidden!("Pushed a webhook for product {}!", curr.id); */
-
-                                                    quantity += 1;
-                                                }
+                                                    .find(|prev| prev.id == variant.id)
+                                                    .is_none_or(|prev| {
+                                                        prev.available != variant.available
+                                                    })
+                                            });
+
+                                            if changed {
+                                                history.record(Record::Snapshot(Snapshot {
+                                                    time,
+                                                    store: store.name.clone(),
+                                                    product_id: curr.id,
+                                                    handle: curr.handle.clone(),
+                                                    variant_id: variant.id,
+                                                    variant: variant.name(),
+                                                    available: variant.available,
+                                                }));
                                             }
-
-                                            /* hidden!("Sending webhooks for `{}`!", curr.id); */
-
-                                            default!(
-                                                "Sending {} webhook{}...",
-                                                quantity,
-                                                if quantity == 1 { '\0' } else { 's' }
-                                            );
-                                        }
-
-                                    // This code will run if a
-                                    // product is found that wasn't
-                                    // present among the previous
-                                    // ones, meaning it's a new one.
-                                    } else {
-                                        hidden!("{}/product/{} was added!", store.url, curr.id);
-                                        success!("{}: `{}` was added!", store.name, curr.title);
-
-                                        let mut quantity = 0;
-
-                                        let ap = available_product(curr);
-
-                                        for channel in restock.read().await.iter() {
-                                            task::spawn(item(ItemSettings {
-                                                kind: Item::New,
-                                                product: ap.clone(),
-                                                url: channel.url.clone(),
-                                                username: channel.settings.username.clone(),
-                                                avatar: channel.settings.avatar.clone(),
-                                                color: channel.settings.color,
-                                                sizes: channel.settings.sizes,
-                                                thumbnail: channel.settings.thumbnail,
-                                                image: channel.settings.image,
-                                                footer_text: channel.settings.footer_text.clone(),
-                                                footer_image: channel.settings.footer_image.clone(),
-                                                timestamp: channel.settings.timestamp,
-                                                store_name: store.name.clone(),
-                                                store_url: store.url.clone(),
-                                                store_logo: store.logo.clone(),
-                                                broken_webhooks: broken_webhooks.clone(),
-                                                tx: tx.clone(),
-                                            }));
-
-                                            quantity += 1;
                                         }
-
-                                        default!(
-                                            "Sending {} webhook{}...",
-                                            quantity,
-                                            if quantity == 1 { '\0' } else { 's' }
-                                        );
                                     }
                                 }
                             }
-
-                            // On the monitor's first run, there
-                            // won't be a `previous` value therefore
-                            // it will have to be saved for the
-                            // first time. This doesn't occur in an
-                            // `else` block, though, as the value
-                            // has to be updated on every cycle
-                            // regardless.
-                            previous = minimal_products(current_products);
-                        } else if let Err(e) = json {
-                            hidden!("Failed to parse JSON for {}: {}", store.url, e);
-
-                            // The program will wait for the interval to complete
-                            // its cycle before running the next iteration and
-                            // fetching the store's products again.
-                            interval.tick().await;
-                            continue;
-                        };
-
-                        // In this case, a webhook with the restocked
-                        // items will be sent.
-                    } else if res.status() == 401 {
-                        // In this case, a webhook saying the password
-                        // page is up will be sent.
-                        if !password_page {
-                            password_page = true;
-
-                            hidden!("Password page raised on {}!", store.url);
-                            success!("{}: Password Page Up!", store.name);
-
-                            let mut quantity = 0;
-
-                            // The program will cycle through each
-                            // channel that should be notified and send
-                            // out a webhook.
-                            for channel in password_up.read().await.iter() {
-                                task::spawn(password(PasswordSettings {
-                                    kind: Password::Up,
-                                    url: channel.url.clone(),
-                                    username: channel.settings.username.clone(),
-                                    avatar: channel.settings.avatar.clone(),
-                                    color: channel.settings.color,
-                                    footer_text: channel.settings.footer_text.clone(),
-                                    footer_image: channel.settings.footer_image.clone(),
-                                    timestamp: channel.settings.timestamp,
-                                    store_name: store.name.clone(),
-                                    store_url: store.url.clone(),
-                                    store_logo: store.logo.clone(),
-                                    broken_webhooks: broken_webhooks.clone(),
-                                    tx: tx.clone(),
-                                }));
-
-                                quantity += 1;
+                            // Products that haven't changed aren't parsed,
+                            // so there's nothing else to do.
+                            Err(_) if res.unchanged => {}
+                            Err(e) => {
+                                hidden!("Failed to parse JSON for {}: {}", store.url, e);
                             }
-
-                            default!(
-                                "Sending {} webhook{}...",
-                                quantity,
-                                if quantity == 1 { '\0' } else { 's' }
-                            );
                         }
-                    } else if res.status() == 429 && !rate_limit {
+                    } else if res.status == 429 && !rate_limit {
                         rate_limit = true;
                         warning!("Rate limit reached for {}!", store.name);
                     }
+
+                    // The events caused by this request are collected
+                    // by the detector, which is shared with the library
+                    // and the replay command, and only dispatched once
+                    // the request has been handled, so that detecting
+                    // them doesn't depend on where they're sent.
+                    let mut detected = detector.detect(res.status, res.products.as_deref().ok());
+                    handle
+                        .password
+                        .store(detector.password(), Ordering::Relaxed);
+
+                    // The products on the watchlist are only reported by
+                    // it, as it usually finds their changes first.
+                    detected.retain(|event| {
+                        event
+                            .product()
+                            .is_none_or(|product| !store.watchlist.contains(&product.handle))
+                    });
+
+                    // When new products are discovered through other
+                    // sources, the ones they already announced are
+                    // skipped, as are all of them if they're the primary
+                    // source.
+                    if let Some(discovery) = &store.discovery {
+                        let mut listed = listed.lock().expect("Failed to lock products.");
+
+                        detected.retain(|event| match event {
+                            Event::NewProduct { product, .. } => {
+                                !discovery.primary && !listed.contains(&product.id)
+                            }
+                            _ => true,
+                        });
+
+                        if let Ok(current) = &res.products {
+                            listed.extend(current.iter().map(|product| product.id));
+                        }
+                    }
+
+                    // Products found through a collection are labelled
+                    // with it, so that the messages say where they are.
+                    for event in detected.iter_mut() {
+                        let collection = event
+                            .product()
+                            .and_then(|product| res.collections.get(&product.id))
+                            .cloned();
+
+                        if let Some(collection) = collection {
+                            event.set_collection(collection);
+                        }
+                    }
+
+                    // Any event, including the password page going up or
+                    // down, usually means more are about to follow, so
+                    // the store is boosted if its schedule allows it.
+                    if let Some(duration) = store.schedule.boost().filter(|_| !detected.is_empty())
+                    {
+                        if boosted_until.is_none_or(|until| until <= Instant::now()) {
+                            default!("Boosted {} for {}s.", store.name, duration.as_secs());
+                        }
+                        boosted_until = Some(Instant::now() + duration);
+                    }
+
+                    for event in detected {
+                        dispatcher.dispatch(event).await;
+                    }
                 } else if online {
                     warning!("Failed to GET {}!", store.url);
-                    tx.send(Update::Site(SiteUpdate::Offline, "".into())).await.expect("Failed to send update.");
+                    tx.send(Update::Site(SiteUpdate::Offline, store.name.clone()))
+                        .await
+                        .expect("Failed to send update.");
                     online = false;
+                    handle.online.store(false, Ordering::Relaxed);
                 }
 
                 // The program will wait for the interval to complete
                 // its cycle before running the next iteration and
-                // fetching the store's products again.
-                interval.tick().await;
-            };
+                // fetching the store's products again. Its cycle is
+                // decided by the store's schedule every time, counting
+                // from when the request was sent, as the delay can vary
+                // between requests.
+                let boosted = boosted_until.is_some_and(|until| Instant::now() < until);
+                let delay = store
+                    .schedule
+                    .delay(handle.delay.load(Ordering::Relaxed), boosted);
+                let mut interval = time::interval_at(time::Instant::from_std(start) + delay, delay);
+
+                if !wait(
+                    &store,
+                    boosted_until,
+                    &mut interval,
+                    &mut control,
+                    &handle,
+                    &mut stop,
+                )
+                .await
+                {
+                    removed = true;
+                    break 'main;
+                }
+            }
+
+            for task in secondary {
+                task.abort();
+            }
+
+            // Stores also stop when the program is shutting down, in
+            // which case they're neither invalid nor removed.
+            let stopping = *stop.borrow();
+
+            controller.unregister(&store.name);
+
+            // A store that stops while offline shouldn't be counted as
+            // such anymore, or the program could never be considered
+            // back online.
+            if !online {
+                tx.send(Update::Site(SiteUpdate::Stopped, store.name.clone()))
+                    .await
+                    .expect("Failed to send update.");
+            }
+
+            // The metrics of stores that were removed are discarded,
+            // while the ones of stores that stopped on their own are
+            // kept, so that their last poll can still be alerted on.
+            if stopping {
+                hidden!("Stopped monitoring {}.", store.url);
+            } else {
+                if removed {
+                    metrics.unregister(&store.name);
+                } else {
+                    error!("All webhook URLs for `{}` are invalid!", store.name);
+                }
+
+                default!("Stopped monitoring {}.", store.url);
+            }
 
-            error!("All webhook URLs for `{}` are invalid!", store.name);
-            default!("Stopped monitoring {}.", store.url);
             *amount.write().await -= 1;
+            metrics.amount.fetch_sub(1, Ordering::Relaxed);
 
             // If no stores are being monitored, the `run()` function
-            // will return and the program will quit.
-            if *amount.read().await == 0 {
-                tx.send(Update::Monitor(MonitorUpdate::Quit, "No valid webhooks!".into())).await.expect("Failed to send update.");
+            // will return and the program will quit. While shutting
+            // down, it's already returning, once the webhooks have
+            // been sent.
+            if *amount.read().await == 0 && !stopping {
+                tx.send(Update::Monitor(
+                    MonitorUpdate::Quit,
+                    if removed {
+                        "No stores left to monitor!"
+                    } else {
+                        "No valid webhooks!"
+                    }
+                    .into(),
+                ))
+                .await
+                .expect("Failed to send update.");
             }
-        }));
+        })
+    };
+
+    for store in stores {
+        tasks.push(watch(store));
     }
 
-    if quit_rx.await.is_ok() {
-        return;
+    // Once all the stores have been started, this loop waits for the
+    // monitor to quit, starting the ones added while it's running.
+    let mut quit_rx = quit_rx;
+
+    loop {
+        tokio::select! {
+            quit = &mut quit_rx => {
+                if quit.is_ok() {
+                    return false;
+                }
+
+                // This function call ensures that the program doesn't
+                // exit while the monitor is still running.
+                join_all(tasks).await;
+                return false;
+            }
+            Some(store) = added.recv() => {
+                success!("Started monitoring {}!", store.name);
+                *amount.write().await += 1;
+                metrics.amount.fetch_add(1, Ordering::Relaxed);
+                tasks.push(watch(store));
+            }
+            Ok(()) = shutdown.changed() => break,
+        }
     }
 
-    // This function call ensures that the program doesn't exit while
-    // the monitor is still running.
-    join_all(tasks).await;
-}
+    // The stores stop once they finish their current request, then the
+    // background task stops once every task sending a webhook, which
+    // all hold a copy of `tx`, has finished, so waiting for it is how
+    // the monitor knows that there's nothing left to send.
+    default!("Waiting for the webhooks being sent to finish...");
+    drop(tx);
 
-#[derive(Debug)]
-enum Update {
-    Monitor(MonitorUpdate, String),
-    Site(SiteUpdate, String),
-    Webhook(WebhookUpdate, String),
-}
+    let drained = time::timeout(DRAIN, join_all(tasks)).await.is_ok();
 
-#[derive(Debug)]
-enum MonitorUpdate {
-    Quit,
-}
+    if drained {
+        success!("Every webhook was sent!");
+    } else {
+        warning!(
+            "Stopped waiting for the webhooks after {}, some may not have been sent!",
+            elapsed(DRAIN)
+        );
+    }
 
-#[derive(Debug, PartialEq)]
-enum SiteUpdate {
-    Online,
-    Offline,
+    // The events recorded to the history are saved by another thread,
+    // which is given a few seconds to write the last ones.
+    if history.enabled() {
+        if task::spawn_blocking(move || history.flush())
+            .await
+            .unwrap_or(false)
+        {
+            success!("Saved the history!");
+        } else {
+            warning!("Failed to save the latest events to the history!");
+        }
+    }
+
+    default!(
+        "Monitored {} stores for {}, detecting {} events and sending {} webhooks ({} failed, {} rate limited, {} skipped during cooldowns).",
+        metrics.stores(),
+        elapsed(started.elapsed()),
+        metrics.events(),
+        metrics.webhooks("sent"),
+        metrics.webhooks("failed") + metrics.webhooks("invalid"),
+        metrics.webhooks("rate_limited"),
+        metrics.suppressed(),
+    );
+
+    true
 }
 
-#[derive(Debug)]
-enum WebhookUpdate {
-    Invalid,
+// This function describes the store an event occurred on.
+fn site(store: &Store) -> event::Store {
+    event::Store {
+        name: store.name.clone(),
+        url: store.url.clone(),
+        logo: store.logo.clone(),
+    }
 }
 
-fn minimal_products(current_products: Arc<Vec<Product>>) -> Option<Vec<MinimalProduct>> {
-    Some({
-        let mut products = vec![];
-        for product in (*current_products).iter() {
-            let mut variants = vec![];
-            for variant in &product.variants {
-                variants.push(MinimalVariant {
-                    id: variant.id,
-                    available: variant.available,
-                    /* updated_at: variant.updated_at.clone(), */
-                });
-            }
-            products.push(MinimalProduct {
+// This function creates the record saved to the history database for
+// an event.
+fn record(event: &Event) -> Record {
+    Record::Event(history::Event {
+        time: Utc::now(),
+        store: event.store().name.clone(),
+        event: event.name(),
+        product: event.product().map(|product| {
+            let product = available_product(product, event.restocked());
+
+            history::Product {
                 id: product.id,
-                updated_at: product.updated_at.clone(),
-                variants,
-            });
-        }
-        products
+                handle: product.handle.clone(),
+                title: product.name.clone(),
+                price: product.price.clone(),
+                variants: product
+                    .variants
+                    .iter()
+                    .map(|variant| (variant.id, variant.name.clone()))
+                    .collect(),
+            }
+        }),
     })
 }
 
-// When previous products and variants are compared against the current ones to find
-// any changes, only their id (used to identify them) and the time they
-// were last updated (do determine if it's even possible for their stock
-// number to have changed) are compared. This new struct, holding the
-// minimum amount of data, can be used to reduce memory usage so that
-// products don't have to be saved to a database.
-struct MinimalProduct {
-    id: u64,
-    updated_at: String,
-    variants: Vec<MinimalVariant>,
+// Each store's task hands the events it detects to its dispatcher, which
+// logs them, counts them, publishes them to the dashboard and to the
+// library's subscribers, records them to the history and finally sends
+// them to every channel subscribed to them.
+struct Dispatcher {
+    router: Router,
+    controller: Arc<Controller>,
+    metrics: Arc<Metrics>,
+    events: Arc<Events>,
+    history: Arc<History>,
+    messages: Messages,
+    cooldowns: Arc<Cooldowns>,
+    broken_webhooks: Arc<RwLock<Vec<String>>>,
+    dry_run: Option<Arc<DryRun>>,
+    tx: Sender<Update>,
 }
 
-// The fields of this struct used to be public while those of `MinimalProduct`
-// are not because a test required it.
+impl Dispatcher {
+    async fn dispatch(&self, event: Event) {
+        let store = event.store();
 
-struct MinimalVariant {
-    id: u64,
-    available: bool,
-    // While the program could check when each variant was last updated,
-    // ignoring that value and only checking its availability is faster,
-    // and removing its field results in lower memory usage.
-    // updated_at: String,
-}
+        match &event {
+            Event::NewProduct { product, .. } => {
+                hidden!("{}/product/{} was added!", store.url, product.id);
+                success!("{}: `{}` was added!", store.name, product.title);
+            }
+            Event::Restock { product, .. } => {
+                /* hidden!("Product {} Updated At: {}", product.id, product.updated_at); */
 
-// Since the monitor will check which variants are available for a
-// certain product before sending a webhook, a different struct is
-// needed to send as `item()`'s parameter, or the program will have to
-// perform those checks again. `AvailableProduct` will therefore only
-// contain the product's information that should be included in the
-// webhook's embed, as well as a vector containing the available
-// variants.
-#[derive(PartialEq, Debug)]
-struct AvailableProduct {
-    name: String,
-
-    // The product's handle can be used to obtain the product link as
-    // follows: `format!("{}/products/{}", store_url, handle)`.
-    handle: String,
-    brand: String,
-    price: String,
-
-    // I changed this to an `Option` as for some reason (which I can't
-    // remember) I was using an empty `String` instead of `None` if the
-    // product didn't have a photo.
-    image: Option<String>,
-    variants: Vec<AvailableVariant>,
-}
+                hidden!("{}/product/{} restocked!", store.url, product.id);
+                success!("{}: `{}` restocked!", store.name, product.title);
+            }
+            Event::SoldOut { .. } => {}
+            Event::PasswordUp { .. } => {
+                hidden!("Password page raised on {}!", store.url);
+                success!("{}: Password Page Up!", store.name);
+            }
+            Event::PasswordDown { .. } => {
+                hidden!("Password page lowered on {}!", store.url);
+                success!("{}: Password Page Down!", store.name);
+            }
+        }
+
+        self.metrics.event(&store.name, event.name());
+        self.events.publish(event.clone());
 
-// There's no need to make unnecessary operations or clone unused data,
-// so this struct holds the bare minimum. Since some values
-#[derive(PartialEq, Debug)]
-struct AvailableVariant {
-    name: String,
-    id: u64,
+        // Sizes selling out aren't saved as events, as the snapshots
+        // already show when each of them sold out. Nothing is saved during
+        // a dry run, as the messages it shows were never sent.
+        let sold_out = matches!(event, Event::SoldOut { .. });
+
+        if !sold_out && self.dry_run.is_none() {
+            self.history.record(record(&event));
+        }
+
+        let mut channels = self
+            .router
+            .route(&event, |channel| {
+                self.controller.muted(&channel.server, &channel.name)
+            })
+            .await;
+
+        // Channels that were sent the same sizes too recently are
+        // skipped, which is counted so that flapping stores can be
+        // spotted.
+        let routed = channels.len();
+        channels.retain(|channel| self.cooldowns.allow(channel, &event));
+        let suppressed = routed - channels.len();
+
+        if suppressed > 0 {
+            self.metrics.suppress(&store.name, suppressed as u64);
+            default!(
+                "Skipped {} webhook{} for `{}`, as its sizes were sent recently.",
+                suppressed,
+                if suppressed == 1 { "" } else { "s" },
+                event.product().map_or("", |product| product.title.as_str())
+            );
+        }
+
+        // Every channel receives the event from its own task, so that a
+        // slow or rate-limited webhook doesn't delay the others.
+        let event = Arc::new(event);
+
+        for channel in channels.iter() {
+            task::spawn(deliver(
+                event.clone(),
+                channel.clone(),
+                self.messages.clone(),
+                self.broken_webhooks.clone(),
+                self.dry_run.clone(),
+                self.tx.clone(),
+            ));
+        }
+
+        if !sold_out {
+            default!(
+                "Sending {} webhook{}...",
+                channels.len(),
+                // This conditional statement appends an "s" to the word
+                // "webhook" if more than one is sent. I'm using `\0`, a
+                // null character, instead of an empty character as the
+                // latter doesn't exist.
+                // https://stackoverflow.com/questions/3670505/why-is-there-no-char-empty-like-string-empty
+                if channels.len() == 1 { '\0' } else { 's' }
+            );
+        }
+    }
 }
 
-// Why do two `struct`s for both "Minimal" and "Available" Products and
-// Variants exist, if they share the goal to reduce memory usage and are
-// so similar? Their similarity is due to their common goal, but they
-// aim for it in different contexts. The "Minimal" `struct`s are the
-// smallest, and contain the data required for the program to check for
-// product updates. The "Available" `struct`s, on the other hand, hold
-// different data types, as they include the product details used to
-// form webhook embeds. As a result, both types are needed.
-
-fn available_product(
-    curr: &Product, /*, prev: Option<&Vec<MinimalVariant>>*/
-) -> Arc<AvailableProduct> {
-    let mut variants: Vec<AvailableVariant> = vec![];
-
-    let price = if let Some(v) = curr.variants.get(0) {
-        v.price.clone()
-    } else {
-        // The default price value is "?" because it must be at least 1
-        // character long.
-        "?".into()
+// This function sends an event to a channel, by running its command if
+// it has one, or through its webhook otherwise. If the channel edits its
+// messages, the one that was sent for the product is edited instead of
+// sending a new one, and is kept track of in `messages`.
+async fn deliver(
+    event: Arc<Event>,
+    channel: Arc<Channel>,
+    messages: Messages,
+    broken: Arc<RwLock<Vec<String>>>,
+    dry_run: Option<Arc<DryRun>>,
+    tx: Sender<Update>,
+) {
+    if let Some(command) = &channel.command {
+        if let Some(dry_run) = &dry_run {
+            dry_run
+                .command(&channel.name, &render::command(&event))
+                .await;
+        } else {
+            command::run(command.clone(), render::command(&event)).await;
+        }
+        return;
+    }
+
+    let settings = &channel.settings;
+
+    let (kind, product) = match (Item::of(&event), event.product()) {
+        (Some(kind), Some(product)) => (kind, product),
+
+        // Password page events aren't about a product, so their
+        // messages are never edited.
+        _ => {
+            if let Some(msg) = render::password(&event, settings) {
+                request(
+                    channel.url.clone(),
+                    event.store().name.clone(),
+                    None,
+                    Arc::new(msg),
+                    broken,
+                    dry_run,
+                    tx,
+                )
+                .await;
+            }
+            return;
+        }
     };
 
-    /*
-    let image = if let Some(img) = curr.images.get(0) {
-        Some(img.src.clone())
+    let key = (channel.url.clone(), product.id);
+
+    // The message that was previously sent for the product is only
+    // looked up if the channel edits its messages, as otherwise a new
+    // one should always be sent.
+    let posted = if settings.edit {
+        messages.read().await.get(&key).cloned()
     } else {
         None
     };
-    */
-
-    let image = curr.images.get(0).map(|img| img.src.clone());
-
-    for variant in curr.variants.iter() {
-        if variant.available {
-            variants.push(AvailableVariant {
-                // Some websites have very weird variant names.
-                // UNDEFEATED, for example, prefixes their "sizes" with
-                // "- / ". `.trim_prefix()` cannot be used to correct
-                // this, as it would only work for specific cases. By
-                // removing all special characters, so that the name
-                // only contains letters, numbers, and whitespace,
-                // almost all of these strange names can be "normalized".
-                name: variant
-                    .title
-                    // The string is split into characters.
-                    .chars()
-                    // The split is transformed into a vector.
-                    .collect::<Vec<char>>()
-                    // The program can now iterate through each char.
-                    .iter()
-                    // "Invalid" characters are removed.
-                    .filter(|c| c.is_alphanumeric() || c.is_whitespace() || c == &&'.')
-                    // The filtered characters are collected into a string.
-                    .collect::<String>()
-                    // Leading and trailing whitespace is removed.
-                    .trim()
-                    // The returned `&str` is converted to a `String`.
-                    .into(),
-                id: variant.id,
-            });
+
+    // If no message was sent for a product, there's nothing to update
+    // when it sells out.
+    let msg = if let Some(msg) = render::item(&event, settings, posted.as_ref()) {
+        msg
+    } else {
+        return;
+    };
+
+    if kind == Item::SoldOut {
+        hidden!(
+            "Updating the stock of {}/product/{} in {}...",
+            event.store().url,
+            product.id,
+            channel.name
+        );
+    }
+
+    // Discord never pings anyone for edited messages, so restocks that
+    // mention someone are sent as a new message, which is then the one
+    // edited from then on.
+    let id = match &posted {
+        Some(_) if kind == Item::Restock && msg.allowed_mentions.is_some() => None,
+        posted => posted.as_ref().map(|posted| posted.id.clone()),
+    };
+
+    let sent = request(
+        channel.url.clone(),
+        event.store().name.clone(),
+        id,
+        Arc::new(msg),
+        broken,
+        dry_run,
+        tx,
+    )
+    .await;
+
+    if settings.edit {
+        if let Some(id) = sent {
+            let mut messages = messages.write().await;
+
+            // Messages that were sent too long ago aren't edited anymore,
+            // so that the map doesn't keep growing while the monitor runs.
+            messages.retain(|_, posted| posted.time.elapsed() < MESSAGES_TTL);
+
+            // Once every size sold out, the message is final, and the
+            // next restock is sent as a new one.
+            if kind == Item::SoldOut && product.variants.iter().all(|variant| !variant.available) {
+                messages.remove(&key);
+                return;
+            }
+
+            // Updating the stock of a product doesn't change which
+            // sizes the message was originally sent for, or when.
+            let posted = match posted {
+                Some(posted) if kind == Item::SoldOut => Posted { id, ..posted },
+                _ => Posted {
+                    id,
+                    time: Instant::now(),
+                    kind,
+                    product: available_product(product, event.restocked()),
+                },
+            };
+
+            messages.insert(key, posted);
         }
     }
+}
 
-    Arc::from(AvailableProduct {
-        name: curr.title.clone(),
-        handle: curr.handle.clone(),
-        brand: curr.vendor.clone(),
-        price,
-        image,
-        variants,
-    })
+// These are polled alongside `/products.json`, by their own task.
+enum Secondary {
+    Watchlist(Watchlist),
+    Discovery(Discovery),
+}
+
+// This function polls a store's watchlist, or the sources it discovers
+// new products through, every `delay`, until it's stopped by the store's
+// task. Nothing is requested while the store is paused.
+async fn poll_secondary(
+    mut secondary: Secondary,
+    delay: u64,
+    dispatcher: Arc<Dispatcher>,
+    handle: Arc<Handle>,
+) {
+    let mut interval = time::interval(Duration::from_millis(delay));
+
+    loop {
+        interval.tick().await;
+
+        if handle.paused.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        let events = match &mut secondary {
+            Secondary::Watchlist(watchlist) => watchlist.poll().await,
+            Secondary::Discovery(discovery) => discovery.poll().await,
+        };
+
+        for event in events {
+            dispatcher.dispatch(event).await;
+        }
+    }
 }
 
-// This function is called by `product()` and `password()` functions,
-// incorporating the logic used in both of them to send webhooks. Its
-// only parameters are the webhook's URL and the `Message` to be sent,
-// while the two functions' role is to construct the embeds, as they
-// will differ between item and password-related notifications.
+// This function waits until the store should be polled again, which is
+// either when the interval completes its cycle or when an immediate poll
+// is requested, handling the other control messages in the meantime. It
+// returns `false` if the store should stop being monitored, which is also
+// the case when the program is shutting down. When the store is resumed
+// or its delay is changed, the next request is scheduled like any other,
+// using the store's schedule and whether it's still boosted.
+async fn wait(
+    store: &Store,
+    boosted_until: Option<Instant>,
+    interval: &mut Interval,
+    control: &mut Receiver<Control>,
+    handle: &Handle,
+    stop: &mut watch::Receiver<bool>,
+) -> bool {
+    let name = &store.name;
+    let next = || {
+        let boosted = boosted_until.is_some_and(|until| Instant::now() < until);
+        store
+            .schedule
+            .delay(handle.delay.load(Ordering::Relaxed), boosted)
+    };
+
+    loop {
+        let paused = handle.paused.load(Ordering::Relaxed);
+
+        if *stop.borrow() {
+            return false;
+        }
+
+        tokio::select! {
+            Ok(()) = stop.changed() => return false,
+            _ = interval.tick(), if !paused => return true,
+            message = control.recv() => match message {
+                Some(Control::Pause) => {
+                    default!("Paused {}.", name);
+                    handle.paused.store(true, Ordering::Relaxed);
+                }
+                Some(Control::Resume) => {
+                    default!("Resumed {}.", name);
+                    handle.paused.store(false, Ordering::Relaxed);
+
+                    // The interval is restarted, as otherwise it would
+                    // complete every cycle it missed while paused at once.
+                    let delay = next();
+                    *interval = time::interval_at(time::Instant::now() + delay, delay);
+                }
+                Some(Control::Delay(delay)) => {
+                    // As in `stores`, the delay can't be 0.
+                    let delay = delay.max(1);
+
+                    default!("Changed the delay of {} to {}ms.", name, delay);
+                    handle.delay.store(delay, Ordering::Relaxed);
+
+                    let delay = next();
+                    *interval = time::interval_at(time::Instant::now() + delay, delay);
+                }
+                Some(Control::Poll) => return true,
+                Some(Control::Remove) | None => return false,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Monitor(MonitorUpdate, String),
+    Site(SiteUpdate, String),
+    // Webhook updates include the URL of the webhook and the name of
+    // the store the message was about.
+    Webhook(WebhookUpdate, String, String),
+}
+
+#[derive(Debug)]
+enum MonitorUpdate {
+    Quit,
+}
+
+#[derive(Debug, PartialEq)]
+enum SiteUpdate {
+    Online,
+    Offline,
+    Stopped,
+}
+
+#[derive(Debug)]
+enum WebhookUpdate {
+    Sent,
+    RateLimited,
+    Failed,
+    Invalid,
+}
+
+// This function is called by `deliver()`, incorporating the logic used
+// to send webhooks. Its main parameters are the webhook's URL and the
+// `Message` to be sent, which is built by `render`, as the embeds
+// differ between item and password-related notifications. If the
+// ID of a previous message is included, that message is edited instead
+// of a new one being sent, and the function returns the ID of the
+// message it sent (or edited) if Discord replied with it.
 async fn request(
     url: String,
+    store: String,
+    id: Option<String>,
     msg: Arc<Message>,
     broken: Arc<RwLock<Vec<String>>>,
+    dry_run: Option<Arc<DryRun>>,
     tx: Sender<Update>,
-) {
-    /* hidden!("Webhook Preview: {}", preview(msg.clone())); */
+) -> Option<String> {
+    // During a dry run, Discord is never contacted, and the message is
+    // only shown to the user.
+    if let Some(dry_run) = dry_run {
+        return Some(dry_run.message(&url, id.as_deref(), &msg).await);
+    }
+
+    // The ID is shadowed as it could be discarded if the message it
+    // refers to no longer exists.
+    let mut id = id;
 
     loop {
-        match webhook::send(url.clone(), msg.clone()).await {
+        let status = if let Some(id) = &id {
+            webhook::edit(url.clone(), id.clone(), msg.clone()).await
+        } else {
+            webhook::send(url.clone(), msg.clone()).await
+        };
+
+        match status {
             // Seems like the compiler complains when I use my logging
             // macros outside of code blocks...
             /* Status::Success => hidden!("Successfully sent webhook to {}!", url), */
-            Status::Success => {
+            Status::Success(sent) => {
                 hidden!("Successfully sent webhook to {}!", url);
+                tx.send(Update::Webhook(WebhookUpdate::Sent, url, store))
+                    .await
+                    .expect("Failed to send update.");
+                return sent.or(id);
             }
             Status::RateLimit(seconds) => {
                 hidden!("Rate Limit reached for {}!", url);
+                tx.send(Update::Webhook(
+                    WebhookUpdate::RateLimited,
+                    url.clone(),
+                    store.clone(),
+                ))
+                .await
+                .expect("Failed to send update.");
 
                 if let Some(seconds) = seconds {
                     hidden!("Waiting {} seconds for {}...", seconds, url);
@@ -786,12 +1130,20 @@ async fn request(
                     continue;
                 }
             }
+            // If a message can't be edited, it was most likely deleted
+            // by someone in the server, which doesn't mean the webhook
+            // is broken, so a new message is sent instead.
+            Status::Invalid if id.is_some() => {
+                hidden!("Failed to edit message {:?} on {}!", id, url);
+                id = None;
+                continue;
+            }
             Status::Invalid => {
                 if !broken.read().await.contains(&url) {
                     // Due to the channel's buffer, sending this message
                     // should take less time than `.write()`ing to
                     // `broken` directly.
-                    tx.send(Update::Webhook(WebhookUpdate::Invalid, url))
+                    tx.send(Update::Webhook(WebhookUpdate::Invalid, url, store))
                         .await
                         .expect("Failed to send update.");
                 }
@@ -805,298 +1157,33 @@ async fn request(
             // the monitor's behavior.
             Status::Unknown => {
                 warning!("Failed to send webhook to {}!", url);
+                tx.send(Update::Webhook(WebhookUpdate::Failed, url, store))
+                    .await
+                    .expect("Failed to send update.");
             }
         }
 
-        return;
+        return None;
     }
 }
 
-// The function and enum are named `item()` and `Item`, and not
-// `product()` and `Product`, because the `Product` name is already used
-// by `crate::products::Product`, which is named after `products.json`.
-async fn item(settings: ItemSettings) {
-    /* hidden!("`item()` started for {}!", product.name.clone()); */
-
-    let embed = Embed {
-        title: Some(settings.product.name.clone()),
-        description: None,
-        url: Some(format!(
-            "{}/products/{}",
-            settings.store_url, settings.product.handle
-        )),
-        color: settings.color,
-        fields: {
-            let quantity = if settings.sizes {
-                /*
-                let len = 3 + product.variants.len();
-
-                if len % 3 == 2 {
-                  len + 4
-                }
+// The key to each message is the webhook URL it was sent to and the ID
+// of the product it's about.
+type Messages = Arc<RwLock<HashMap<(String, u64), Posted>>>;
 
-                len + 3
-                */
+// This is how long a message keeps being edited after it was sent.
+const MESSAGES_TTL: Duration = Duration::from_secs(24 * 60 * 60);
 
-                // Since the checks for the number of variants (above
-                // this comment) were removed, the number of fields
-                // is always 4 more than the number of variants, and
-                // is the vector holding them occasionally has a
-                // slightly larger capacity than necessary.
-                settings.product.variants.len() + 4
-            } else {
-                3
-            };
+// This formats the time it took for a product to sell out in the same
+// way it's usually written, such as "42s" or "3m 12s".
+pub fn elapsed(duration: Duration) -> String {
+    let seconds = duration.as_secs();
 
-            // The value calculated above is used to preallocate the
-            // correct amount of space in the heap to hold this
-            // vector, slightly improving performance.
-            let mut fields = Vec::with_capacity(quantity);
-
-            fields.push(Field {
-                name: "Event".into(),
-                inline: Some(true),
-                value: {
-                    if settings.kind == Item::New {
-                        "New Product".into()
-                    } else {
-                        "Restock".into()
-                    }
-                },
-            });
-
-            fields.push(Field {
-                name: "Brand".into(),
-                inline: Some(true),
-                value: settings.product.brand.clone(),
-            });
-
-            fields.push(Field {
-                name: "Price".into(),
-                inline: Some(true),
-                value: settings.product.price.clone(),
-            });
-
-            /* hidden!("{} has {} updated variants!", settings.product.name, settings.product.variants.len()); */
-
-            if settings.sizes {
-                for variant in (*settings.product.variants).iter() {
-                    fields.push(Field {
-                        name: format!("Size {}", variant.name),
-                        inline: Some(true),
-                        value: format!("[ATC]({}/cart/add?id={})", settings.store_url, variant.id),
-                    });
-                }
-
-                // When the bottom row of a Discord embed
-                // has two fields, it is aligned differently from
-                // the other rows, which some users consider
-                // displeasing. As a test, it is currently always
-                // "corrected" by the program, which adds an
-                // invisible field when necessary. In a future
-                // update, a toggle may be added allowing users to
-                // opt out of this behavior.
-                if fields.len() % 3 == 2 {
-                    fields.push(Field {
-                        // The characters held by the `name` and
-                        // `value` fields are the `U+2800` "Braille
-                        // Pattern Blank" character, which can be
-                        // used to fool Discord into thinking that
-                        // they aren't blank.
-                        name: '⠀'.into(),
-                        inline: Some(true),
-                        value: '⠀'.into(),
-                    });
-                }
-            }
-
-            Some(fields)
-        },
-        author: Some(Author {
-            name: settings.store_name,
-            url: Some(settings.store_url.clone()),
-            icon_url: Some(settings.store_logo),
-        }),
-        footer: {
-            // The program doesn't check if a footer image was
-            // included, as if a timestamp or footer text
-            // weren't, it won't be rendered regardless.
-            if settings.footer_text.is_some() || settings.timestamp {
-                Some(Footer {
-                    text: settings.footer_text,
-                    icon_url: settings.footer_image,
-                })
-            } else {
-                None
-            }
-        },
-        timestamp: {
-            if settings.timestamp {
-                Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true))
-            } else {
-                None
-            }
-        },
-        image: {
-            // This isn't very elegant, but I copied it from the
-            // `thumbnail` field below where it was the only
-            // solution I found.
-
-            let mut img = None;
-            if settings.image && settings.product.image.is_some() {
-                img = Some(Image {
-                    url: settings
-                        .product
-                        .image
-                        .clone()
-                        .expect("Failed to extract Image URL."),
-                });
-            }
-            img
-        },
-        thumbnail: {
-            let mut tn = None;
-            if settings.thumbnail && settings.product.image.is_some() {
-                tn = Some(Thumbnail {
-                    url: settings
-                        .product
-                        .image
-                        .clone()
-                        .expect("Failed to extract Image URL."),
-                });
-            }
-            tn
-        },
-    };
-
-    let msg = Arc::from(Message {
-        content: None,
-        embeds: Some(vec![embed]),
-        username: settings.username,
-        avatar_url: settings.avatar.clone(),
-    });
-
-    /* hidden!("Calling `request()` for {}!", product.name.clone()); */
-
-    request(settings.url, msg, settings.broken_webhooks, settings.tx).await;
-}
-
-struct ItemSettings {
-    kind: Item,
-    product: Arc<AvailableProduct>,
-    url: String,
-    username: Option<String>,
-    avatar: Option<String>,
-    color: Option<u32>,
-    sizes: bool,
-    /* atc: Option<bool>, */
-    thumbnail: bool,
-    image: bool,
-    footer_text: Option<String>,
-    footer_image: Option<String>,
-    timestamp: bool,
-    store_name: String,
-    store_url: String,
-    store_logo: String,
-    broken_webhooks: Arc<RwLock<Vec<String>>>,
-    tx: Sender<Update>,
-}
-
-#[derive(PartialEq)]
-enum Item {
-    New,
-    Restock,
-}
-
-async fn password(settings: PasswordSettings) {
-    let embed = Embed {
-        title: Some(format!("Password Page {}!", {
-            if settings.kind == Password::Up {
-                "Up"
-            } else {
-                "Down"
-            }
-        })),
-        description: None,
-        url: Some(settings.store_url.clone()),
-        color: settings.color,
-        fields: None,
-        author: Some(Author {
-            name: settings.store_name,
-            url: Some(settings.store_url.clone()),
-            icon_url: Some(settings.store_logo),
-        }),
-        footer: {
-            // The program doesn't check if a footer image was included,
-            // as if a timestamp or footer text weren't, it won't be
-            // rendered regardless.
-            if settings.footer_text.is_some() || settings.timestamp {
-                Some(Footer {
-                    text: settings.footer_text,
-                    icon_url: settings.footer_image,
-                })
-            } else {
-                None
-            }
-        },
-        timestamp: {
-            if settings.timestamp {
-                Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true))
-            } else {
-                None
-            }
-        },
-        image: None,
-        thumbnail: None,
-    };
-
-    let msg = Arc::from(Message {
-        content: None,
-        embeds: Some(vec![embed]),
-        username: settings.username,
-        avatar_url: settings.avatar.clone(),
-    });
-
-    request(settings.url, msg, settings.broken_webhooks, settings.tx).await;
-}
-
-struct PasswordSettings {
-    kind: Password,
-    url: String,
-    username: Option<String>,
-    avatar: Option<String>,
-    color: Option<u32>,
-    footer_text: Option<String>,
-    footer_image: Option<String>,
-    timestamp: bool,
-    store_name: String,
-    store_url: String,
-    store_logo: String,
-    broken_webhooks: Arc<RwLock<Vec<String>>>,
-    tx: Sender<Update>,
-}
-
-#[derive(PartialEq)]
-enum Password {
-    Up,
-    Down,
-}
-
-// While this function currently isn't used anywhere, it has been tested
-// and seems to be working. In the future, it could be used to allow
-// users to view a preview of the embeds, which could be convenient when
-// setting up the monitor using the command line in future versions of
-// the program.
-#[allow(dead_code)]
-fn preview(msg: Arc<Message>) -> String {
-    format!(
-        "https://discohook.org/?data={}",
-        encode_config(
-            format!(
-                "{{\"messages\":[{{\"data\":{}}}]}}",
-                serde_json::to_string(&*msg).expect("Failed to serialize JSON.")
-            ),
-            URL_SAFE
-        )
-    )
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 3600 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    }
 }
@@ -0,0 +1,445 @@
+// This module sends the requests used to find out what a store is
+// selling. `Poller` fetches its products once, while `watch()` polls it
+// repeatedly and turns the changes into events, for programs that only
+// need the events and not the rest of the monitor.
+
+// Most polls return exactly the same products as the previous one, so
+// the poller remembers the last response, sending its `ETag` and
+// `Last-Modified` headers back to the store, which can then reply with
+// `304 Not Modified` and no body, and hashing its body, so that a body
+// that didn't change isn't parsed again.
+
+// Stores can also be polled through some of their collections, instead
+// of their whole catalog, in which case every page of each collection is
+// fetched, and their products are combined as if they were a single
+// `products.json` file.
+
+use crate::{
+    diff::Detector,
+    event::{Event, Store},
+    products::{self, File, Page, Product},
+};
+use futures::{stream, Stream};
+use reqwest::{header::HeaderMap, Client, RequestBuilder};
+use std::{
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        HashMap, HashSet, VecDeque,
+    },
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+use tokio::time::{self, Interval};
+
+// This is the most products Shopify returns in a single page.
+const LIMIT: usize = 250;
+
+// Stores that ignore the `page` parameter would return the same page
+// forever, so collections stop being paginated after this many pages.
+const PAGES: usize = 40;
+
+#[derive(Clone, Debug)]
+pub struct Poller {
+    client: Client,
+    url: String,
+    collections: Vec<String>,
+    full: bool,
+
+    // Each page that was fetched is cached separately, using its link.
+    cache: HashMap<String, Cache>,
+}
+
+// This is what's remembered about the last `200 OK` response of a page.
+#[derive(Clone, Debug, Default)]
+struct Cache {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    hash: Option<u64>,
+
+    // The products of collections are kept too, as every page is needed
+    // to compare them, even if only one of them changed.
+    products: Option<Vec<Product>>,
+}
+
+// This is a single request, before its body is parsed.
+struct Fetched {
+    status: u16,
+    body: String,
+    unchanged: bool,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+
+    // The body is kept as it was received, so that it can be recorded.
+    pub body: String,
+
+    // This is the size of the body, in bytes.
+    pub bytes: usize,
+
+    // This is set if the store replied with `304 Not Modified`, or with
+    // the same body as the previous time, in which case the products
+    // weren't parsed, and the previous ones still apply.
+    pub unchanged: bool,
+
+    // The products are only parsed if the store replied with `200 OK`,
+    // as the other responses are HTML pages, and if the body changed.
+    pub products: Result<Vec<Product>, String>,
+
+    // When the store is polled through its collections, this holds the
+    // collection each product was found in, using their IDs.
+    pub collections: HashMap<u64, String>,
+}
+
+impl Poller {
+    pub fn new(url: &str) -> Poller {
+        Poller {
+            client: Client::new(),
+            url: url.trim_end_matches('/').into(),
+            collections: vec![],
+            full: false,
+            cache: HashMap::new(),
+        }
+    }
+
+    // The products are only parsed in full if this is set, as only the
+    // stores whose channels filter them, or show their details, need it.
+    pub fn full(mut self, full: bool) -> Poller {
+        self.full = full;
+        self
+    }
+
+    // Only these collections are fetched, instead of `/products.json`,
+    // if there are any.
+    pub fn collections(mut self, collections: Vec<String>) -> Poller {
+        self.collections = collections;
+        self
+    }
+
+    // This only fails if the store couldn't be reached at all.
+    pub async fn poll(&mut self) -> Result<Response, reqwest::Error> {
+        if !self.collections.is_empty() {
+            return self.poll_collections().await;
+        }
+
+        // The endpoint for all Shopify store is `/products.json`, so it
+        // has to be added to the website's URL to get the link to it.
+        let fetched = self
+            .fetch(
+                /* format!("{}/products.json?limit=100", */
+                format!("{}/products.json", self.url),
+            )
+            .await?;
+
+        let products = if fetched.unchanged {
+            Err(UNCHANGED.into())
+        } else {
+            parse(fetched.status, &fetched.body, self.full)
+        };
+
+        Ok(Response {
+            status: fetched.status,
+            bytes: fetched.body.len(),
+            body: fetched.body,
+            unchanged: fetched.unchanged,
+            products,
+            collections: HashMap::new(),
+        })
+    }
+
+    async fn poll_collections(&mut self) -> Result<Response, reqwest::Error> {
+        let mut bytes = 0;
+        let mut unchanged = true;
+        let mut products = vec![];
+        let mut collections = HashMap::new();
+
+        for collection in self.collections.clone() {
+            let mut seen = HashSet::new();
+
+            for page in 1..=PAGES {
+                let url = format!(
+                    "{}/collections/{}/products.json?limit={}&page={}",
+                    self.url, collection, LIMIT, page
+                );
+                let fetched = self.fetch(url.clone()).await?;
+                bytes += fetched.body.len();
+
+                // Any other response, such as the password page or a rate
+                // limit, is returned as it is, as the products can't be
+                // compared without every page.
+                if fetched.status != 200 && fetched.status != 304 {
+                    return Ok(Response {
+                        status: fetched.status,
+                        bytes,
+                        products: parse(fetched.status, &fetched.body, self.full),
+                        body: fetched.body,
+                        unchanged: false,
+                        collections: HashMap::new(),
+                    });
+                }
+
+                let cached = self
+                    .cache
+                    .get(&url)
+                    .and_then(|cache| cache.products.clone());
+                let current = match cached {
+                    Some(current) if fetched.unchanged => current,
+                    _ => match parse(200, &fetched.body, self.full) {
+                        Ok(current) => {
+                            if let Some(cache) = self.cache.get_mut(&url) {
+                                cache.products = Some(current.clone());
+                            }
+                            current
+                        }
+                        Err(e) => {
+                            return Ok(Response {
+                                status: fetched.status,
+                                bytes,
+                                body: fetched.body,
+                                unchanged: false,
+                                products: Err(e),
+                                collections: HashMap::new(),
+                            });
+                        }
+                    },
+                };
+
+                unchanged &= fetched.unchanged;
+
+                let last = current.len() < LIMIT
+                    || current
+                        .first()
+                        .is_some_and(|product| seen.contains(&product.id));
+
+                for product in current {
+                    seen.insert(product.id);
+
+                    // Products in several collections are labelled with
+                    // the first one they're found in.
+                    if let Entry::Vacant(entry) = collections.entry(product.id) {
+                        entry.insert(collection.clone());
+                        products.push(product);
+                    }
+                }
+
+                if last {
+                    break;
+                }
+            }
+        }
+
+        if unchanged {
+            return Ok(Response {
+                status: 200,
+                bytes,
+                body: String::new(),
+                unchanged,
+                products: Err(UNCHANGED.into()),
+                collections: HashMap::new(),
+            });
+        }
+
+        // The body combines every page, so that the recordings of stores
+        // polled through their collections can be replayed too.
+        let body = serde_json::to_string(&File {
+            products: products.clone(),
+        })
+        .unwrap_or_default();
+
+        Ok(Response {
+            status: 200,
+            bytes,
+            body,
+            unchanged,
+            products: Ok(products),
+            collections,
+        })
+    }
+
+    // This sends a conditional request if the page was fetched before,
+    // and finds out whether its body changed.
+    async fn fetch(&mut self, url: String) -> Result<Fetched, reqwest::Error> {
+        let cache = self.cache.entry(url.clone()).or_default();
+        let mut headers = HeaderMap::new();
+
+        if let Some(etag) = cache.etag.as_ref().and_then(|etag| etag.parse().ok()) {
+            headers.insert("if-none-match", etag);
+        }
+        if let Some(date) = cache
+            .last_modified
+            .as_ref()
+            .and_then(|date| date.parse().ok())
+        {
+            headers.insert("if-modified-since", date);
+        }
+
+        let res = self.request(url.clone()).headers(headers).send().await?;
+
+        let status = res.status().as_u16();
+        let header = |name: &str| {
+            res.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let (etag, last_modified) = (header("etag"), header("last-modified"));
+
+        // If the body can't be read, it's treated as invalid JSON, as the
+        // store could still be reached.
+        let body = res.text().await.unwrap_or_default();
+        let cache = self.cache.entry(url).or_default();
+
+        let unchanged = match status {
+            304 => true,
+            200 => {
+                let hash = hash(&body);
+                let unchanged = cache.hash == Some(hash);
+
+                // The products are kept until the body changes.
+                if !unchanged {
+                    cache.products = None;
+                }
+
+                cache.etag = etag;
+                cache.last_modified = last_modified;
+                cache.hash = Some(hash);
+
+                unchanged
+            }
+
+            // Any other response, such as the password page, means the
+            // next `200 OK` has to be parsed, even if it's the same as
+            // the last one, so that the products are compared again.
+            _ => {
+                *cache = Cache::default();
+                false
+            }
+        };
+
+        Ok(Fetched {
+            status,
+            body,
+            unchanged,
+        })
+    }
+
+    // This fetches a single product from its page, for the watchlist,
+    // returning `None` if the store doesn't have it (yet), and an error if
+    // that couldn't be told, such as when the password page is up.
+    pub async fn product(&self, handle: &str) -> Result<Option<Product>, String> {
+        let res = self
+            .request(format!("{}/products/{}.js", self.url, handle))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match res.status().as_u16() {
+            200 => {
+                let body = res.text().await.map_err(|e| e.to_string())?;
+
+                serde_json::from_str::<Page>(&body)
+                    .map(|page| Some(page.into()))
+                    .map_err(|e| e.to_string())
+            }
+            404 => Ok(None),
+            status => Err(format!("The store replied with a {} status code.", status)),
+        }
+    }
+
+    // This fetches any other page of the store, such as its Atom feed,
+    // returning its body if the store replied with `200 OK`.
+    pub async fn page(&self, path: &str) -> Result<String, String> {
+        let res = self
+            .request(format!("{}{}", self.url, path))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match res.status().as_u16() {
+            200 => res.text().await.map_err(|e| e.to_string()),
+            status => Err(format!("The store replied with a {} status code.", status)),
+        }
+    }
+
+    fn request(&self, url: String) -> RequestBuilder {
+        self.client
+            .get(url)
+            // For this first version, I simply "borrowed" the "Safe
+            // Headers" used in his JavaScript Shopify Monitor, however I
+            // will experiment with more techniques to avoid bot detection
+            // later. Here's the link to his repository:
+            // https://github.com/aarock1234/shopify-monitor/blob/master/src/class/monitor.js.
+            .header("pragma", "no-cache")
+            .header("cache-control", "no-cache")
+            .header("upgrade-insecure-requests", "1")
+            .header("user-agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/86.0.4240.198 Safari/537.36")
+            .header("accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9")
+            .header("sec-fetch-site", "none")
+            .header("sec-fetch-mode", "navigate")
+            .header("sec-fetch-user", "?1")
+            .header("sec-fetch-dest", "document")
+            .header("accept-language", "en-US,en;q=0.9")
+    }
+}
+
+// The hash only has to tell consecutive bodies apart, so the standard
+// library's hasher is enough.
+fn hash(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+// This function is also used to parse the responses that were recorded,
+// so that they're handled in the same way when they're replayed.
+pub fn parse(status: u16, body: &str, full: bool) -> Result<Vec<Product>, String> {
+    if status == 200 {
+        products::parse(body, full).map_err(|e| e.to_string())
+    } else {
+        Err(format!("The store replied with a {} status code.", status))
+    }
+}
+
+const UNCHANGED: &str = "The products haven't changed.";
+
+// This is what `watch()` keeps track of between requests.
+struct State {
+    poller: Poller,
+    interval: Interval,
+    detector: Detector,
+
+    // A single request can cause several events, which are returned one
+    // at a time.
+    queue: VecDeque<Event>,
+}
+
+// This function polls a store every `delay`, returning the events it
+// detects with the same rules as the monitor, but without sending any
+// webhooks. Errors are ignored, as the store is simply polled again.
+pub fn watch(store: Store, delay: Duration) -> impl Stream<Item = Event> {
+    let state = State {
+        poller: Poller::new(&store.url),
+        interval: time::interval(delay),
+        detector: Detector::new(store),
+        queue: VecDeque::new(),
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.queue.pop_front() {
+                return Some((event, state));
+            }
+
+            state.interval.tick().await;
+
+            if let Ok(res) = state.poller.poll().await {
+                let events = state
+                    .detector
+                    .detect(res.status, res.products.as_deref().ok());
+
+                state.queue.extend(events);
+            }
+        }
+    })
+}
@@ -6,78 +6,145 @@
 // Still, some of them may become useful in the future, either for this
 // monitor or in other projects, so I won't remove them.
 
-use serde::Deserialize;
+// The types can also be serialized, as they're included in the events
+// published by `crate::event`.
 
-#[derive(Deserialize)]
+// Some of those fields turned out to be useful after all, such as the
+// tags used to filter releases, and the images of each colorway, so
+// they're back. They're only read when a store is parsed in full though,
+// as most stores don't need them, and `products.json` is parsed with the
+// lean types at the bottom of this file otherwise, which skip them.
+
+use serde::{Deserialize, Serialize};
+
+// This parses the body of `products.json`, either in full or only
+// reading the fields the monitor always needs, in which case the other
+// ones keep their default values.
+pub fn parse(body: &str, full: bool) -> serde_json::Result<Vec<Product>> {
+    if full {
+        serde_json::from_str::<File>(body).map(|file| file.products)
+    } else {
+        serde_json::from_str::<LeanFile>(body)
+            .map(|file| file.products.into_iter().map(Product::from).collect())
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct File {
     pub products: Vec<Product>,
 }
 
-#[derive(Deserialize)]
+// The fields that are only read when parsing in full have default
+// values, so that the same types can hold the products parsed either way.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
 pub struct Product {
     pub id: u64,
     pub title: String,
     pub handle: String,
     /* pub body_html: String, */
-    /* pub published_at: String, */
-    /* pub created_at: String, */
+    #[serde(default)]
+    pub published_at: String,
+    #[serde(default)]
+    pub created_at: String,
     pub updated_at: String,
     pub vendor: String,
-    /* pub product_type: String, */
-    /* pub tags: Vec<String>, */
+    #[serde(default)]
+    pub product_type: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub variants: Vec<Variant>,
     pub images: Vec<Image>,
-    /* pub options: Vec<ProductOption>, */
+    #[serde(default)]
+    pub options: Vec<ProductOption>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
 pub struct Variant {
     pub id: u64,
     pub title: String,
-    /* pub option1: String, */
-    /* pub option2: Option<String>, */
-    /* pub option3: Option<String>, */
-    /* pub sku: String, */
+
+    // Products with a single option, such as the size, only set the
+    // first one, and the others are `null`.
+    #[serde(default)]
+    pub option1: Option<String>,
+    #[serde(default)]
+    pub option2: Option<String>,
+    #[serde(default)]
+    pub option3: Option<String>,
+    #[serde(default)]
+    pub sku: Option<String>,
     /* pub requires_shipping: bool, */
     /* pub taxable: bool, */
-
     // // In the `products.json` files I've checked so far,
     // // `featured_image` always has a `null` value, therefore I'm not
     // // sure what its type is. I will temporarily assume it's a string
     // // until I find out.
     // pub featured_image: Option<String>,
 
-    // Turns out its not a string but a map...
-    /* pub featured_image: Option<FeaturedImage>, */
+    // Turns out its not a string but a map, which is set when the
+    // variant has its own image, such as a colorway of a sneaker.
+    #[serde(default)]
+    pub featured_image: Option<FeaturedImage>,
     pub available: bool,
     pub price: String,
-    /* pub grams: u32, */
 
-    // The same applies for `compare_at_price`.
-    /* pub compare_at_price: Option<String>, */
+    // `products.json` never includes this, but the pages of some stores
+    // do, so it's only set for the products found by the watchlist.
+    #[serde(default)]
+    pub inventory_quantity: Option<i64>,
+    /* pub grams: u32, */
+    // This is only set while the variant is on sale, and is `null`
+    // otherwise.
+    #[serde(default)]
+    pub compare_at_price: Option<String>,
     /* pub position: u32, */
     /* pub product_id: u64, */
     /* pub created_at: String, */
     /* pub updated_at: String, */
 }
 
-/*
-#[derive(Deserialize)]
+impl Variant {
+    // Some websites have very weird variant names. UNDEFEATED, for example,
+    // prefixes their "sizes" with "- / ". `.trim_prefix()` cannot be used to
+    // correct this, as it would only work for specific cases. By removing
+    // all special characters, so that the name only contains letters,
+    // numbers, and whitespace, almost all of these strange names can be
+    // "normalized".
+    pub fn name(&self) -> String {
+        self.title
+            // The string is split into characters.
+            .chars()
+            // The split is transformed into a vector.
+            .collect::<Vec<char>>()
+            // The program can now iterate through each char.
+            .iter()
+            // "Invalid" characters are removed.
+            .filter(|c| c.is_alphanumeric() || c.is_whitespace() || c == &&'.')
+            // The filtered characters are collected into a string.
+            .collect::<String>()
+            // Leading and trailing whitespace is removed.
+            .trim()
+            // The returned `&str` is converted to a `String`.
+            .into()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
 pub struct FeaturedImage {
     pub id: u64,
     pub product_id: u64,
     pub position: u32,
     pub created_at: String,
     pub updated_at: String,
-    pub alt: String,
+    pub alt: Option<String>,
     pub width: u32,
     pub height: u32,
     pub src: String,
     pub variant_ids: Vec<u64>,
 }
-*/
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
 pub struct Image {
     /* pub id: u64, */
     /* pub created_at: String, */
@@ -94,11 +161,161 @@ pub struct Image {
     /* pub height: u32, */
 }
 
-/*
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
 pub struct ProductOption {
     pub name: String,
     pub position: u32,
     pub values: Vec<String>,
 }
-*/
+
+// This models `/products/{handle}.js`, which describes a single product
+// on its own page, and is used by the watchlist. It lists the same
+// details as `products.json`, but prices are in cents and images are
+// links without a scheme, so it's converted into a `Product`. While
+// `/products/{handle}.json` exists too, its variants don't say whether
+// they're available, so it can't be used to find restocks.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Page {
+    pub id: u64,
+    pub title: String,
+    pub handle: String,
+    #[serde(default)]
+    pub published_at: String,
+    #[serde(default)]
+    pub created_at: String,
+    pub vendor: String,
+    #[serde(default, rename = "type")]
+    pub product_type: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub variants: Vec<PageVariant>,
+    pub images: Vec<String>,
+    #[serde(default)]
+    pub options: Vec<ProductOption>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PageVariant {
+    pub id: u64,
+    pub title: String,
+    #[serde(default)]
+    pub option1: Option<String>,
+    #[serde(default)]
+    pub option2: Option<String>,
+    #[serde(default)]
+    pub option3: Option<String>,
+    #[serde(default)]
+    pub sku: Option<String>,
+    #[serde(default)]
+    pub featured_image: Option<FeaturedImage>,
+    pub available: bool,
+    pub price: u64,
+    pub inventory_quantity: Option<i64>,
+    #[serde(default)]
+    pub compare_at_price: Option<u64>,
+}
+
+impl From<Page> for Product {
+    fn from(page: Page) -> Product {
+        Product {
+            id: page.id,
+            title: page.title,
+            handle: page.handle,
+            published_at: page.published_at,
+            created_at: page.created_at,
+            updated_at: "".into(),
+            vendor: page.vendor,
+            product_type: page.product_type,
+            tags: page.tags,
+            variants: page
+                .variants
+                .into_iter()
+                .map(|variant| Variant {
+                    id: variant.id,
+                    title: variant.title,
+                    option1: variant.option1,
+                    option2: variant.option2,
+                    option3: variant.option3,
+                    sku: variant.sku,
+                    featured_image: variant.featured_image.map(|image| FeaturedImage {
+                        src: https(image.src),
+                        ..image
+                    }),
+                    available: variant.available,
+                    price: cents(variant.price),
+                    inventory_quantity: variant.inventory_quantity,
+                    compare_at_price: variant.compare_at_price.map(cents),
+                })
+                .collect(),
+            images: page
+                .images
+                .into_iter()
+                .map(|src| Image { src: https(src) })
+                .collect(),
+            options: page.options,
+        }
+    }
+}
+
+fn cents(price: u64) -> String {
+    format!("{}.{:02}", price / 100, price % 100)
+}
+
+fn https(src: String) -> String {
+    if src.starts_with("//") {
+        format!("https:{}", src)
+    } else {
+        src
+    }
+}
+
+// These are the lean versions of the types above, which only include
+// the fields the monitor needs to find restocks and send its messages.
+#[derive(Deserialize)]
+struct LeanFile {
+    products: Vec<LeanProduct>,
+}
+
+#[derive(Deserialize)]
+struct LeanProduct {
+    id: u64,
+    title: String,
+    handle: String,
+    updated_at: String,
+    vendor: String,
+    variants: Vec<LeanVariant>,
+    images: Vec<Image>,
+}
+
+#[derive(Deserialize)]
+struct LeanVariant {
+    id: u64,
+    title: String,
+    available: bool,
+    price: String,
+}
+
+impl From<LeanProduct> for Product {
+    fn from(product: LeanProduct) -> Product {
+        Product {
+            id: product.id,
+            title: product.title,
+            handle: product.handle,
+            updated_at: product.updated_at,
+            vendor: product.vendor,
+            variants: product
+                .variants
+                .into_iter()
+                .map(|variant| Variant {
+                    id: variant.id,
+                    title: variant.title,
+                    available: variant.available,
+                    price: variant.price,
+                    ..Default::default()
+                })
+                .collect(),
+            images: product.images,
+            ..Default::default()
+        }
+    }
+}
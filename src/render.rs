@@ -0,0 +1,716 @@
+// This module turns events into what's sent to each channel: either a
+// Discord message, built using the channel's settings and template, or
+// the JSON passed to the program run by channels with a `command`.
+// Nothing is sent from here, so the messages can be checked without
+// sending any requests.
+
+use crate::{
+    event::Event,
+    message::*,
+    monitor::elapsed,
+    products::Product,
+    stores::{Mention, Settings},
+    template::{self, render, Template},
+};
+use chrono::prelude::*;
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+// Since the monitor will check which variants are available for a
+// certain product before sending a webhook, a different struct is
+// needed to render the messages, or the program will have to
+// perform those checks again. `AvailableProduct` will therefore only
+// contain the product's information that should be included in the
+// webhook's embed, as well as a vector containing the available
+// variants.
+#[derive(PartialEq, Debug)]
+pub struct AvailableProduct {
+    // The ID is used to find the message that was sent for the product,
+    // if the channel it's sent to edits its messages.
+    pub id: u64,
+    pub name: String,
+
+    // The product's handle can be used to obtain the product link as
+    // follows: `format!("{}/products/{}", store_url, handle)`.
+    pub handle: String,
+    pub brand: String,
+    pub price: String,
+
+    // I changed this to an `Option` as for some reason (which I can't
+    // remember) I was using an empty `String` instead of `None` if the
+    // product didn't have a photo.
+    pub image: Option<String>,
+    pub variants: Vec<AvailableVariant>,
+
+    // These are only known if the store's products are parsed in full,
+    // and are empty otherwise. The price before the sale is the first
+    // variant's, like `price`.
+    pub product_type: String,
+    pub tags: Vec<String>,
+    pub published_at: String,
+    pub compare_at_price: Option<String>,
+}
+
+// There's no need to make unnecessary operations or clone unused data,
+// so this struct holds the bare minimum. Since some values
+#[derive(PartialEq, Debug)]
+pub struct AvailableVariant {
+    pub name: String,
+    pub id: u64,
+
+    // This is only true for the sizes that just restocked, as opposed to
+    // the ones that were already available.
+    pub restocked: bool,
+
+    // Only some stores show how many units of each size are left.
+    pub stock: Option<i64>,
+    pub sku: Option<String>,
+
+    // This is the image of the variant's colorway, if it has its own.
+    pub image: Option<String>,
+}
+
+// Why do two `struct`s for both "Minimal" and "Available" Products and
+// Variants exist, if they share the goal to reduce memory usage and are
+// so similar? Their similarity is due to their common goal, but they
+// aim for it in different contexts. The "Minimal" `struct`s are the
+// smallest, and contain the data required for the program to check for
+// product updates. The "Available" `struct`s, on the other hand, hold
+// different data types, as they include the product details used to
+// form webhook embeds. As a result, both types are needed.
+
+pub fn available_product(
+    curr: &Product, /*, prev: Option<&Vec<MinimalVariant>>*/
+    restocked: &[u64],
+) -> Arc<AvailableProduct> {
+    let mut variants: Vec<AvailableVariant> = vec![];
+
+    let price = if let Some(v) = curr.variants.first() {
+        v.price.clone()
+    } else {
+        // The default price value is "?" because it must be at least 1
+        // character long.
+        "?".into()
+    };
+
+    /*
+    let image = if let Some(img) = curr.images.first() {
+        Some(img.src.clone())
+    } else {
+        None
+    };
+    */
+
+    // Products that come in several colorways often have an image for
+    // each of them, in which case the one of the first size that just
+    // restocked is shown, so that the message shows the right colorway.
+    let image = curr
+        .variants
+        .iter()
+        .filter(|variant| variant.available && restocked.contains(&variant.id))
+        .find_map(|variant| variant.featured_image.as_ref())
+        .map(|image| image.src.clone())
+        .or_else(|| curr.images.first().map(|img| img.src.clone()));
+
+    for variant in curr.variants.iter() {
+        if variant.available {
+            variants.push(AvailableVariant {
+                name: variant.name(),
+                id: variant.id,
+                restocked: restocked.contains(&variant.id),
+                stock: variant.inventory_quantity,
+                sku: variant.sku.clone(),
+                image: variant
+                    .featured_image
+                    .as_ref()
+                    .map(|image| image.src.clone()),
+            });
+        }
+    }
+
+    Arc::from(AvailableProduct {
+        id: curr.id,
+        name: curr.title.clone(),
+        handle: curr.handle.clone(),
+        brand: curr.vendor.clone(),
+        price,
+        image,
+        variants,
+        product_type: curr.product_type.clone(),
+        tags: curr.tags.clone(),
+        published_at: curr.published_at.clone(),
+        compare_at_price: curr
+            .variants
+            .first()
+            .and_then(|v| v.compare_at_price.clone()),
+    })
+}
+
+// The enum is named `Item`, and not `Product`, because the `Product`
+// name is already used by `crate::products::Product`, which is named
+// after `products.json`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Item {
+    New,
+    Restock,
+
+    // This isn't a "real" event, as it's only used to edit the messages
+    // that were sent for the other two.
+    SoldOut,
+}
+
+impl Item {
+    // Password page events aren't about a product.
+    pub fn of(event: &Event) -> Option<Item> {
+        match event {
+            Event::NewProduct { .. } => Some(Item::New),
+            Event::Restock { .. } => Some(Item::Restock),
+            Event::SoldOut { .. } => Some(Item::SoldOut),
+            Event::PasswordUp { .. } | Event::PasswordDown { .. } => None,
+        }
+    }
+}
+
+// This is the message that was sent to a channel for a product, which
+// is edited instead of sending a new one if the channel has `edit`
+// enabled.
+#[derive(Clone, Debug)]
+pub struct Posted {
+    pub id: String,
+    pub time: Instant,
+    pub kind: Item,
+    pub product: Arc<AvailableProduct>,
+}
+
+// This function renders the message sent for a new product, a restock or
+// a size selling out, returning `None` for password page events. If the
+// product sold out, the message that was previously sent for it has to
+// be included, as it's the one being updated, so nothing is returned if
+// there isn't one.
+pub fn item(event: &Event, settings: &Settings, posted: Option<&Posted>) -> Option<Message> {
+    let current = Item::of(event)?;
+    let store = event.store();
+    let product = available_product(event.product()?, event.restocked());
+
+    if current == Item::SoldOut && posted.is_none() {
+        return None;
+    }
+
+    // When a product sells out, the sizes that were listed in the
+    // original message are kept, so that the ones which are no longer
+    // available can be struck through, instead of disappearing.
+    let (listed, kind) = match (current, posted) {
+        (Item::SoldOut, Some(posted)) => (posted.product.clone(), posted.kind),
+        _ => (product.clone(), current),
+    };
+
+    // This is only true when none of the product's variants are still
+    // available.
+    let sold_out = current == Item::SoldOut && product.variants.is_empty();
+
+    // If the channel doesn't use a custom template, the default one
+    // produces the same embeds the monitor has always sent.
+    let template = settings
+        .template
+        .clone()
+        .unwrap_or_else(|| Arc::new(Template::new()));
+
+    // These are the values of the placeholders that can be used in the
+    // template, while the ones related to each variant are added later.
+    let mut values = HashMap::new();
+    values.insert(
+        "event",
+        if sold_out {
+            "Sold Out"
+        } else if kind == Item::New {
+            "New Product"
+        } else {
+            "Restock"
+        }
+        .to_string(),
+    );
+    values.insert("price", product.price.clone());
+    values.insert(
+        "compare_at_price",
+        product.compare_at_price.clone().unwrap_or_default(),
+    );
+    values.insert("product.title", product.name.clone());
+    values.insert("product.handle", product.handle.clone());
+    values.insert(
+        "product.url",
+        format!("{}/products/{}", store.url, product.handle),
+    );
+    values.insert("product.brand", product.brand.clone());
+    values.insert("product.image", product.image.clone().unwrap_or_default());
+    values.insert("product.type", product.product_type.clone());
+    values.insert("product.tags", product.tags.join(", "));
+    values.insert("product.published_at", product.published_at.clone());
+    values.insert("store.name", store.name.clone());
+    values.insert("store.url", store.url.clone());
+    values.insert("store.logo", store.logo.clone());
+    values.insert(
+        "collection",
+        event.collection().unwrap_or_default().to_string(),
+    );
+
+    let embed = Embed {
+        title: Some(render(
+            template.title.as_deref().unwrap_or(template::ITEM_TITLE),
+            &values,
+        )),
+        description: template
+            .description
+            .as_ref()
+            .map(|text| render(text, &values))
+            .filter(|text| !text.trim().is_empty()),
+        url: Some(render(
+            template.url.as_deref().unwrap_or(template::ITEM_URL),
+            &values,
+        ))
+        .filter(|url| !url.trim().is_empty()),
+        color: settings.color,
+        fields: {
+            let quantity = if settings.sizes {
+                /*
+                let len = 3 + product.variants.len();
+
+                if len % 3 == 2 {
+                  len + 4
+                }
+
+                len + 3
+                */
+
+                // Since the checks for the number of variants (above
+                // this comment) were removed, the number of fields
+                // is always a couple more than the number of variants
+                // and the template's fields, and the vector holding
+                // them occasionally has a slightly larger capacity than
+                // necessary.
+                template.fields.len() + listed.variants.len() + 2
+            } else {
+                template.fields.len() + 1
+            };
+
+            // The value calculated above is used to preallocate the
+            // correct amount of space in the heap to hold this
+            // vector, slightly improving performance.
+            let mut fields = Vec::with_capacity(quantity);
+
+            for field in template.fields.iter() {
+                fields.push(Field {
+                    name: filled(&field.name, &values),
+                    inline: Some(field.inline),
+                    value: filled(&field.value, &values),
+                });
+            }
+
+            // The time is measured from when the message was last sent
+            // or edited to announce the product's availability.
+            if sold_out {
+                if let Some(posted) = posted {
+                    fields.push(Field {
+                        name: "Sold Out After".into(),
+                        inline: Some(true),
+                        value: elapsed(posted.time.elapsed()),
+                    });
+                }
+            }
+
+            /* hidden!("{} has {} updated variants!", product.name, product.variants.len()); */
+
+            if settings.sizes {
+                for variant in (*listed.variants).iter() {
+                    let mut values = values.clone();
+                    values.insert("variant.title", variant.name.clone());
+                    values.insert("variant.id", variant.id.to_string());
+                    values.insert(
+                        "variant.stock",
+                        variant
+                            .stock
+                            .map_or_else(String::new, |stock| format!("{} left", stock)),
+                    );
+                    values.insert("variant.sku", variant.sku.clone().unwrap_or_default());
+                    values.insert("variant.image", variant.image.clone().unwrap_or_default());
+                    values.insert(
+                        "atc_url",
+                        format!("{}/cart/add?id={}", store.url, variant.id),
+                    );
+
+                    // The sizes of a restock are split between the ones
+                    // that just became available and the rest, while the
+                    // other messages don't tell them apart.
+                    values.insert(
+                        "variant.status",
+                        match kind {
+                            Item::Restock if variant.restocked => "Restocked",
+                            Item::Restock => "Still Available",
+                            _ => "",
+                        }
+                        .to_string(),
+                    );
+
+                    let name = filled(&template.size.name, &values);
+                    let value = filled(&template.size.value, &values);
+
+                    // Sizes that are no longer available are struck
+                    // through using Discord's Markdown.
+                    if product.variants.iter().any(|v| v.id == variant.id) {
+                        fields.push(Field {
+                            name,
+                            inline: Some(template.size.inline),
+                            value,
+                        });
+                    } else {
+                        fields.push(Field {
+                            name: format!("~~{}~~", name),
+                            inline: Some(template.size.inline),
+                            value: format!("~~{}~~", value),
+                        });
+                    }
+                }
+
+                // When the bottom row of a Discord embed
+                // has two fields, it is aligned differently from
+                // the other rows, which some users consider
+                // displeasing. As a test, it is currently always
+                // "corrected" by the program, which adds an
+                // invisible field when necessary. In a future
+                // update, a toggle may be added allowing users to
+                // opt out of this behavior.
+                if fields.len() % 3 == 2 {
+                    fields.push(Field {
+                        // The characters held by the `name` and
+                        // `value` fields are the `U+2800` "Braille
+                        // Pattern Blank" character, which can be
+                        // used to fool Discord into thinking that
+                        // they aren't blank.
+                        name: '⠀'.into(),
+                        inline: Some(true),
+                        value: '⠀'.into(),
+                    });
+                }
+            }
+
+            Some(fields)
+        },
+        // The collection the product was found in, if the store is
+        // monitored through its collections, is shown next to its name.
+        author: Some(Author {
+            name: match event.collection() {
+                Some(collection) => format!("{} | {}", store.name, collection),
+                None => store.name.clone(),
+            },
+            url: Some(store.url.clone()),
+            icon_url: Some(store.logo.clone()),
+        }),
+        footer: {
+            // The program doesn't check if a footer image was
+            // included, as if a timestamp or footer text
+            // weren't, it won't be rendered regardless.
+            if settings.footer_text.is_some() || settings.timestamp {
+                Some(Footer {
+                    text: settings.footer_text.clone(),
+                    icon_url: settings.footer_image.clone(),
+                })
+            } else {
+                None
+            }
+        },
+        timestamp: {
+            if settings.timestamp {
+                Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true))
+            } else {
+                None
+            }
+        },
+        image: {
+            // This isn't very elegant, but I copied it from the
+            // `thumbnail` field below where it was the only
+            // solution I found.
+
+            let mut img = None;
+            if settings.image && product.image.is_some() {
+                img = Some(Image {
+                    url: product.image.clone().expect("Failed to extract Image URL."),
+                });
+            }
+            img
+        },
+        thumbnail: {
+            let mut tn = None;
+            if settings.thumbnail && product.image.is_some() {
+                tn = Some(Thumbnail {
+                    url: product.image.clone().expect("Failed to extract Image URL."),
+                });
+            }
+            tn
+        },
+    };
+
+    // The first keyword group matching the product that changes who is
+    // pinged takes priority over the channel's `mention` setting.
+    let title = &product.name;
+    let mention = settings
+        .keywords
+        .iter()
+        .find(|keyword| keyword.mention.is_some() && keyword.matches(title))
+        .map_or(settings.mention.clone(), |keyword| {
+            keyword.mention.clone().unwrap_or_default()
+        });
+
+    let (content, allowed_mentions) = mentions(
+        mention.as_deref(),
+        template.content.as_ref().map(|text| render(text, &values)),
+    );
+
+    // Products with a lot of sizes or long names could exceed the
+    // limits set by Discord, so the message has to be adjusted.
+    Some(
+        Message {
+            content,
+            embeds: Some(vec![embed]),
+            username: settings.username.clone(),
+            avatar_url: settings.avatar.clone(),
+            allowed_mentions,
+        }
+        .fit(),
+    )
+}
+
+// Only the content, title, URL and description of a template are used
+// for password page embeds, as they don't include any fields. `None` is
+// returned if the event isn't about the password page.
+pub fn password(event: &Event, settings: &Settings) -> Option<Message> {
+    let up = match event {
+        Event::PasswordUp { .. } => true,
+        Event::PasswordDown { .. } => false,
+        _ => return None,
+    };
+    let store = event.store();
+
+    let template = settings
+        .template
+        .clone()
+        .unwrap_or_else(|| Arc::new(Template::new()));
+
+    let mut values = HashMap::new();
+    values.insert(
+        "event",
+        if up {
+            "Password Page Up"
+        } else {
+            "Password Page Down"
+        }
+        .to_string(),
+    );
+    values.insert("store.name", store.name.clone());
+    values.insert("store.url", store.url.clone());
+    values.insert("store.logo", store.logo.clone());
+
+    // The parts of the template written for products, which use their
+    // placeholders, are replaced with the usual ones, or left out.
+    fn part(text: &Option<String>) -> Option<&str> {
+        text.as_deref().filter(|text| template::for_password(text))
+    }
+
+    let embed = Embed {
+        title: Some(render(
+            part(&template.title).unwrap_or(template::PASSWORD_TITLE),
+            &values,
+        ))
+        .filter(|title| !title.trim().is_empty()),
+        description: part(&template.description)
+            .map(|text| render(text, &values))
+            .filter(|text| !text.trim().is_empty()),
+        url: Some(render(
+            part(&template.url).unwrap_or(template::PASSWORD_URL),
+            &values,
+        ))
+        .filter(|url| !url.trim().is_empty()),
+        color: settings.color,
+        fields: None,
+        author: Some(Author {
+            name: store.name.clone(),
+            url: Some(store.url.clone()),
+            icon_url: Some(store.logo.clone()),
+        }),
+        footer: {
+            // The program doesn't check if a footer image was included,
+            // as if a timestamp or footer text weren't, it won't be
+            // rendered regardless.
+            if settings.footer_text.is_some() || settings.timestamp {
+                Some(Footer {
+                    text: settings.footer_text.clone(),
+                    icon_url: settings.footer_image.clone(),
+                })
+            } else {
+                None
+            }
+        },
+        timestamp: {
+            if settings.timestamp {
+                Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true))
+            } else {
+                None
+            }
+        },
+        image: None,
+        thumbnail: None,
+    };
+
+    let (content, allowed_mentions) = mentions(
+        settings.mention.as_deref(),
+        part(&template.content).map(|text| render(text, &values)),
+    );
+
+    // While this embed is a lot simpler, a long footer or store name
+    // could still exceed Discord's limits.
+    Some(
+        Message {
+            content,
+            embeds: Some(vec![embed]),
+            username: settings.username.clone(),
+            avatar_url: settings.avatar.clone(),
+            allowed_mentions,
+        }
+        .fit(),
+    )
+}
+
+// Channels that run a command receive the event's details instead of an
+// embed, including the sizes that are available and the links to add
+// them to the cart.
+pub fn command(event: &Event) -> Value {
+    let store = event.store();
+
+    let mut value = json!({
+        "event": event.name(),
+        "store": {
+            "name": store.name,
+            "url": store.url,
+            "logo": store.logo,
+        },
+    });
+
+    if let Some(product) = event.product() {
+        let product = available_product(product, event.restocked());
+
+        value["product"] = json!({
+            "id": product.id,
+            "title": product.name,
+            "handle": product.handle,
+            "url": format!("{}/products/{}", store.url, product.handle),
+            "brand": product.brand,
+            "price": product.price,
+            "compare_at_price": product.compare_at_price,
+            "image": product.image,
+            "type": product.product_type,
+            "tags": product.tags,
+            "published_at": product.published_at,
+        });
+        value["collection"] = json!(event.collection());
+        value["variants"] = product
+            .variants
+            .iter()
+            .map(|variant| {
+                json!({
+                    "id": variant.id,
+                    "title": variant.name,
+                    "restocked": variant.restocked,
+                    "stock": variant.stock,
+                    "sku": variant.sku,
+                    "image": variant.image,
+                    "atc_url": format!("{}/cart/add?id={}", store.url, variant.id),
+                })
+            })
+            .collect();
+    }
+
+    value
+}
+
+// This function puts the pings at the start of the message's content,
+// and only allows Discord to notify the roles and users that were
+// selected. If no one should be pinged, the content is left untouched,
+// so that any mentions included in a template still work.
+fn mentions(
+    mention: Option<&Mention>,
+    content: Option<String>,
+) -> (Option<String>, Option<AllowedMentions>) {
+    let content = content.filter(|text| !text.trim().is_empty());
+
+    let mention = if let Some(mention) = mention {
+        mention
+    } else {
+        return (content, None);
+    };
+
+    let mut pings = vec![];
+
+    if mention.everyone {
+        pings.push("@everyone".to_string());
+    }
+
+    if mention.here {
+        pings.push("@here".to_string());
+    }
+
+    for role in &mention.roles {
+        pings.push(format!("<@&{}>", role));
+    }
+
+    for user in &mention.users {
+        pings.push(format!("<@{}>", user));
+    }
+
+    if pings.is_empty() {
+        return (content, None);
+    }
+
+    if let Some(text) = content {
+        pings.push(text);
+    }
+
+    (
+        Some(pings.join(" ")),
+        Some(AllowedMentions {
+            parse: if mention.everyone || mention.here {
+                vec!["everyone".into()]
+            } else {
+                vec![]
+            },
+            roles: mention.roles.clone(),
+            users: mention.users.clone(),
+        }),
+    )
+}
+
+// Discord doesn't allow the name or value of a field to be empty, which
+// could happen if a template only contains a placeholder for something
+// the product doesn't have, so blank ones are replaced with the same
+// invisible character used to align the fields. Lines are only dropped
+// if their placeholders left them blank, such as `{variant.status}` in
+// messages that aren't about a restock, or `{variant.stock}` for stores
+// that don't show it, so the rest of the template is kept as it is.
+fn filled(text: &str, values: &HashMap<&str, String>) -> String {
+    let text = text
+        .lines()
+        .filter_map(|line| {
+            let rendered = render(line, values);
+
+            if line.contains('{') && rendered.trim().is_empty() {
+                None
+            } else {
+                Some(rendered)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if text.trim().is_empty() {
+        '⠀'.into()
+    } else {
+        text
+    }
+}
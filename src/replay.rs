@@ -0,0 +1,318 @@
+// This module records the responses the monitor receives from each
+// store's `/products.json` endpoint, and replays them with the `replay`
+// command, which runs them through the same detector used by the monitor
+// to show the events that would have been sent, without sending any
+// webhooks. This makes it possible to find out what went wrong during a
+// drop, or to check changes to the diffing rules against a real one.
+
+// Each store gets its own folder, containing the store's details in
+// `store.json` and a file per response, named after the time it was
+// received (in milliseconds since the Unix epoch) and its status code,
+// such as `1640995200000-200.json`.
+
+use crate::{config, default, diff::Detector, error, event, hidden, poller, warning};
+use chrono::prelude::*;
+use colored::*;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+// This is the directory used when the config file doesn't include one.
+const DIRECTORY: &str = "recordings";
+
+// By default, an hour of recordings is replayed in a minute.
+const SPEED: f64 = 60.0;
+
+const STORE: &str = "store.json";
+
+pub struct Recorder {
+    directory: PathBuf,
+
+    // The stores whose folder has already been created, along with its
+    // `store.json` file, so that it's only done once.
+    created: Mutex<HashSet<String>>,
+}
+
+impl Recorder {
+    // Nothing is recorded unless `recording` is set in the config file.
+    pub fn new(recording: Option<config::Recording>) -> Option<Arc<Recorder>> {
+        let directory = recording?.directory.unwrap_or_else(|| DIRECTORY.into());
+
+        default!("Recording responses to `{}`...", directory);
+
+        Some(Arc::new(Recorder {
+            directory: directory.into(),
+            created: Mutex::new(HashSet::new()),
+        }))
+    }
+
+    // Failing to save a response is only logged, as it shouldn't stop
+    // the store from being monitored.
+    pub async fn save(&self, store: &event::Store, status: u16, body: &str) {
+        let folder = self.directory.join(folder(&store.name));
+
+        {
+            let mut created = self.created.lock().await;
+
+            if !created.contains(&store.name) {
+                let metadata = serde_json::to_string_pretty(store).unwrap_or_default();
+
+                let result = async {
+                    tokio::fs::create_dir_all(&folder).await?;
+                    tokio::fs::write(folder.join(STORE), metadata).await
+                }
+                .await;
+
+                if let Err(e) = result {
+                    warning!("Failed to create `{}`: {}", folder.display(), e);
+                    return;
+                }
+
+                created.insert(store.name.clone());
+            }
+        }
+
+        // Password pages and errors are HTML pages rather than JSON, so
+        // they're saved as such to make them easier to open.
+        let extension = if status == 200 { "json" } else { "html" };
+        let path = folder.join(format!(
+            "{}-{}.{}",
+            Utc::now().timestamp_millis(),
+            status,
+            extension
+        ));
+
+        if let Err(e) = tokio::fs::write(&path, body).await {
+            hidden!("Failed to save `{}`: {}", path.display(), e);
+        }
+    }
+}
+
+// Store names are used as folder names, so any character that could
+// cause issues in a path is replaced.
+fn folder(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// This is a single recorded response.
+struct Recording {
+    store: usize,
+    time: i64,
+    status: u16,
+    path: PathBuf,
+}
+
+const USAGE: &str = "Usage: shopify-monitor replay [DIRECTORY] [--speed <N>]
+
+Replays the responses recorded by the monitor, which requires `recording`
+to be set in the config file, printing the events that would have been
+sent instead of sending them:
+
+    DIRECTORY    The directory the responses were recorded to, which is
+                 `recordings` by default
+    --speed <N>  How many times faster than real time the responses are
+                 replayed, which is 60 by default; 0 replays them all at
+                 once";
+
+// This function runs the `replay` command, which reads the recordings
+// directly, so the monitor doesn't have to be running.
+pub fn run(args: Vec<String>) {
+    let mut directory = None;
+    let mut speed = SPEED;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => {
+                speed = match args.next().and_then(|speed| speed.parse::<f64>().ok()) {
+                    Some(speed) if speed >= 0.0 => speed,
+                    _ => {
+                        println!("{}", USAGE);
+                        return;
+                    }
+                }
+            }
+            _ if directory.is_none() && !arg.starts_with('-') => directory = Some(arg.clone()),
+            _ => {
+                println!("{}", USAGE);
+                return;
+            }
+        }
+    }
+
+    let directory = directory.unwrap_or_else(|| DIRECTORY.into());
+
+    let (stores, recordings) = match read(Path::new(&directory)) {
+        Ok(read) => read,
+        Err(e) => {
+            error!("Failed to read `{}`: {}", directory, e);
+            return;
+        }
+    };
+
+    if recordings.is_empty() {
+        println!("There aren't any recordings in `{}`.", directory);
+        return;
+    }
+
+    println!(
+        "Replaying {} responses from {} stores...\n",
+        recordings.len(),
+        stores.len()
+    );
+
+    // Each store has its own detector, exactly like in the monitor, so
+    // the responses of different stores don't affect each other.
+    let mut detectors = stores
+        .iter()
+        .map(|store| Detector::new(store.clone()))
+        .collect::<Vec<_>>();
+
+    let mut count = 0;
+    let mut last: Option<i64> = None;
+
+    for recording in recordings {
+        // The time between the responses is shortened according to the
+        // speed, so that a long recording can be replayed quickly.
+        if let Some(last) = last {
+            if speed > 0.0 {
+                let delta = (recording.time - last).max(0) as f64 / speed;
+                thread::sleep(Duration::from_millis(delta as u64));
+            }
+        }
+
+        last = Some(recording.time);
+
+        let body = match fs::read_to_string(&recording.path) {
+            Ok(body) => body,
+            Err(e) => {
+                warning!("Failed to read `{}`: {}", recording.path.display(), e);
+                continue;
+            }
+        };
+
+        // The recordings are parsed in full, as it doesn't matter how
+        // the store was parsed when they were recorded.
+        let products = poller::parse(recording.status, &body, true);
+        let events = detectors[recording.store].detect(recording.status, products.as_deref().ok());
+
+        for event in events {
+            count += 1;
+            print(recording.time, &event);
+        }
+    }
+
+    println!("\n{} events were detected.", count);
+}
+
+// This function returns the stores found in the directory, along with
+// every response recorded for them, sorted by the time they were
+// received.
+fn read(directory: &Path) -> std::io::Result<(Vec<event::Store>, Vec<Recording>)> {
+    let mut stores = vec![];
+    let mut recordings = vec![];
+
+    for entry in fs::read_dir(directory)? {
+        let folder = entry?.path();
+
+        if !folder.is_dir() {
+            continue;
+        }
+
+        // Folders without a valid `store.json` weren't created by the
+        // monitor, so they're skipped.
+        let store = match fs::read_to_string(folder.join(STORE))
+            .ok()
+            .and_then(|store| serde_json::from_str::<event::Store>(&store).ok())
+        {
+            Some(store) => store,
+            None => {
+                warning!(
+                    "Skipping `{}`, as it doesn't contain a `{}` file.",
+                    folder.display(),
+                    STORE
+                );
+                continue;
+            }
+        };
+
+        for entry in fs::read_dir(&folder)? {
+            let path = entry?.path();
+
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if let Some((time, status)) = name.split_once('-') {
+                if let (Ok(time), Ok(status)) = (time.parse(), status.parse()) {
+                    recordings.push(Recording {
+                        store: stores.len(),
+                        time,
+                        status,
+                        path,
+                    });
+                }
+            }
+        }
+
+        stores.push(store);
+    }
+
+    // Responses received at the same time are kept in the order of
+    // their files, which is also the order they were saved in.
+    recordings.sort_by(|a, b| (a.time, &a.path).cmp(&(b.time, &b.path)));
+
+    Ok((stores, recordings))
+}
+
+// The events are printed with the time the response that caused them
+// was received, rather than the current one.
+fn print(time: i64, event: &event::Event) {
+    let time = Local
+        .timestamp_millis_opt(time)
+        .single()
+        .map_or_else(String::new, |time| {
+            time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+        });
+
+    let description = match event.product() {
+        Some(product) => {
+            let sizes = product
+                .variants
+                .iter()
+                .filter(|variant| variant.available)
+                .map(|variant| variant.name())
+                .collect::<Vec<_>>();
+
+            if sizes.is_empty() {
+                format!("{} (no sizes available)", product.title)
+            } else {
+                format!("{} ({})", product.title, sizes.join(", "))
+            }
+        }
+        None => event.store().url.clone(),
+    };
+
+    println!(
+        "[{}] {} {} {}",
+        time.dimmed(),
+        event.store().name.bold(),
+        event.name().green(),
+        description
+    );
+}
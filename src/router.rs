@@ -0,0 +1,111 @@
+// This module decides which channels an event is sent to. Each store
+// keeps the channels subscribed to its events in three lists, which are
+// shared with the control API and with the monitor, as channels whose
+// webhooks turn out to be invalid are removed from them.
+
+use crate::{
+    event::Event,
+    stores::{Channel, Store},
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+type Channels = Arc<RwLock<Vec<Arc<Channel>>>>;
+
+#[derive(Clone, Debug)]
+pub struct Router {
+    // New products, restocks and sizes selling out are all sent to the
+    // channels in `restock`, as they're about the same products.
+    restock: Channels,
+    password_up: Channels,
+    password_down: Channels,
+}
+
+impl Router {
+    pub fn new(store: &Store) -> Router {
+        Router {
+            restock: store.restock.clone(),
+            password_up: store.password_up.clone(),
+            password_down: store.password_down.clone(),
+        }
+    }
+
+    // This function returns the channels the event should be sent to,
+    // skipping the ones that were muted through the control API.
+    pub async fn route(
+        &self,
+        event: &Event,
+        muted: impl Fn(&Channel) -> bool,
+    ) -> Vec<Arc<Channel>> {
+        let channels = match event {
+            Event::NewProduct { .. } | Event::Restock { .. } | Event::SoldOut { .. } => {
+                &self.restock
+            }
+            Event::PasswordUp { .. } => &self.password_up,
+            Event::PasswordDown { .. } => &self.password_down,
+        };
+
+        channels
+            .read()
+            .await
+            .iter()
+            .filter(|channel| !muted(channel) && accepts(event, channel))
+            .cloned()
+            .collect()
+    }
+
+    // The URL is checked instead of the channel itself because if two
+    // user-created "channels" were to share the same link, both should
+    // be removed.
+    pub async fn remove(&self, url: &str) {
+        for channels in [&self.restock, &self.password_up, &self.password_down].iter() {
+            channels.write().await.retain(|channel| channel.url != url);
+        }
+    }
+
+    // If nothing is being monitored (as there aren't any valid webhooks
+    // to send updates to) the store stops being monitored.
+    pub async fn is_empty(&self) -> bool {
+        self.restock.read().await.is_empty()
+            && self.password_up.read().await.is_empty()
+            && self.password_down.read().await.is_empty()
+    }
+}
+
+// This function checks the channel's filters, once it's known to be
+// subscribed to the kind of event that occurred.
+pub fn accepts(event: &Event, channel: &Channel) -> bool {
+    // Products that don't match the channel's filter, such as the ones
+    // without a release's tag, are never sent to it.
+    if let (Some(filter), Some(product)) = (&channel.settings.filter, event.product()) {
+        if !filter.matches(product) {
+            return false;
+        }
+    }
+
+    match event {
+        // Restocks are only sent if enough sizes are available, as set
+        // by the channel's `minimum` setting, and if enough of them just
+        // became available, as set by `restocked`.
+        Event::Restock {
+            product, restocked, ..
+        } => {
+            product
+                .variants
+                .iter()
+                .filter(|variant| variant.available)
+                .count()
+                >= channel.settings.minimum
+                && restocked.len() >= channel.settings.restocked
+        }
+
+        // Sizes selling out are only used to edit the messages that
+        // were sent for the product, so channels that don't edit their
+        // messages never receive these, as sending a new message every
+        // time a size sells out would be spam. Channels running a
+        // command don't send messages, so they have nothing to update.
+        Event::SoldOut { .. } => channel.settings.edit && channel.command.is_none(),
+
+        Event::NewProduct { .. } | Event::PasswordUp { .. } | Event::PasswordDown { .. } => true,
+    }
+}
@@ -0,0 +1,289 @@
+// This module runs the `test` command, which sends a sample of every
+// event each channel is subscribed to, so that the way the channels'
+// settings and templates render can be checked straight after editing
+// the config file, instead of waiting for a real restock.
+
+// The samples are about a made-up product, unless a store is named, in
+// which case one of its real products is used, and only its channels
+// receive them. Every message is marked as a test, with a note before
+// its content, and mentions are disabled, so that nobody is pinged by
+// it.
+
+use crate::{
+    command, config, default, error,
+    event::{self, Event},
+    message::AllowedMentions,
+    poller::Poller,
+    products::{Image, Product, Variant},
+    render,
+    stores::{self, Channel, Store},
+    success, warning,
+    webhook::{self, Status},
+};
+use std::{sync::Arc, time::Duration};
+use tokio::time::sleep;
+
+const USAGE: &str = "Usage: shopify-monitor test [STORE]
+
+Sends a sample of every event each channel is subscribed to, using a
+made-up product, or a real product from the given store, in which case
+only that store's channels receive them.";
+
+const CONTENT: &str = "**Test message** sent by `shopify-monitor test`.";
+
+pub async fn run(args: Vec<String>) {
+    let name = match args.as_slice() {
+        [] => None,
+        [name] => Some(name.clone()),
+        _ => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    let (stores, _) = stores::get(config::read());
+
+    let stores = stores
+        .into_iter()
+        .filter(|store| {
+            name.as_ref()
+                .is_none_or(|name| store.name.eq_ignore_ascii_case(name))
+        })
+        .collect::<Vec<_>>();
+
+    if stores.is_empty() {
+        if let Some(name) = name {
+            error!("There isn't a store called `{}`!", name);
+        } else {
+            warning!("There aren't any stores to send samples for!");
+        }
+        return;
+    }
+
+    let mut sent = 0;
+
+    for store in stores.iter() {
+        let site = event::Store {
+            name: store.name.clone(),
+            url: store.url.clone(),
+            logo: store.logo.clone(),
+        };
+
+        // The made-up product is labelled with the store's first
+        // collection, if it has any, so that the label can be seen.
+        let (product, collection) = if name.is_some() {
+            fetch(store).await.unwrap_or_else(|| {
+                warning!("Using a sample product for `{}` instead...", store.name);
+                (sample(store), store.collections.first().cloned())
+            })
+        } else {
+            (sample(store), store.collections.first().cloned())
+        };
+
+        // Half of the sizes in stock are marked as restocked, so that
+        // the sample shows both kinds of sizes.
+        let available = product
+            .variants
+            .iter()
+            .filter(|variant| variant.available)
+            .map(|variant| variant.id)
+            .collect::<Vec<_>>();
+        let restocked = available[..available.len().div_ceil(2)].to_vec();
+
+        // The channels are grouped by the events they're subscribed to,
+        // in the same way the monitor does it, and each of them receives
+        // a sample of each.
+        let groups = [
+            (
+                &store.restock,
+                vec![
+                    Event::NewProduct {
+                        store: site.clone(),
+                        product: product.clone(),
+                        collection: collection.clone(),
+                    },
+                    Event::Restock {
+                        store: site.clone(),
+                        product: product.clone(),
+                        restocked,
+                        collection: collection.clone(),
+                    },
+                ],
+            ),
+            (
+                &store.password_up,
+                vec![Event::PasswordUp {
+                    store: site.clone(),
+                }],
+            ),
+            (
+                &store.password_down,
+                vec![Event::PasswordDown {
+                    store: site.clone(),
+                }],
+            ),
+        ];
+
+        for (channels, events) in groups.iter() {
+            for channel in channels.read().await.iter() {
+                for event in events.iter() {
+                    if send(&store.name, channel, event).await {
+                        sent += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    default!(
+        "Sent {} test message{}.",
+        sent,
+        if sent == 1 { "" } else { "s" }
+    );
+}
+
+// This function returns the first product of the store with a size in
+// stock, or just the first one if they're all sold out, along with the
+// collection it was found in.
+async fn fetch(store: &Store) -> Option<(Product, Option<String>)> {
+    let res = Poller::new(&store.url)
+        .collections(store.collections.clone())
+        .full(store.full)
+        .poll()
+        .await;
+
+    let (products, collections) = match res {
+        Ok(res) => (res.products, res.collections),
+        Err(e) => {
+            warning!("Failed to GET {}: {}", store.url, e);
+            return None;
+        }
+    };
+
+    let products = match products {
+        Ok(products) => products,
+        Err(e) => {
+            warning!("Failed to get the products of `{}`: {}", store.name, e);
+            return None;
+        }
+    };
+
+    products
+        .iter()
+        .find(|product| product.variants.iter().any(|variant| variant.available))
+        .or_else(|| products.first())
+        .map(|product| (product.clone(), collections.get(&product.id).cloned()))
+}
+
+// The made-up product has a few sizes in stock, so that every part of
+// the messages is shown, and uses the store's logo as its image. It has
+// a type, tags and SKUs too, for the templates that show them.
+fn sample(store: &Store) -> Product {
+    Product {
+        id: 0,
+        title: "Test Product".into(),
+        handle: "test-product".into(),
+        updated_at: "".into(),
+        vendor: "Shopify Monitor".into(),
+        variants: ["8", "9", "10", "11", "12"]
+            .iter()
+            .enumerate()
+            .map(|(i, size)| Variant {
+                id: i as u64 + 1,
+                title: (*size).into(),
+                available: true,
+                price: "100.00".into(),
+                sku: Some(format!("TEST-{}", size)),
+                ..Default::default()
+            })
+            .collect(),
+        images: vec![Image {
+            src: store.logo.clone(),
+        }],
+        product_type: "Sample".into(),
+        tags: vec!["test".into()],
+        ..Default::default()
+    }
+}
+
+// This returns whether the sample was delivered. Channels running a
+// command receive the same JSON as usual, with `test` set to `true`.
+async fn send(store: &str, channel: &Channel, event: &Event) -> bool {
+    if let Some(command) = &channel.command {
+        let mut payload = render::command(event);
+        payload["test"] = true.into();
+
+        command::run(command.clone(), payload).await;
+        success!(
+            "Ran the command of `{}` for a test {}!",
+            channel.name,
+            event.name()
+        );
+        return true;
+    }
+
+    let msg = match event {
+        Event::PasswordUp { .. } | Event::PasswordDown { .. } => {
+            render::password(event, &channel.settings)
+        }
+        _ => render::item(event, &channel.settings, None),
+    };
+
+    let mut msg = if let Some(msg) = msg {
+        msg
+    } else {
+        return false;
+    };
+
+    // The message's content is kept, so that its template can be tested
+    // too, but the roles and users it mentions aren't pinged.
+    msg.content = Some(match msg.content.take() {
+        Some(content) => format!("{}\n{}", CONTENT, content),
+        None => CONTENT.into(),
+    });
+    msg.allowed_mentions = Some(AllowedMentions {
+        parse: vec![],
+        roles: vec![],
+        users: vec![],
+    });
+
+    for embed in msg.embeds.iter_mut().flatten() {
+        if let Some(title) = &embed.title {
+            embed.title = Some(format!("[TEST] {}", title));
+        }
+    }
+
+    let msg = Arc::new(msg.fit());
+
+    loop {
+        match webhook::send(channel.url.clone(), msg.clone()).await {
+            Status::Success(_) => {
+                success!(
+                    "Sent a test {} for `{}` to `{}`!",
+                    event.name(),
+                    store,
+                    channel.name
+                );
+                return true;
+            }
+            Status::RateLimit(Some(seconds)) => {
+                sleep(Duration::from_secs_f64(seconds)).await;
+            }
+            Status::RateLimit(None) => {
+                warning!("Failed to send a test to `{}`: rate limited!", channel.name);
+                return false;
+            }
+            Status::Invalid => {
+                warning!(
+                    "Failed to send a test to `{}`: invalid webhook!",
+                    channel.name
+                );
+                return false;
+            }
+            Status::Unknown => {
+                warning!("Failed to send a test to `{}`!", channel.name);
+                return false;
+            }
+        }
+    }
+}
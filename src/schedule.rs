@@ -0,0 +1,170 @@
+// This module decides how long each store waits between requests. By
+// default, it's always the site's `delay`, but a schedule can change it
+// during certain windows of time, such as polling faster during a weekly
+// release and slower overnight, poll faster for a while after an event
+// is detected, and add some randomness to every delay.
+
+use crate::config;
+use chrono::prelude::*;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, Default)]
+pub struct Schedule {
+    offset: Option<FixedOffset>,
+    windows: Vec<Window>,
+    boost: Option<Boost>,
+    jitter: u64,
+}
+
+#[derive(Debug)]
+struct Window {
+    // Every day is included if the window didn't list any.
+    days: Vec<Weekday>,
+    from: NaiveTime,
+    to: NaiveTime,
+    delay: u64,
+}
+
+#[derive(Debug)]
+struct Boost {
+    delay: u64,
+    duration: Duration,
+}
+
+impl Schedule {
+    // This converts the schedule included in the config file, returning
+    // an error describing the first problem that's found, if any.
+    pub fn parse(schedule: &config::Schedule) -> Result<Schedule, String> {
+        let offset = schedule.offset.as_deref().map(offset).transpose()?;
+
+        let mut windows = vec![];
+
+        for window in schedule.windows.iter().flatten() {
+            let mut days = vec![];
+
+            for day in window.days.iter().flatten() {
+                days.push(
+                    day.parse::<Weekday>()
+                        .map_err(|_| format!("Invalid day: `{}`!", day))?,
+                );
+            }
+
+            windows.push(Window {
+                days,
+                from: time(&window.from)?,
+                to: time(&window.to)?,
+                delay: window.delay.max(1),
+            });
+        }
+
+        Ok(Schedule {
+            offset,
+            windows,
+            boost: schedule.boost.as_ref().map(|boost| Boost {
+                delay: boost.delay.max(1),
+                duration: Duration::from_secs(boost.duration),
+            }),
+            jitter: schedule.jitter.unwrap_or(0).min(100),
+        })
+    }
+
+    // This is how long the store is polled faster for after an event.
+    pub fn boost(&self) -> Option<Duration> {
+        self.boost.as_ref().map(|boost| boost.duration)
+    }
+
+    // This returns how long to wait before the next request, given the
+    // site's usual delay, which can be changed through the control API,
+    // and whether the store was boosted by a recent event.
+    pub fn delay(&self, base: u64, boosted: bool) -> Duration {
+        let now = match self.offset {
+            Some(offset) => Utc::now().with_timezone(&offset),
+            None => {
+                let now = Local::now();
+                now.with_timezone(now.offset())
+            }
+        };
+
+        let delay = self.at(base, boosted, now.weekday(), now.time());
+
+        // The jitter is spread evenly between making the delay shorter
+        // and longer, so the store is still polled as often on average.
+        let jitter = delay as f64 * self.jitter as f64 / 100.0 * (random() * 2.0 - 1.0);
+
+        Duration::from_millis(((delay as f64 + jitter) as u64).max(1))
+    }
+
+    // The first window containing the time is used, and a boost only
+    // applies if it's faster than the delay that would be used otherwise.
+    pub(crate) fn at(&self, base: u64, boosted: bool, day: Weekday, time: NaiveTime) -> u64 {
+        let delay = self
+            .windows
+            .iter()
+            .find(|window| window.contains(day, time))
+            .map_or(base, |window| window.delay);
+
+        match &self.boost {
+            Some(boost) if boosted => delay.min(boost.delay),
+            _ => delay,
+        }
+    }
+}
+
+impl Window {
+    fn contains(&self, day: Weekday, time: NaiveTime) -> bool {
+        let on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+
+        if self.from <= self.to {
+            on(day) && self.from <= time && time < self.to
+        } else {
+            // Windows that go past midnight belong to the day they start
+            // on, so their end is checked against the previous day.
+            (on(day) && time >= self.from) || (on(day.pred()) && time < self.to)
+        }
+    }
+}
+
+fn time(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time: `{}`!", text))
+}
+
+// Offsets are written like `+01:00` or `-05:30`.
+fn offset(text: &str) -> Result<FixedOffset, String> {
+    let invalid = || format!("Invalid offset: `{}`!", text);
+    let text = text.trim();
+
+    let (sign, rest) = match text.chars().next() {
+        Some('+') => (1, &text[1..]),
+        Some('-') => (-1, &text[1..]),
+        _ => return Err(invalid()),
+    };
+
+    let (hours, minutes) = rest.split_once(':').ok_or_else(invalid)?;
+    let hours = hours.parse::<i32>().map_err(|_| invalid())?;
+    let minutes = minutes.parse::<i32>().map_err(|_| invalid())?;
+
+    if hours > 14 || minutes > 59 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+// The standard library doesn't include a random number generator, but
+// every `RandomState` is seeded randomly, which is more than enough to
+// make the delays less regular. This returns a number between 0 and 1.
+fn random() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos()),
+    );
+
+    hasher.finish() as f64 / u64::MAX as f64
+}
@@ -0,0 +1,446 @@
+// This module contains the optional HTTP server that exposes the
+// monitor's status, so that it can be checked without looking at the
+// terminal, the dashboard showing it along with the latest events, and
+// the API used to control the monitor while it's running. Since it
+// only needs to handle a few simple requests, it's written directly on
+// top of `tokio`'s TCP listener instead of adding a web framework as a
+// dependency.
+
+use crate::{
+    config,
+    control::{Control, Controller},
+    feed::Feed,
+    hidden,
+    metrics::Metrics,
+    success, warning,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::broadcast::error::RecvError,
+    task, time,
+};
+
+// The dashboard is a single page, which is included in the binary so
+// that it doesn't have to be distributed separately.
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+// Open event streams receive a comment this often, so that proxies don't
+// close them and closed connections are noticed.
+const KEEP_ALIVE: u64 = 15;
+
+// Requests larger than this are rejected, as none of the endpoints need
+// more than a few headers and a small JSON body.
+const REQUEST_LIMIT: usize = 64 * 1024;
+
+// Clients that don't finish sending their request within this many
+// seconds are disconnected, so that they can't keep a task waiting.
+const READ_TIMEOUT: u64 = 10;
+
+pub async fn run(
+    http: config::Http,
+    metrics: Arc<Metrics>,
+    controller: Arc<Controller>,
+    feed: Arc<Feed>,
+) {
+    let listener = match TcpListener::bind(&http.address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warning!("Failed to start the HTTP server on {}: {}", http.address, e);
+            return;
+        }
+    };
+
+    success!("Serving the dashboard on http://{}", http.address);
+
+    // Without a token, anyone who can reach the server can control the
+    // monitor, which is only safe if it's limited to this machine.
+    let local = listener
+        .local_addr()
+        .map(|address| address.ip().is_loopback())
+        .unwrap_or(false);
+
+    if http.token.is_none() && !local {
+        warning!(
+            "The control API on {} can be used by anyone who can reach it, as `http.token` isn't set!",
+            http.address
+        );
+    }
+
+    let token = Arc::new(http.token);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                task::spawn(handle(
+                    stream,
+                    token.clone(),
+                    metrics.clone(),
+                    controller.clone(),
+                    feed.clone(),
+                ));
+            }
+            Err(e) => {
+                hidden!("Failed to accept connection: {}", e);
+            }
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn text(status: &'static str, body: String) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+
+    fn json(status: &'static str, body: Value) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn message(message: String) -> Response {
+        Response::json("200 OK", json!({ "message": message }))
+    }
+
+    fn error(status: &'static str, error: String) -> Response {
+        Response::json(status, json!({ "error": error }))
+    }
+}
+
+// This is the body of `POST /api/stores/{name}/delay`.
+#[derive(Deserialize)]
+struct Delay {
+    delay: u64,
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    token: Arc<Option<String>>,
+    metrics: Arc<Metrics>,
+    controller: Arc<Controller>,
+    feed: Arc<Feed>,
+) {
+    let request = time::timeout(Duration::from_secs(READ_TIMEOUT), read(&mut stream)).await;
+
+    let request = if let Ok(Some(request)) = request {
+        request
+    } else {
+        return;
+    };
+
+    // The event stream is kept open, so it's handled separately from the
+    // other endpoints, which send a single response.
+    if request.method == "GET" && request.path == "/events" {
+        events(stream, feed).await;
+        return;
+    }
+
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => Response {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            body: DASHBOARD.into(),
+        },
+        ("GET", "/status") => Response::json("200 OK", json!(controller.list())),
+        ("GET", "/metrics") => Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: metrics.render(),
+        },
+        ("GET", "/healthz") => {
+            if metrics.healthy() {
+                Response::text("200 OK", "OK\n".into())
+            } else {
+                Response::text("503 Service Unavailable", "OFFLINE\n".into())
+            }
+        }
+        (_, "/") | (_, "/status") | (_, "/metrics") | (_, "/healthz") | (_, "/events") => {
+            Response::text("405 Method Not Allowed", "Method Not Allowed\n".into())
+        }
+        (_, path) if path.starts_with("/api/") => {
+            // Unlike the endpoints above, which are read-only, the API
+            // requires the token set in the config file, if any.
+            if let Some(token) = &*token {
+                if request.authorization.as_deref() != Some(&format!("Bearer {}", token)) {
+                    Response::error("401 Unauthorized", "Invalid token!".into())
+                } else {
+                    api(&request, &controller).await
+                }
+            } else {
+                api(&request, &controller).await
+            }
+        }
+        _ => Response::text("404 Not Found", "Not Found\n".into()),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+
+    if let Err(e) = stream.write_all(head.as_bytes()).await {
+        hidden!("Failed to respond to HTTP request: {}", e);
+        return;
+    }
+
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+// This function streams the events detected by the monitor as
+// server-sent events, starting with the most recent ones, until the
+// connection is closed.
+async fn events(mut stream: TcpStream, feed: Arc<Feed>) {
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    let (recent, mut rx) = feed.subscribe();
+
+    for entry in recent {
+        if stream
+            .write_all(event(&json!(entry)).as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    let mut keep_alive = time::interval(Duration::from_secs(KEEP_ALIVE));
+
+    loop {
+        let text = tokio::select! {
+            entry = rx.recv() => match entry {
+                Ok(entry) => event(&json!(entry)),
+
+                // If the connection is too slow to keep up, the events
+                // it missed are skipped.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            },
+            _ = keep_alive.tick() => ": keep-alive\n\n".into(),
+        };
+
+        if stream.write_all(text.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn event(data: &Value) -> String {
+    format!("data: {}\n\n", data)
+}
+
+// The request is read until the end of its headers, then until the end
+// of its body, if it has one.
+async fn read(stream: &mut TcpStream) -> Option<Request> {
+    let mut buffer = vec![];
+    let mut chunk = [0; 1024];
+
+    let end = loop {
+        if let Some(i) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+
+        if buffer.len() > REQUEST_LIMIT {
+            return None;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..end]).to_string();
+    let mut lines = head.lines();
+    let mut line = lines.next().unwrap_or_default().split(' ');
+    let method = line.next().unwrap_or_default().to_string();
+
+    // Query strings are ignored, as none of the endpoints use them.
+    let path = line
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let mut length = 0;
+    let mut authorization = None;
+
+    for header in lines {
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim().to_lowercase();
+
+            if name == "content-length" {
+                length = value.trim().parse().ok()?;
+            } else if name == "authorization" {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    // The length is sent by the client, so it could be large enough to
+    // overflow.
+    if end.checked_add(length)? > REQUEST_LIMIT {
+        return None;
+    }
+
+    let mut body = buffer[end..].to_vec();
+
+    while body.len() < length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    body.truncate(length);
+
+    Some(Request {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+// These are the endpoints used to control the monitor, which are all
+// documented in the README's `HTTP` section.
+async fn api(request: &Request, controller: &Controller) -> Response {
+    let segments = request
+        .path
+        .trim_start_matches("/api/")
+        .trim_end_matches('/')
+        .split('/')
+        .map(decode)
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["stores"]) => Response::json("200 OK", json!(controller.list())),
+        ("POST", ["stores"]) => match serde_json::from_slice::<config::Site>(&request.body) {
+            Ok(site) => {
+                let name = site.name.clone();
+
+                match controller.add(site).await {
+                    Ok(()) => Response::json(
+                        "201 Created",
+                        json!({ "message": format!("Started monitoring `{}`!", name) }),
+                    ),
+                    Err(e) => Response::error("400 Bad Request", e),
+                }
+            }
+            Err(e) => Response::error("400 Bad Request", format!("Invalid site: {}", e)),
+        },
+        ("DELETE", ["stores", name]) => {
+            control(controller, name, Control::Remove, "Stopping").await
+        }
+        ("POST", ["stores", name, "pause"]) => {
+            control(controller, name, Control::Pause, "Pausing").await
+        }
+        ("POST", ["stores", name, "resume"]) => {
+            control(controller, name, Control::Resume, "Resuming").await
+        }
+        ("POST", ["stores", name, "poll"]) => {
+            control(controller, name, Control::Poll, "Polling").await
+        }
+        ("POST", ["stores", name, "delay"]) => {
+            match serde_json::from_slice::<Delay>(&request.body) {
+                Ok(Delay { delay }) => {
+                    control(
+                        controller,
+                        name,
+                        Control::Delay(delay),
+                        "Changing the delay of",
+                    )
+                    .await
+                }
+                Err(e) => Response::error("400 Bad Request", format!("Invalid delay: {}", e)),
+            }
+        }
+        ("GET", ["channels"]) => {
+            let muted = controller
+                .muted_channels()
+                .into_iter()
+                .map(|(server, channel)| json!({ "server": server, "channel": channel }))
+                .collect::<Vec<_>>();
+
+            Response::json("200 OK", json!({ "muted": muted }))
+        }
+        ("POST", ["channels", server, name, "mute"]) => {
+            controller.mute(server, name, true);
+            Response::message(format!("Muted `{}` > `{}`.", server, name))
+        }
+        ("POST", ["channels", server, name, "unmute"]) => {
+            controller.mute(server, name, false);
+            Response::message(format!("Unmuted `{}` > `{}`.", server, name))
+        }
+        _ => Response::error("404 Not Found", "Unknown endpoint!".into()),
+    }
+}
+
+async fn control(controller: &Controller, name: &str, control: Control, action: &str) -> Response {
+    if controller.send(name, control).await {
+        Response::message(format!("{} `{}`...", action, name))
+    } else {
+        Response::error(
+            "404 Not Found",
+            format!("`{}` isn't being monitored!", name),
+        )
+    }
+}
+
+// Store and channel names can contain spaces and other characters that
+// have to be percent-encoded in URLs.
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(Ok(byte)) = segment
+                .get(i + 1..i + 3)
+                .map(|hex| u8::from_str_radix(hex, 16))
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
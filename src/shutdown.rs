@@ -0,0 +1,61 @@
+// This module listens for the signals sent to stop the program, such as
+// when Ctrl-C is pressed or when a service manager restarts it during a
+// deploy, so that the monitor can finish sending the webhooks for the
+// events it already detected instead of being killed halfway through.
+
+use crate::{default, important, warning};
+use std::process;
+use tokio::{
+    signal,
+    sync::watch::{self, Receiver},
+    task,
+};
+
+// The receiver's value becomes `true` once the program should stop. If
+// a second signal is received while it's stopping, it quits straight
+// away, in case sending the webhooks takes too long.
+pub fn listen() -> Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+
+    task::spawn(async move {
+        let name = wait().await;
+
+        important!("SHUTTING DOWN");
+        default!("Received {}, stopping the monitor...", name);
+        default!("Press Ctrl-C again to quit without waiting for the webhooks.");
+
+        // This can only fail if the monitor already stopped.
+        let _ = tx.send(true);
+
+        let name = wait().await;
+        warning!("Received {} again, quitting immediately!", name);
+        process::exit(1);
+    });
+
+    rx
+}
+
+// Ctrl-C sends `SIGINT`, while service managers such as systemd and
+// Docker send `SIGTERM`, which only exists on Unix.
+async fn wait() -> &'static str {
+    #[cfg(unix)]
+    {
+        use signal::unix::{self, SignalKind};
+
+        if let Ok(mut terminate) = unix::signal(SignalKind::terminate()) {
+            tokio::select! {
+                Ok(()) = signal::ctrl_c() => return "SIGINT",
+                Some(()) = terminate.recv() => return "SIGTERM",
+                else => {}
+            }
+        }
+    }
+
+    if signal::ctrl_c().await.is_ok() {
+        return "Ctrl-C";
+    }
+
+    // If the signals can't be listened to, the program can only be
+    // killed, so this never completes.
+    futures::future::pending().await
+}
@@ -3,29 +3,65 @@
 // specified sites and their settings for the monitor to more easily and
 // efficiently work with.
 
-use crate::{alternative::Alternative as Alt, config, default, hidden, warning};
-use std::sync::Arc;
+use crate::{
+    alternative::Alternative as Alt, command::Command, config, default, error, hidden,
+    products::Product, schedule::Schedule, template::Template, warning,
+};
+use std::{collections::HashMap, process, sync::Arc};
 use tokio::sync::RwLock;
 
-pub fn get() -> Vec<Store> {
-    let config = config::read();
-
+pub fn get(config: config::Config) -> (Vec<Store>, Builder) {
     // As usual, `hidden!()` came to the rescue when I couldn't figure
     // out where a bug originated from. I didn't use `println!()` as the
     // example config file is so long that Visual Studio Code's terminal
     // won't display all of it if you scroll  up.
     /* hidden!("\n{:#?}", config); */
 
+    // Invalid templates are treated like any other mistake in the
+    // config file, so the program stops before it starts monitoring.
+    if !templates(&config) || !schedules(&config) {
+        config::suggest_instructions();
+        process::exit(0);
+    }
+
+    let mut builder = Builder {
+        servers: config.servers,
+        invalid: vec![],
+        commands: HashMap::new(),
+    };
+
+    let stores = build(config.sites, &mut builder);
+
+    // The builder is returned alongside the stores so that sites can be
+    // added while the monitor is running, through the control API.
+    (stores, builder)
+}
+
+// This struct holds what's needed to turn a site into a `Store` once
+// the config file has been read.
+#[derive(Debug)]
+pub struct Builder {
+    servers: config::VecMap<config::Server, config::ServerHM>,
+
+    // This `Vec<>` will store all invalid webhook URLs so that the
+    // program won't warn the user about them more than once.
+    invalid: Vec<String>,
+
+    // Channels running the same command share it, so that its
+    // concurrency limit applies to every site they receive events for,
+    // including the ones added later on.
+    commands: HashMap<config::Command, Arc<Command>>,
+}
+
+// This function creates a `Store` for each of the sites it's given that
+// at least one channel receives events for.
+pub fn build(sites: impl IntoIterator<Item = config::Site>, builder: &mut Builder) -> Vec<Store> {
     // This vector, which will then be passed to the `monitor::run()`
     // function, will be filled with one `Store` struct per site listed
     // in `config.json`, along with every event the user has selected.
     let mut stores: Vec<Store> = vec![];
 
-    // This `Vec<>` will store all invalid webhook URLs so that the
-    // program won't warn the user about them more than once.
-    let mut invalid: Vec<String> = vec![];
-
-    for site in config.sites {
+    for site in sites {
         // A mutable vector is created for each event type
         let mut restock: Vec<Arc<Channel>> = vec![];
         let mut password_up: Vec<Arc<Channel>> = vec![];
@@ -85,13 +121,58 @@ pub fn get() -> Vec<Store> {
             minimum
         };
 
+        // Invalid schedules stop the program before it starts, like the
+        // other mistakes in the config file, but sites added through the
+        // control API are only warned about, and use a fixed delay.
+        let schedule = match site.schedule.as_ref().map(Schedule::parse).transpose() {
+            Ok(schedule) => schedule.unwrap_or_default(),
+            Err(e) => {
+                warning!("Invalid schedule for `{}`: {}", site.name, e);
+                Schedule::default()
+            }
+        };
+
+        // The watchlist only keeps the products' handles, and uses the
+        // site's delay if it doesn't have one, which can't be 0 either.
+        let watchlist = site
+            .watchlist
+            .iter()
+            .flat_map(|watchlist| watchlist.products.iter())
+            .map(|product| handle(product, "products"))
+            .filter(|handle| !handle.is_empty())
+            .collect::<Vec<_>>();
+        let watchlist_delay = site
+            .watchlist
+            .as_ref()
+            .and_then(|watchlist| watchlist.delay)
+            .map_or(delay, |delay| delay.max(1));
+
+        // Only the products of these collections are fetched, if there
+        // are any, instead of the whole catalog.
+        let collections = site
+            .collections
+            .iter()
+            .flatten()
+            .map(|collection| handle(collection, "collections"))
+            .filter(|handle| !handle.is_empty())
+            .collect::<Vec<_>>();
+
+        let discovery = site.discovery.as_ref().map(|discovery| Discovery {
+            sources: discovery
+                .sources
+                .clone()
+                .unwrap_or_else(|| vec![config::Source::Atom, config::Source::Sitemap]),
+            primary: discovery.mode == Some(config::Mode::Primary),
+            delay: discovery.delay.map_or(delay, |delay| delay.max(1)),
+        });
+
         // It would be better to use `&c.servers` instead of
         // `c.servers.clone()`, but I haven't implemented the required
         // traits for it yet, and have delayed this project's initial
         // release for too long for me to work on those now. This module
         // is only run when the program is initializing, so the slight
         // increase in memory usage isn't a concern.
-        for server in config.servers.clone() {
+        for server in builder.servers.clone() {
             // After creating `Alt` I was posed with a dilemma related
             // to where I should place the logic to determine which
             // settings to use in each channel. Although the performance
@@ -177,10 +258,48 @@ pub fn get() -> Vec<Store> {
                 if let Alt::Some(value) = settings.minimum {
                     server_settings.minimum = value;
                 }
+
+                if let Alt::Some(value) = settings.restocked {
+                    server_settings.restocked = value;
+                }
+
+                if let Alt::Some(value) = settings.cooldown {
+                    server_settings.cooldown = value;
+                }
+
+                if let Alt::Some(value) = settings.edit {
+                    server_settings.edit = value;
+                }
+
+                if let Alt::Some(value) = &settings.template {
+                    server_settings.template = Template::parse(value).ok().map(Arc::new);
+                }
+
+                if let Alt::Some(value) = &settings.mention {
+                    server_settings.mention = Some(Arc::new(Mention::from(value)));
+                }
+
+                if let Alt::Some(value) = &settings.keywords {
+                    server_settings.keywords = keywords(value, &server_settings.keywords);
+                }
+
+                if let Alt::Some(value) = &settings.filter {
+                    server_settings.filter = Some(Arc::new(Filter::from(value)));
+                }
             }
 
             for channel in server.channels {
-                if channel.url.contains("https://discord.com/api/webhooks/") {
+                if channel.url.contains("https://discord.com/api/webhooks/")
+                    || channel.command.is_some()
+                {
+                    let command = channel.command.as_ref().map(|command| {
+                        builder
+                            .commands
+                            .entry(command.clone())
+                            .or_insert_with(|| Arc::new(Command::from(command)))
+                            .clone()
+                    });
+
                     let mut channel_settings = server_settings.clone();
                     let mut color = color.clone();
 
@@ -240,6 +359,48 @@ pub fn get() -> Vec<Store> {
                         } else if settings.minimum.is_null() {
                             channel_settings.minimum = 0;
                         }
+
+                        if let Alt::Some(value) = settings.restocked {
+                            channel_settings.restocked = value;
+                        } else if settings.restocked.is_null() {
+                            channel_settings.restocked = 1;
+                        }
+
+                        if let Alt::Some(value) = settings.cooldown {
+                            channel_settings.cooldown = value;
+                        } else if settings.cooldown.is_null() {
+                            channel_settings.cooldown = 0;
+                        }
+
+                        if let Alt::Some(value) = settings.edit {
+                            channel_settings.edit = value;
+                        } else if settings.edit.is_null() {
+                            channel_settings.edit = false;
+                        }
+
+                        if let Alt::Some(value) = &settings.template {
+                            channel_settings.template = Template::parse(value).ok().map(Arc::new);
+                        } else if settings.template.is_null() {
+                            channel_settings.template = None;
+                        }
+
+                        if let Alt::Some(value) = &settings.mention {
+                            channel_settings.mention = Some(Arc::new(Mention::from(value)));
+                        } else if settings.mention.is_null() {
+                            channel_settings.mention = None;
+                        }
+
+                        if let Alt::Some(value) = &settings.keywords {
+                            channel_settings.keywords = keywords(value, &channel_settings.keywords);
+                        } else if settings.keywords.is_null() {
+                            channel_settings.keywords = vec![];
+                        }
+
+                        if let Alt::Some(value) = &settings.filter {
+                            channel_settings.filter = Some(Arc::new(Filter::from(value)));
+                        } else if settings.filter.is_null() {
+                            channel_settings.filter = None;
+                        }
                     } else if channel.settings.is_null() {
                         channel_settings = Settings::new();
                     }
@@ -308,6 +469,48 @@ pub fn get() -> Vec<Store> {
                             } else if settings.minimum.is_null() {
                                 store_settings.minimum = 0;
                             }
+
+                            if let Alt::Some(value) = settings.restocked {
+                                store_settings.restocked = value;
+                            } else if settings.restocked.is_null() {
+                                store_settings.restocked = 1;
+                            }
+
+                            if let Alt::Some(value) = settings.cooldown {
+                                store_settings.cooldown = value;
+                            } else if settings.cooldown.is_null() {
+                                store_settings.cooldown = 0;
+                            }
+
+                            if let Alt::Some(value) = settings.edit {
+                                store_settings.edit = value;
+                            } else if settings.edit.is_null() {
+                                store_settings.edit = false;
+                            }
+
+                            if let Alt::Some(value) = &settings.template {
+                                store_settings.template = Template::parse(value).ok().map(Arc::new);
+                            } else if settings.template.is_null() {
+                                store_settings.template = None;
+                            }
+
+                            if let Alt::Some(value) = &settings.mention {
+                                store_settings.mention = Some(Arc::new(Mention::from(value)));
+                            } else if settings.mention.is_null() {
+                                store_settings.mention = None;
+                            }
+
+                            if let Alt::Some(value) = &settings.keywords {
+                                store_settings.keywords = keywords(value, &store_settings.keywords);
+                            } else if settings.keywords.is_null() {
+                                store_settings.keywords = vec![];
+                            }
+
+                            if let Alt::Some(value) = &settings.filter {
+                                store_settings.filter = Some(Arc::new(Filter::from(value)));
+                            } else if settings.filter.is_null() {
+                                store_settings.filter = None;
+                            }
                         } else if store.settings.is_null() {
                             store_settings = Settings::new();
                         }
@@ -377,6 +580,50 @@ pub fn get() -> Vec<Store> {
                                 } else if settings.minimum.is_null() {
                                     event_settings.minimum = 0;
                                 }
+
+                                if let Alt::Some(value) = settings.restocked {
+                                    event_settings.restocked = value;
+                                } else if settings.restocked.is_null() {
+                                    event_settings.restocked = 1;
+                                }
+
+                                if let Alt::Some(value) = settings.cooldown {
+                                    event_settings.cooldown = value;
+                                } else if settings.cooldown.is_null() {
+                                    event_settings.cooldown = 0;
+                                }
+
+                                if let Alt::Some(value) = settings.edit {
+                                    event_settings.edit = value;
+                                } else if settings.edit.is_null() {
+                                    event_settings.edit = false;
+                                }
+
+                                if let Alt::Some(value) = &settings.template {
+                                    event_settings.template =
+                                        Template::parse(value).ok().map(Arc::new);
+                                } else if settings.template.is_null() {
+                                    event_settings.template = None;
+                                }
+
+                                if let Alt::Some(value) = &settings.mention {
+                                    event_settings.mention = Some(Arc::new(Mention::from(value)));
+                                } else if settings.mention.is_null() {
+                                    event_settings.mention = None;
+                                }
+
+                                if let Alt::Some(value) = &settings.keywords {
+                                    event_settings.keywords =
+                                        keywords(value, &event_settings.keywords);
+                                } else if settings.keywords.is_null() {
+                                    event_settings.keywords = vec![];
+                                }
+
+                                if let Alt::Some(value) = &settings.filter {
+                                    event_settings.filter = Some(Arc::new(Filter::from(value)));
+                                } else if settings.filter.is_null() {
+                                    event_settings.filter = None;
+                                }
                             } else if store.settings.is_null() {
                                 event_settings = Settings::new();
                             }
@@ -415,8 +662,10 @@ pub fn get() -> Vec<Store> {
                             if store.name == site.name {
                                 let channel = Arc::new(Channel {
                                     name: channel.name.clone(),
+                                    server: server.name.clone(),
                                     /* id: channel.id.clone(), */
                                     url: channel.url.trim_end_matches('/').into(),
+                                    command: command.clone(),
                                     settings: Settings {
                                         username: event_settings.username,
                                         avatar: event_settings.avatar,
@@ -430,6 +679,13 @@ pub fn get() -> Vec<Store> {
                                         footer_image: event_settings.footer_image,
                                         timestamp: event_settings.timestamp,
                                         minimum: event_settings.minimum,
+                                        restocked: event_settings.restocked,
+                                        cooldown: event_settings.cooldown,
+                                        edit: event_settings.edit,
+                                        template: event_settings.template,
+                                        mention: event_settings.mention,
+                                        keywords: event_settings.keywords,
+                                        filter: event_settings.filter,
                                     },
                                 });
 
@@ -454,9 +710,9 @@ pub fn get() -> Vec<Store> {
                             }
                         }
                     }
-                } else if !invalid.contains(&channel.url) {
+                } else if !builder.invalid.contains(&channel.url) {
                     warning!("Invalid Webhook URL: `{}`!", channel.url);
-                    invalid.push(channel.url);
+                    builder.invalid.push(channel.url);
                 }
             }
         }
@@ -465,11 +721,60 @@ pub fn get() -> Vec<Store> {
         // is configured but no channel will receive its updates,
         // sending requests to the website is useless.
         if !restock.is_empty() || !password_up.is_empty() || !password_down.is_empty() {
+            // Only the channels receiving products need their details, so
+            // the store is parsed in full if one of them filters them or
+            // uses them in its template, unless the site chose a mode.
+            let full = match site.parsing {
+                Some(parsing) => parsing == config::Parsing::Full,
+                None => restock.iter().any(|channel| {
+                    channel.settings.filter.is_some()
+                        || channel
+                            .settings
+                            .template
+                            .as_ref()
+                            .is_some_and(|template| template.details)
+                }),
+            };
+
+            // Lean parsing skips details such as the tags and the product
+            // type, so the filters and templates that rely on them would
+            // never match or be left empty, which is pointed out straight
+            // away.
+            if site.parsing == Some(config::Parsing::Lean) {
+                for channel in restock.iter() {
+                    let filtered = channel.settings.filter.as_ref().is_some_and(|filter| {
+                        !filter.tags.is_empty()
+                            || !filter.exclude_tags.is_empty()
+                            || !filter.types.is_empty()
+                    });
+                    let detailed = channel
+                        .settings
+                        .template
+                        .as_ref()
+                        .is_some_and(|template| template.details);
+
+                    if filtered || detailed {
+                        warning!(
+                            "`{}` > `{}` filters or shows details of `{}`'s products that aren't read, as its `parsing` is `lean`!",
+                            channel.server,
+                            channel.name,
+                            site.name
+                        );
+                    }
+                }
+            }
+
             stores.push(Store {
                 name: site.name.clone(),
                 url: site.url.clone(),
                 logo,
                 delay,
+                schedule: Arc::new(schedule),
+                collections,
+                watchlist,
+                watchlist_delay,
+                discovery,
+                full,
                 restock: Arc::new(RwLock::new(restock)),
                 password_up: Arc::new(RwLock::new(password_up)),
                 password_down: Arc::new(RwLock::new(password_down)),
@@ -482,7 +787,85 @@ pub fn get() -> Vec<Store> {
     stores
 }
 
-fn parse_color(color: &Option<String>) -> Option<u32> {
+// This function checks every template in the config file, logging the
+// ones that are invalid and where they are, and returns whether they are
+// all valid. Since the settings are processed once for every site, doing
+// this beforehand means that each error is only reported once.
+fn templates(config: &config::Config) -> bool {
+    let mut valid = true;
+
+    let mut check = |settings: &Alt<config::Settings>, location: String| {
+        if let Alt::Some(config::Settings {
+            template: Alt::Some(template),
+            ..
+        }) = settings
+        {
+            if let Err(e) = Template::parse(template) {
+                error!("Invalid template in {}: {}", location, e);
+                valid = false;
+            }
+        }
+    };
+
+    for server in config.servers.clone() {
+        check(&server.settings, format!("`{}`", server.name));
+
+        for channel in server.channels {
+            check(
+                &channel.settings,
+                format!("`{}` > `{}`", server.name, channel.name),
+            );
+
+            for store in channel.sites {
+                let location = format!("`{}` > `{}` > `{}`", server.name, channel.name, store.name);
+                check(&store.settings, location.clone());
+
+                for event in store.events {
+                    check(&event.settings, format!("{} > event", location));
+                }
+            }
+        }
+    }
+
+    valid
+}
+
+// Schedules are checked in the same way as templates, before the monitor
+// starts.
+fn schedules(config: &config::Config) -> bool {
+    let mut valid = true;
+
+    for site in config.sites.clone() {
+        if let Some(Err(e)) = site.schedule.as_ref().map(Schedule::parse) {
+            error!("Invalid schedule for `{}`: {}", site.name, e);
+            valid = false;
+        }
+    }
+
+    valid
+}
+
+// Products and collections can be listed using a link to them, such as
+// `https://kith.com/collections/footwear`, in which case only their
+// handle is kept. `kind` is the part of the link that comes before it.
+pub fn handle(text: &str, kind: &str) -> String {
+    let text = text.trim();
+    let prefix = format!("/{}/", kind);
+    let handle = match text.find(&prefix) {
+        Some(i) => &text[i + prefix.len()..],
+        None => text,
+    };
+
+    handle
+        .split(['?', '#', '/'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".json")
+        .trim_end_matches(".js")
+        .into()
+}
+
+pub fn parse_color(color: &Option<String>) -> Option<u32> {
     if let Some(code) = color {
         return Some(match code.to_lowercase().as_str() {
             "white" => 0xffffff,
@@ -599,16 +982,48 @@ pub struct Store {
     // This field isn't optional, as a default value is set if one
     // wasn't configured.
     pub delay: u64,
+    pub schedule: Arc<Schedule>,
+
+    // These are the handles of the collections the store's products are
+    // fetched from, or empty if its whole catalog is.
+    pub collections: Vec<String>,
+
+    // These are the handles of the products on the store's watchlist,
+    // which are polled every `watchlist_delay`.
+    pub watchlist: Vec<String>,
+    pub watchlist_delay: u64,
+    pub discovery: Option<Discovery>,
+
+    // This is whether the store's products are parsed in full, rather
+    // than only reading the fields that are always needed.
+    pub full: bool,
     pub restock: Arc<RwLock<Vec<Arc<Channel>>>>,
     pub password_up: Arc<RwLock<Vec<Arc<Channel>>>>,
     pub password_down: Arc<RwLock<Vec<Arc<Channel>>>>,
 }
 
+// This is how new products are found without `/products.json`, which is
+// explained in `crate::discovery`.
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    pub sources: Vec<config::Source>,
+    pub primary: bool,
+    pub delay: u64,
+}
+
 #[derive(Debug)]
 pub struct Channel {
     pub name: String,
+
+    // Channel names don't have to be unique, so the name of the server
+    // is kept too, to tell them apart.
+    pub server: String,
     /* pub id: u64, */
     pub url: String,
+
+    // If this is set, the channel runs a program instead of sending a
+    // webhook to its URL.
+    pub command: Option<Arc<Command>>,
     /* pub include: Option<Vec<String>>, */
     /* pub exclude: Option<Vec<String>>, */
     /* pub proxies: Option<Vec<String>>, */
@@ -629,10 +1044,17 @@ pub struct Settings {
     pub footer_image: Option<String>,
     pub timestamp: bool,
     pub minimum: usize,
+    pub restocked: usize,
+    pub cooldown: u64,
+    pub edit: bool,
+    pub template: Option<Arc<Template>>,
+    pub mention: Option<Arc<Mention>>,
+    pub keywords: Vec<Arc<Keyword>>,
+    pub filter: Option<Arc<Filter>>,
 }
 
 impl Settings {
-    fn new() -> Settings {
+    pub(crate) fn new() -> Settings {
         Settings {
             username: None,
             avatar: None,
@@ -644,6 +1066,155 @@ impl Settings {
             footer_image: None,
             timestamp: false,
             minimum: 0,
+            restocked: 1,
+            cooldown: 0,
+            edit: false,
+            template: None,
+            mention: None,
+            keywords: vec![],
+            filter: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Mention {
+    pub roles: Vec<String>,
+    pub users: Vec<String>,
+    pub here: bool,
+    pub everyone: bool,
+}
+
+impl From<&config::Mention> for Mention {
+    fn from(mention: &config::Mention) -> Mention {
+        Mention {
+            roles: mention
+                .roles
+                .clone()
+                .map(|roles| roles.into_iter().collect())
+                .unwrap_or_default(),
+            users: mention
+                .users
+                .clone()
+                .map(|users| users.into_iter().collect())
+                .unwrap_or_default(),
+            here: mention.here == Some(true),
+            everyone: mention.everyone == Some(true),
+        }
+    }
+}
+
+// The values are saved in lowercase, like keywords, so that they only
+// have to be converted once.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub types: Vec<String>,
+}
+
+impl From<&config::Filter> for Filter {
+    fn from(filter: &config::Filter) -> Filter {
+        let lowercase = |values: &Option<config::VecMap<String, String>>| {
+            values
+                .clone()
+                .map(|values| {
+                    values
+                        .into_iter()
+                        .map(|value| value.to_lowercase())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        Filter {
+            tags: lowercase(&filter.tags),
+            exclude_tags: lowercase(&filter.exclude_tags),
+            types: lowercase(&filter.types),
+        }
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, product: &Product) -> bool {
+        let tags = product
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_lowercase())
+            .collect::<Vec<_>>();
+        let product_type = product.product_type.trim().to_lowercase();
+
+        (self.tags.is_empty() || self.tags.iter().any(|tag| tags.contains(tag)))
+            && !self.exclude_tags.iter().any(|tag| tags.contains(tag))
+            && (self.types.is_empty() || self.types.contains(&product_type))
+    }
+}
+
+// Unlike `config::Keyword`, this struct doesn't need a `combine` field,
+// as the keywords of the "wider" scopes are already included in the
+// list it's part of, and the use of `Alt` is no longer necessary.
+#[derive(Debug)]
+pub struct Keyword {
+    // The keywords are saved in lowercase, so that they don't have to be
+    // converted every time a product is checked.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+
+    // The outer `Option` is `None` if the group doesn't change who is
+    // pinged, while the inner one is `None` if it was set to `null`.
+    pub mention: Option<Option<Arc<Mention>>>,
+}
+
+impl Keyword {
+    // A product matches a keyword group if its title contains all of
+    // the group's `include` keywords and none of its `exclude` ones.
+    pub fn matches(&self, title: &str) -> bool {
+        let title = title.to_lowercase();
+
+        self.include.iter().all(|keyword| title.contains(keyword))
+            && !self.exclude.iter().any(|keyword| title.contains(keyword))
+    }
+}
+
+// This function converts the keyword groups set at a certain level,
+// adding them to the ones that were set at a "wider" one, unless any
+// of them has `combine` set to `false`.
+fn keywords(
+    groups: &config::VecMap<config::Keyword, config::Keyword>,
+    wider: &[Arc<Keyword>],
+) -> Vec<Arc<Keyword>> {
+    let groups = groups.clone().into_iter().collect::<Vec<config::Keyword>>();
+
+    let mut keywords = if groups.iter().all(|group| group.combine != Some(false)) {
+        wider.to_vec()
+    } else {
+        vec![]
+    };
+
+    let list = |keywords: &Alt<config::VecMap<String, String>>| -> Vec<String> {
+        if let Alt::Some(keywords) = keywords {
+            keywords
+                .clone()
+                .into_iter()
+                .map(|keyword| keyword.trim().to_lowercase())
+                .filter(|keyword| !keyword.is_empty())
+                .collect()
+        } else {
+            vec![]
         }
+    };
+
+    for group in groups {
+        keywords.push(Arc::new(Keyword {
+            include: list(&group.include),
+            exclude: list(&group.exclude),
+            mention: match &group.mention {
+                Alt::Some(mention) => Some(Some(Arc::new(Mention::from(mention)))),
+                Alt::Null => Some(None),
+                Alt::None => None,
+            },
+        }));
     }
+
+    keywords
 }
@@ -0,0 +1,291 @@
+// This module allows users to customize the layout of the embeds sent by
+// the monitor. Templates are set in the `template` setting, and contain
+// text with placeholders, such as `{product.title}`, which are replaced
+// with the event's details when a message is sent.
+
+// Templates are checked when the settings are loaded, so that typos in
+// placeholder names are reported immediately instead of showing up in
+// the middle of a drop.
+
+use crate::config;
+use std::collections::HashMap;
+
+// These placeholders can be used in every part of a template.
+pub const PLACEHOLDERS: [&str; 15] = [
+    "event",
+    "price",
+    "compare_at_price",
+    "product.title",
+    "product.handle",
+    "product.url",
+    "product.brand",
+    "product.image",
+    "product.type",
+    "product.tags",
+    "product.published_at",
+    "store.name",
+    "store.url",
+    "store.logo",
+    "collection",
+];
+
+// These, on the other hand, refer to a single variant, so they can only
+// be used in the `size` field, which is repeated for every size.
+pub const VARIANT_PLACEHOLDERS: [&str; 7] = [
+    "variant.title",
+    "variant.id",
+    "variant.status",
+    "variant.stock",
+    "variant.sku",
+    "variant.image",
+    "atc_url",
+];
+
+// These are only known if the store's products are parsed in full, which
+// happens automatically for the stores whose channels use them.
+pub const DETAILS: [&str; 6] = [
+    "compare_at_price",
+    "product.type",
+    "product.tags",
+    "product.published_at",
+    "variant.sku",
+    "variant.image",
+];
+
+// Password page events aren't about a product, so these are the only
+// placeholders they have values for.
+pub const PASSWORD_PLACEHOLDERS: [&str; 4] = ["event", "store.name", "store.url", "store.logo"];
+
+// These are the values used for the parts of a template that weren't
+// set, which reproduce the monitor's original layout.
+pub const ITEM_TITLE: &str = "{product.title}";
+pub const ITEM_URL: &str = "{product.url}";
+pub const PASSWORD_TITLE: &str = "{event}!";
+pub const PASSWORD_URL: &str = "{store.url}";
+
+#[derive(Debug)]
+pub struct Template {
+    pub content: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+    pub size: Field,
+
+    // This is set if the template uses any of the `DETAILS`.
+    pub details: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+impl Default for Template {
+    fn default() -> Template {
+        Template::new()
+    }
+}
+
+impl Template {
+    // This converts the template included in the config file into the
+    // one used by the monitor, returning an error describing the first
+    // problem that's found, if any.
+    pub fn parse(template: &config::Template) -> Result<Template, String> {
+        let mut details = false;
+
+        for text in [
+            &template.content,
+            &template.title,
+            &template.url,
+            &template.description,
+        ]
+        .iter()
+        .copied()
+        .flatten()
+        {
+            details |= check(text, &PLACEHOLDERS)?;
+        }
+
+        let fields = if let Some(fields) = &template.fields {
+            let mut parsed = vec![];
+
+            for field in fields.clone() {
+                details |= check(&field.name, &PLACEHOLDERS)?;
+                details |= check(&field.value, &PLACEHOLDERS)?;
+
+                // Discord doesn't allow fields to be empty.
+                if field.name.trim().is_empty() || field.value.trim().is_empty() {
+                    return Err("Fields must have a name and a value!".into());
+                }
+
+                parsed.push(Field {
+                    name: field.name,
+                    value: field.value,
+                    inline: field.inline.unwrap_or(true),
+                });
+            }
+
+            parsed
+        } else {
+            item_fields()
+        };
+
+        let size = if let Some(size) = &template.size {
+            let allowed = [&PLACEHOLDERS[..], &VARIANT_PLACEHOLDERS[..]].concat();
+
+            details |= check(&size.name, &allowed)?;
+            details |= check(&size.value, &allowed)?;
+
+            Field {
+                name: size.name.clone(),
+                value: size.value.clone(),
+                inline: size.inline.unwrap_or(true),
+            }
+        } else {
+            size_field()
+        };
+
+        Ok(Template {
+            content: template.content.clone(),
+            title: template.title.clone(),
+            url: template.url.clone(),
+            description: template.description.clone(),
+            fields,
+            size,
+            details,
+        })
+    }
+
+    // The template used when the settings don't include one.
+    pub fn new() -> Template {
+        Template {
+            content: None,
+            title: None,
+            url: None,
+            description: None,
+            fields: item_fields(),
+            size: size_field(),
+            details: false,
+        }
+    }
+}
+
+// These are the three fields at the top of the original embeds.
+fn item_fields() -> Vec<Field> {
+    vec![
+        Field {
+            name: "Event".into(),
+            value: "{event}".into(),
+            inline: true,
+        },
+        Field {
+            name: "Brand".into(),
+            value: "{product.brand}".into(),
+            inline: true,
+        },
+        Field {
+            name: "Price".into(),
+            value: "{price}".into(),
+            inline: true,
+        },
+    ]
+}
+
+fn size_field() -> Field {
+    Field {
+        name: "Size {variant.title}".into(),
+        value: "[ATC]({atc_url})\n{variant.status}\n{variant.stock}".into(),
+        inline: true,
+    }
+}
+
+// This function goes through the text, returning an error if it finds
+// a placeholder that isn't allowed or a brace that isn't closed.
+// Literal braces can be included by doubling them, like in `format!()`.
+// Otherwise, it returns whether the text uses any of the `DETAILS`.
+fn check(text: &str, allowed: &[&str]) -> Result<bool, String> {
+    let mut details = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+
+                if !closed {
+                    return Err(format!("Unclosed placeholder in `{}`!", text));
+                }
+
+                if !allowed.contains(&name.trim()) {
+                    return Err(if VARIANT_PLACEHOLDERS.contains(&name.trim()) {
+                        format!("`{{{}}}` can only be used in `size`!", name)
+                    } else {
+                        format!("Unknown placeholder `{{{}}}`!", name)
+                    });
+                }
+
+                details |= DETAILS.contains(&name.trim());
+            }
+            '}' => return Err(format!("Unmatched `}}` in `{}`!", text)),
+            _ => {}
+        }
+    }
+
+    Ok(details)
+}
+
+// This returns whether the text can be used in password page embeds,
+// as a title like `{product.title}` would otherwise leave them empty.
+pub fn for_password(text: &str) -> bool {
+    check(text, &PASSWORD_PLACEHOLDERS).is_ok()
+}
+
+// Since templates are checked when they're loaded, any placeholder that
+// doesn't have a value is simply replaced with an empty string, which
+// happens, for example, when a product placeholder is used in a
+// password page embed.
+pub fn render(text: &str, values: &HashMap<&str, String>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                rendered.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                rendered.push('}');
+            }
+            '{' => {
+                let name = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+
+                if let Some(value) = values.get(name.trim()) {
+                    rendered.push_str(value);
+                }
+            }
+            _ => rendered.push(c),
+        }
+    }
+
+    rendered
+}
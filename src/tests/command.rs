@@ -0,0 +1,58 @@
+// These tests run real programs, so they rely on `sh` being available,
+// and check that commands can't block the monitor for longer than their
+// timeout, or run more often at once than their concurrency allows.
+
+#[cfg(test)]
+mod command_test {
+    use crate::command::{self, Command};
+    use serde_json::json;
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use tokio::sync::Semaphore;
+
+    fn c(script: &str, timeout: u64, concurrency: usize) -> Arc<Command> {
+        Arc::new(Command {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            timeout: Duration::from_millis(timeout),
+            limit: Arc::new(Semaphore::new(concurrency)),
+        })
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let start = Instant::now();
+        command::run(c("sleep 5", 200, 1), json!({})).await;
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn timeout_without_reading_input() {
+        // The event is larger than the pipe's buffer, so writing it would
+        // never finish, as the program doesn't read it.
+        let event = json!({ "padding": "a".repeat(1 << 20) });
+        let start = Instant::now();
+        command::run(c("sleep 5", 200, 1), event).await;
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn concurrency() {
+        let command = c("sleep 0.3", 5000, 1);
+        let start = Instant::now();
+
+        tokio::join!(
+            command::run(command.clone(), json!({})),
+            command::run(command.clone(), json!({})),
+        );
+
+        // With a single slot, the second program only starts once the
+        // first one is done.
+        assert!(start.elapsed() >= Duration::from_millis(600));
+        assert_eq!(command.limit.available_permits(), 1);
+    }
+}
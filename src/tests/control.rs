@@ -0,0 +1,211 @@
+// These tests check that the monitor can be controlled while it's
+// running, both through `Controller` and through the API exposed by the
+// HTTP server.
+
+#[cfg(test)]
+mod control_test {
+    use crate::{
+        config::{self, Config},
+        control::{Control, Controller},
+        feed::Feed,
+        metrics::Metrics,
+        server,
+        stores::{self, Store},
+    };
+    use reqwest::{Client, Method, StatusCode};
+    use serde_json::{json, Value};
+    use std::{net::TcpListener, sync::Arc, time::Duration};
+    use tokio::{sync::mpsc::Receiver, task, time::sleep};
+
+    // The channel receives events for "Store", which is included in the
+    // config file, and for "Added", which can only be added later on.
+    fn controller() -> (Arc<Controller>, Receiver<Store>, Store) {
+        let config = serde_json::from_value::<Config>(json!({
+            "sites": [{ "name": "Store", "url": "https://store.com", "logo": "shopify" }],
+            "servers": [{
+                "name": "Server",
+                "channels": [{
+                    "name": "Channel",
+                    "url": "https://discord.com/api/webhooks/1/a",
+                    "sites": [
+                        { "name": "Store", "events": [{ "restock": true }] },
+                        { "name": "Added", "events": [{ "restock": true }] },
+                    ],
+                }],
+            }],
+        }))
+        .unwrap();
+
+        let (mut stores, builder) = stores::get(config);
+        let (controller, added) = Controller::new(builder);
+
+        (controller, added, stores.remove(0))
+    }
+
+    fn site(name: &str) -> config::Site {
+        serde_json::from_value(json!({
+            "name": name,
+            "url": "https://added.com",
+            "logo": "shopify",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stores_are_controlled() {
+        let (controller, _added, store) = controller();
+        let (_handle, mut rx) = controller.register(&store);
+
+        let list = controller.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "Store");
+        assert!(list[0].last_poll.is_none());
+
+        assert!(controller.send("Store", Control::Pause).await);
+        assert!(matches!(rx.recv().await, Some(Control::Pause)));
+        assert!(!controller.send("Missing", Control::Pause).await);
+
+        controller.unregister("Store");
+        assert!(controller.list().is_empty());
+        assert!(!controller.send("Store", Control::Poll).await);
+    }
+
+    #[tokio::test]
+    async fn sites_are_added() {
+        let (controller, mut added, store) = controller();
+        controller.register(&store);
+
+        assert!(controller.add(site("Added")).await.is_ok());
+        let store = added.recv().await.unwrap();
+        assert_eq!(store.name, "Added");
+
+        // Stores that are already monitored, or that no channel receives
+        // events for, are rejected, including the ones that were added
+        // but haven't started yet.
+        assert!(controller.add(site("Added")).await.is_err());
+        assert!(controller.add(site("Store")).await.is_err());
+        assert!(controller.add(site("Unknown")).await.is_err());
+
+        // Once they stop, they can be added again.
+        controller.register(&store);
+        controller.unregister("Added");
+        assert!(controller.add(site("Added")).await.is_ok());
+    }
+
+    #[test]
+    fn channels_are_muted_by_server() {
+        let (controller, _added, _store) = controller();
+
+        controller.mute("Server", "Channel", true);
+        controller.mute("Other Server", "Other Channel", true);
+
+        assert!(controller.muted("Server", "Channel"));
+        assert!(!controller.muted("Other Server", "Channel"));
+        assert_eq!(
+            controller.muted_channels(),
+            vec![
+                ("Other Server".to_string(), "Other Channel".to_string()),
+                ("Server".to_string(), "Channel".to_string()),
+            ]
+        );
+
+        controller.mute("Server", "Channel", false);
+        assert!(!controller.muted("Server", "Channel"));
+    }
+
+    #[tokio::test]
+    async fn api() {
+        let (controller, _added, store) = controller();
+        let (_handle, mut rx) = controller.register(&store);
+
+        // The server binds to the address itself, so a free port is found
+        // first.
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        task::spawn(server::run(
+            config::Http {
+                address: address.clone(),
+                token: Some("token".into()),
+            },
+            Arc::new(Metrics::default()),
+            controller.clone(),
+            Arc::new(Feed::new()),
+        ));
+
+        let client = Client::new();
+        let url = |path: &str| format!("http://{}/api/{}", address, path);
+
+        // The server might take a moment to start listening.
+        let mut unauthorized = None;
+        for _ in 0..50 {
+            if let Ok(res) = client.get(url("stores")).send().await {
+                unauthorized = Some(res.status());
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(unauthorized, Some(StatusCode::UNAUTHORIZED));
+
+        let send = |method: reqwest::Method, path: &str, body: Option<Value>| {
+            let mut req = client.request(method, url(path)).bearer_auth("token");
+            if let Some(body) = body {
+                req = req.json(&body);
+            }
+            req.send()
+        };
+
+        let res = send(Method::GET, "stores", None).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let stores = res.json::<Value>().await.unwrap();
+        assert_eq!(stores[0]["name"], "Store");
+
+        let res = send(Method::POST, "stores/Store/pause", None)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(matches!(rx.recv().await, Some(Control::Pause)));
+
+        let res = send(
+            Method::POST,
+            "stores/Store/delay",
+            Some(json!({ "delay": 5000 })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(matches!(rx.recv().await, Some(Control::Delay(5000))));
+
+        let res = send(
+            Method::POST,
+            "stores/Store/delay",
+            Some(json!({ "delay": "soon" })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = send(Method::DELETE, "stores/Missing", None).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // Names are percent-encoded, and channels are muted along with
+        // the name of their server.
+        let res = send(Method::POST, "channels/Server/Channel%201/mute", None)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(controller.muted("Server", "Channel 1"));
+
+        let res = send(Method::GET, "channels", None).await.unwrap();
+        assert_eq!(
+            res.json::<Value>().await.unwrap(),
+            json!({ "muted": [{ "server": "Server", "channel": "Channel 1" }] })
+        );
+
+        let res = send(Method::GET, "unknown", None).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,144 @@
+// This document contains tests for `discovery`, which finds products in
+// a store's feeds and sitemaps before they're listed on
+// `/products.json`.
+
+#[cfg(test)]
+mod discovery_test {
+    use crate::{
+        config::Source,
+        discovery::{self, Discovery, GIVE_UP},
+        event::{Event, Store},
+        tests::mock::{Reply, Shopify},
+    };
+    use serde_json::json;
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    };
+    use tokio::time;
+
+    #[test]
+    fn feeds_list_products() {
+        let atom = r#"<feed>
+            <link rel="alternate" type="text/html" href="https://store.com/collections/all" />
+            <entry>
+                <link rel="alternate" type="text/html" href="https://store.com/products/dunk-low" />
+                <s:image><s:src>https://cdn.shopify.com/s/files/1/products/dunk.png</s:src></s:image>
+            </entry>
+        </feed>"#;
+        let sitemap = r#"<urlset>
+            <url>
+                <loc>https://store.com/products/air-max-1</loc>
+                <image:image><image:loc>https://cdn.shopify.com/s/files/1/products/air-max.png</image:loc></image:image>
+            </url>
+        </urlset>"#;
+
+        assert_eq!(
+            discovery::handles(atom, Source::Atom)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["dunk-low"]
+        );
+        assert_eq!(
+            discovery::handles(sitemap, Source::Sitemap)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["air-max-1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn discovery_finds_new_products() {
+        let page = |id: u64, handle: &str| {
+            Reply::page(json!({
+                "id": id,
+                "title": handle,
+                "handle": handle,
+                "vendor": "Nike",
+                "variants": [],
+                "images": [],
+            }))
+        };
+
+        // The product that `/products.json` already lists isn't announced
+        // again.
+        let shopify = Shopify::start(vec![
+            Reply::sitemap(&["dunk-low"]),
+            Reply::sitemap(&["dunk-low", "air-max-1"]),
+            page(2, "air-max-1"),
+            Reply::sitemap(&["dunk-low", "air-max-1", "jordan-1"]),
+            page(1, "jordan-1"),
+        ])
+        .await;
+        let store = Store {
+            name: "Store".into(),
+            url: shopify.url.clone(),
+            logo: "https://store.com/logo.png".into(),
+        };
+        let listed = Arc::new(Mutex::new(vec![1].into_iter().collect()));
+        let mut discovery =
+            Discovery::new(&shopify.url, store, &[Source::Sitemap], false, listed, &[]);
+
+        assert!(discovery.poll().await.is_empty());
+
+        match discovery.poll().await.as_slice() {
+            [Event::NewProduct { product, .. }] => assert_eq!(product.handle, "air-max-1"),
+            events => panic!("Expected a new product, found {:?}.", events),
+        }
+
+        assert!(discovery.poll().await.is_empty());
+        assert_eq!(shopify.requests(), 5);
+    }
+
+    #[tokio::test]
+    async fn discovery_skips_watchlist_and_gives_up() {
+        // Once the script is over, every request fails, so the sitemap
+        // isn't read again, and the product that never went live is
+        // looked up until it's given up on, an hour after it was found.
+        // The product on the watchlist is never looked up.
+        let shopify = Shopify::start(vec![
+            Reply::sitemap(&["air-max-1"]),
+            Reply::sitemap(&["air-max-1", "dunk-low", "jordan-1"]),
+            Reply::status(404),
+        ])
+        .await;
+        let store = Store {
+            name: "Store".into(),
+            url: shopify.url.clone(),
+            logo: "https://store.com/logo.png".into(),
+        };
+        let listed = Arc::new(Mutex::new(HashSet::new()));
+        let mut discovery = Discovery::new(
+            &shopify.url,
+            store,
+            &[Source::Sitemap],
+            false,
+            listed,
+            &["dunk-low".into()],
+        );
+
+        let lookups = || {
+            shopify
+                .received()
+                .iter()
+                .filter(|request| request.target.starts_with("/products/"))
+                .map(|request| request.target.clone())
+                .collect::<Vec<_>>()
+        };
+
+        for _ in 0..3 {
+            assert!(discovery.poll().await.is_empty());
+        }
+        assert_eq!(lookups(), vec!["/products/jordan-1.js"; 2]);
+
+        // The clock is moved forward rather than waiting for an hour.
+        time::pause();
+        time::advance(GIVE_UP).await;
+        time::resume();
+
+        for _ in 0..2 {
+            assert!(discovery.poll().await.is_empty());
+        }
+        assert_eq!(lookups(), vec!["/products/jordan-1.js"; 3]);
+    }
+}
@@ -0,0 +1,25 @@
+// This document contains tests for the output of dry runs, which
+// shouldn't include anything that could be used to send messages.
+
+#[cfg(test)]
+mod dry_run_test {
+    use crate::dry_run::redact;
+
+    #[test]
+    fn tokens_are_redacted() {
+        assert_eq!(
+            redact("https://discord.com/api/webhooks/123/secret"),
+            "https://discord.com/api/webhooks/123/[token]"
+        );
+        assert_eq!(
+            redact("https://discord.com/api/webhooks/123/secret?wait=true"),
+            "https://discord.com/api/webhooks/123/[token]?wait=true"
+        );
+
+        // Anything that isn't a webhook is left as it is.
+        assert_eq!(
+            redact("https://example.com/hook"),
+            "https://example.com/hook"
+        );
+    }
+}
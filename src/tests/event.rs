@@ -0,0 +1,459 @@
+// This document contains tests for `diff::Detector`, which turns the
+// responses into events, and for `router`, `cooldown` and `render`,
+// which decide which channels an event is sent to and what they receive,
+// without sending any requests, as well as for the two ways products are
+// parsed.
+
+#[cfg(test)]
+mod event_test {
+    use crate::{
+        config,
+        cooldown::Cooldowns,
+        diff::Detector,
+        event::{Event, Store},
+        products::{self, FeaturedImage, Product, Variant},
+        render::{self, available_product, Item, Posted},
+        router::accepts,
+        stores::{Channel, Filter, Settings},
+        template::Template,
+    };
+    use std::{sync::Arc, time::Instant};
+
+    // Every test is about the same product and store, so only the
+    // variants and the channel's settings change between them.
+    fn p(variants: Vec<Variant>) -> Product {
+        Product {
+            id: 1,
+            title: "Air Jordan 1".into(),
+            handle: "air-jordan-1".into(),
+            updated_at: "".into(),
+            vendor: "Nike".into(),
+            variants,
+            images: vec![],
+            ..Default::default()
+        }
+    }
+
+    fn v(id: u64, title: &str, available: bool) -> Variant {
+        Variant {
+            id,
+            title: title.into(),
+            available,
+            price: "170.00".into(),
+            ..Default::default()
+        }
+    }
+
+    fn s() -> Store {
+        Store {
+            name: "Store".into(),
+            url: "https://store.com".into(),
+            logo: "https://store.com/logo.png".into(),
+        }
+    }
+
+    fn c(settings: Settings) -> Channel {
+        Channel {
+            name: "Channel".into(),
+            server: "Server".into(),
+            url: "https://discord.com/api/webhooks/0/token".into(),
+            command: None,
+            settings,
+        }
+    }
+
+    fn restock(variants: Vec<Variant>, restocked: &[u64]) -> Event {
+        Event::Restock {
+            store: s(),
+            product: p(variants),
+            restocked: restocked.to_vec(),
+            collection: None,
+        }
+    }
+
+    fn sold_out(variants: Vec<Variant>) -> Event {
+        Event::SoldOut {
+            store: s(),
+            product: p(variants),
+            collection: None,
+        }
+    }
+
+    #[test]
+    fn restock_below_minimum() {
+        let mut settings = Settings::new();
+        settings.minimum = 2;
+
+        let event = restock(vec![v(1, "9", true), v(2, "10", false)], &[1]);
+        assert!(!accepts(&event, &c(settings)));
+    }
+
+    #[test]
+    fn restock_at_minimum() {
+        let mut settings = Settings::new();
+        settings.minimum = 2;
+
+        let event = restock(vec![v(1, "9", true), v(2, "10", true)], &[1]);
+        assert!(accepts(&event, &c(settings)));
+    }
+
+    #[test]
+    fn restock_threshold() {
+        let mut settings = Settings::new();
+        settings.restocked = 2;
+        let channel = c(settings);
+
+        // Sizes that were already available don't count towards it.
+        let event = restock(vec![v(1, "9", true), v(2, "10", true)], &[1]);
+        assert!(!accepts(&event, &channel));
+
+        let event = restock(vec![v(1, "9", true), v(2, "10", true)], &[1, 2]);
+        assert!(accepts(&event, &channel));
+    }
+
+    #[test]
+    fn cooldown() {
+        let mut settings = Settings::new();
+        settings.cooldown = 60;
+        let channel = c(settings);
+        let cooldowns = Cooldowns::default();
+
+        let event = restock(vec![v(1, "9", true), v(2, "10", true)], &[1]);
+        assert!(cooldowns.allow(&channel, &event));
+        assert!(!cooldowns.allow(&channel, &event));
+
+        // A restock is still sent if any of its sizes isn't cooling down.
+        let event = restock(vec![v(1, "9", true), v(2, "10", true)], &[1, 2]);
+        assert!(cooldowns.allow(&channel, &event));
+
+        // Channels without a cooldown receive every restock.
+        let channel = c(Settings::new());
+        assert!(cooldowns.allow(&channel, &event));
+        assert!(cooldowns.allow(&channel, &event));
+    }
+
+    #[test]
+    fn cooldown_command_channels() {
+        let mut settings = Settings::new();
+        settings.cooldown = 60;
+
+        // Channels running a command don't have a URL, so they're told
+        // apart by their name and server instead.
+        let command = |server: &str, name: &str| Channel {
+            name: name.into(),
+            server: server.into(),
+            url: "".into(),
+            ..c(settings.clone())
+        };
+        let cooldowns = Cooldowns::default();
+
+        let event = restock(vec![v(1, "9", true)], &[1]);
+        assert!(cooldowns.allow(&command("Server", "Script"), &event));
+        assert!(cooldowns.allow(&command("Server", "Other Script"), &event));
+        assert!(cooldowns.allow(&command("Other Server", "Script"), &event));
+        assert!(!cooldowns.allow(&command("Server", "Script"), &event));
+    }
+
+    #[test]
+    fn sold_out_only_edits() {
+        let event = sold_out(vec![v(1, "9", false)]);
+        assert!(!accepts(&event, &c(Settings::new())));
+
+        let mut settings = Settings::new();
+        settings.edit = true;
+        assert!(accepts(&event, &c(settings)));
+    }
+
+    #[test]
+    fn render_restock() {
+        let mut settings = Settings::new();
+        settings.sizes = true;
+
+        let event = restock(vec![v(1, "- / 9", true), v(2, "10", false)], &[1]);
+        let msg = render::item(&event, &settings, None).unwrap();
+        let embed = &msg.embeds.unwrap()[0];
+
+        assert_eq!(embed.title.as_deref(), Some("Air Jordan 1"));
+        assert_eq!(
+            embed.url.as_deref(),
+            Some("https://store.com/products/air-jordan-1")
+        );
+
+        // Only the available size is listed, after the template's three
+        // fields, and its name is normalized.
+        let fields = embed.fields.as_ref().unwrap();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[3].name, "Size 9");
+    }
+
+    #[test]
+    fn render_restocked_sizes() {
+        let mut settings = Settings::new();
+        settings.sizes = true;
+
+        let event = restock(vec![v(1, "9", true), v(2, "10", true)], &[2]);
+        let msg = render::item(&event, &settings, None).unwrap();
+        let embeds = msg.embeds.unwrap();
+        let fields = embeds[0].fields.as_ref().unwrap();
+
+        assert_eq!(
+            fields[3].value,
+            "[ATC](https://store.com/cart/add?id=1)\nStill Available"
+        );
+        assert_eq!(
+            fields[4].value,
+            "[ATC](https://store.com/cart/add?id=2)\nRestocked"
+        );
+
+        // New products don't tell the sizes apart.
+        let event = Event::NewProduct {
+            store: s(),
+            product: p(vec![v(1, "9", true)]),
+            collection: None,
+        };
+        let msg = render::item(&event, &settings, None).unwrap();
+        let embeds = msg.embeds.unwrap();
+
+        assert_eq!(
+            embeds[0].fields.as_ref().unwrap()[3].value,
+            "[ATC](https://store.com/cart/add?id=1)"
+        );
+    }
+
+    #[test]
+    fn render_template_keeps_its_layout() {
+        let template = serde_json::from_str::<config::Template>(
+            r#"{ "size": { "name": "  Size {variant.title}", "value": "[ATC]({atc_url})\n\n{variant.status}\nStock: {variant.stock}" } }"#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::new();
+        settings.sizes = true;
+        settings.template = Some(Arc::new(Template::parse(&template).unwrap()));
+
+        // Only the line left blank by `{variant.status}` is dropped, while
+        // the empty line and the indentation the template has are kept.
+        let event = Event::NewProduct {
+            store: s(),
+            product: p(vec![v(1, "9", true)]),
+            collection: None,
+        };
+        let msg = render::item(&event, &settings, None).unwrap();
+        let embeds = msg.embeds.unwrap();
+        let field = embeds[0].fields.as_ref().unwrap().last().unwrap();
+
+        assert_eq!(field.name, "  Size 9");
+        assert_eq!(
+            field.value,
+            "[ATC](https://store.com/cart/add?id=1)\n\nStock: "
+        );
+    }
+
+    #[test]
+    fn render_sold_out_without_message() {
+        let event = sold_out(vec![v(1, "9", false)]);
+        assert!(render::item(&event, &Settings::new(), None).is_none());
+    }
+
+    #[test]
+    fn render_password_with_item_template() {
+        let template = serde_json::from_str::<config::Template>(
+            r#"{ "title": "{product.title}", "url": "{product.url}", "content": "{event} on {store.name}" }"#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::new();
+        settings.template = Some(Arc::new(Template::parse(&template).unwrap()));
+
+        // The parts of the template that are about a product fall back to
+        // the usual ones, while the others are still used.
+        let msg = render::password(&Event::PasswordUp { store: s() }, &settings).unwrap();
+        let embed = &msg.embeds.as_ref().unwrap()[0];
+
+        assert_eq!(embed.title.as_deref(), Some("Password Page Up!"));
+        assert_eq!(embed.url.as_deref(), Some("https://store.com"));
+        assert_eq!(msg.content.as_deref(), Some("Password Page Up on Store"));
+    }
+
+    #[test]
+    fn render_sold_out_strikes_sizes() {
+        let mut settings = Settings::new();
+        settings.sizes = true;
+
+        let posted = Posted {
+            id: "0".into(),
+            time: Instant::now(),
+            kind: Item::Restock,
+            product: available_product(&p(vec![v(1, "9", true), v(2, "10", true)]), &[]),
+        };
+
+        let event = sold_out(vec![v(1, "9", true), v(2, "10", false)]);
+        let msg = render::item(&event, &settings, Some(&posted)).unwrap();
+        let embeds = msg.embeds.unwrap();
+        let fields = embeds[0].fields.as_ref().unwrap();
+
+        assert!(fields.iter().any(|field| field.name == "~~Size 10~~"));
+        assert!(fields.iter().any(|field| field.name == "Size 9"));
+    }
+
+    #[test]
+    fn render_password() {
+        let event = Event::PasswordUp { store: s() };
+        let msg = render::password(&event, &Settings::new()).unwrap();
+
+        assert_eq!(
+            msg.embeds.unwrap()[0].title.as_deref(),
+            Some("Password Page Up!")
+        );
+        assert!(render::item(&event, &Settings::new(), None).is_none());
+    }
+
+    #[test]
+    fn command_payload() {
+        let event = restock(vec![v(1, "9", true), v(2, "10", false)], &[1]);
+        let value = render::command(&event);
+
+        assert_eq!(value["event"], "restock");
+        assert_eq!(value["product"]["brand"], "Nike");
+        assert_eq!(value["variants"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            value["variants"][0]["atc_url"],
+            "https://store.com/cart/add?id=1"
+        );
+    }
+
+    #[test]
+    fn detector() {
+        let mut detector = Detector::new(s());
+        let mut product = p(vec![v(1, "9", false)]);
+
+        // The first response is only used to compare the next ones to.
+        assert!(detector.detect(200, Some(&[product.clone()])).is_empty());
+
+        // Errors and rate limits don't change anything, as their products
+        // couldn't be parsed.
+        assert!(detector.detect(429, None).is_empty());
+        assert!(detector.detect(200, None).is_empty());
+
+        let names = |events: Vec<Event>| events.iter().map(Event::name).collect::<Vec<_>>();
+
+        assert_eq!(names(detector.detect(401, None)), vec!["password_up"]);
+        assert_eq!(names(detector.detect(401, None)), Vec::<&str>::new());
+        assert!(detector.password());
+
+        // Restocks are found even if `updated_at` didn't change.
+        product.variants[0].available = true;
+
+        assert_eq!(
+            names(detector.detect(200, Some(&[product]))),
+            vec!["password_down", "restock"]
+        );
+        assert!(!detector.password());
+    }
+
+    #[test]
+    fn parse_lean_and_full() {
+        let body = r#"{"products": [{
+            "id": 1,
+            "title": "Dunk Low",
+            "handle": "dunk-low",
+            "published_at": "2022-01-01T10:00:00-05:00",
+            "updated_at": "2022-01-01T10:00:00-05:00",
+            "vendor": "Nike",
+            "product_type": "Footwear",
+            "tags": ["dunk", "release"],
+            "variants": [{
+                "id": 2,
+                "title": "Panda / 9",
+                "option1": "Panda",
+                "option2": "9",
+                "option3": null,
+                "sku": "DD1391-100-9",
+                "featured_image": {"id": 3, "src": "https://cdn.shopify.com/panda.png", "alt": null},
+                "available": true,
+                "price": "110.00",
+                "compare_at_price": null
+            }],
+            "images": [{"src": "https://cdn.shopify.com/dunk.png"}],
+            "options": [{"name": "Color", "position": 1, "values": ["Panda"]}]
+        }]}"#;
+
+        let full = products::parse(body, true).unwrap();
+        assert_eq!(full[0].tags, vec!["dunk", "release"]);
+        assert_eq!(full[0].product_type, "Footwear");
+        assert_eq!(full[0].options[0].values, vec!["Panda"]);
+        assert_eq!(full[0].variants[0].option2.as_deref(), Some("9"));
+        assert_eq!(full[0].variants[0].option3, None);
+        assert_eq!(
+            full[0].variants[0]
+                .featured_image
+                .as_ref()
+                .map(|image| image.id),
+            Some(3)
+        );
+
+        // Lean parsing reads the same products, without their details.
+        let lean = products::parse(body, false).unwrap();
+        assert_eq!(lean[0].variants[0].price, "110.00");
+        assert!(lean[0].tags.is_empty());
+        assert_eq!(lean[0].variants[0].sku, None);
+        assert_eq!(lean[0].variants[0].featured_image, None);
+    }
+
+    #[test]
+    fn filter_by_tags_and_type() {
+        let mut settings = Settings::new();
+        settings.filter = Some(Arc::new(Filter {
+            tags: vec!["release".into()],
+            exclude_tags: vec!["raffle".into()],
+            types: vec!["footwear".into()],
+        }));
+        let channel = c(settings);
+
+        let event = |tags: &[&str], product_type: &str| {
+            let mut product = p(vec![v(1, "9", true)]);
+            product.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            product.product_type = product_type.into();
+
+            Event::NewProduct {
+                store: s(),
+                product,
+                collection: None,
+            }
+        };
+
+        assert!(accepts(&event(&["Release"], "Footwear"), &channel));
+        assert!(!accepts(&event(&[], "Footwear"), &channel));
+        assert!(!accepts(
+            &event(&["release", "raffle"], "Footwear"),
+            &channel
+        ));
+        assert!(!accepts(&event(&["release"], "Apparel"), &channel));
+    }
+
+    #[test]
+    fn render_variant_image() {
+        let mut settings = Settings::new();
+        settings.thumbnail = true;
+
+        let mut colorway = v(2, "10", true);
+        colorway.featured_image = Some(FeaturedImage {
+            src: "https://store.com/panda.png".into(),
+            ..Default::default()
+        });
+
+        let mut event = restock(vec![v(1, "9", true), colorway], &[2]);
+        if let Event::Restock { product, .. } = &mut event {
+            product.images = vec![products::Image {
+                src: "https://store.com/dunk.png".into(),
+            }];
+        }
+
+        // The image of the colorway that restocked replaces the product's.
+        let msg = render::item(&event, &settings, None).unwrap();
+        assert_eq!(
+            msg.embeds.unwrap()[0].thumbnail.as_ref().unwrap().url,
+            "https://store.com/panda.png"
+        );
+    }
+}
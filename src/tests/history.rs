@@ -0,0 +1,373 @@
+// This document contains tests for the queries of the `history` and
+// `export` commands and of the daily digests, which read the events and
+// snapshots saved in an in-memory database.
+
+#[cfg(test)]
+mod history_test {
+    use crate::history::*;
+    use chrono::prelude::*;
+    use rusqlite::Connection;
+
+    fn database() -> Connection {
+        open(":memory:").unwrap()
+    }
+
+    fn time(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().into()
+    }
+
+    fn event(connection: &Connection, at: &str, event: &'static str, title: &str) {
+        product_event(connection, "Store", at, event, title);
+    }
+
+    fn product_event(
+        connection: &Connection,
+        store: &str,
+        at: &str,
+        event: &'static str,
+        title: &str,
+    ) {
+        let record = Record::Event(Event {
+            time: time(at),
+            store: store.into(),
+            event,
+            product: Some(Product {
+                id: 1,
+                handle: title.to_lowercase().replace(' ', "-"),
+                title: title.into(),
+                price: "110.00".into(),
+                variants: vec![(11, "9".into()), (12, "10".into())],
+            }),
+        });
+
+        insert(connection, record).unwrap();
+    }
+
+    fn password(connection: &Connection, store: &str, at: &str, event: &'static str) {
+        let record = Record::Event(Event {
+            time: time(at),
+            store: store.into(),
+            event,
+            product: None,
+        });
+
+        insert(connection, record).unwrap();
+    }
+
+    fn snapshot(
+        connection: &Connection,
+        at: &str,
+        variant_id: u64,
+        variant: &str,
+        available: bool,
+    ) {
+        let record = Record::Snapshot(Snapshot {
+            time: time(at),
+            store: "Store".into(),
+            product_id: 1,
+            handle: "dunk-low".into(),
+            variant_id,
+            variant: variant.into(),
+            available,
+        });
+
+        insert(connection, record).unwrap();
+    }
+
+    fn export(connection: &Connection, filters: &Filters, format: Format) -> Vec<String> {
+        let mut output = vec![];
+        write_events(connection, filters, &format, &mut output).unwrap();
+
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn stock_periods() {
+        let connection = database();
+
+        // The second snapshot doesn't change anything, and the last
+        // period is still open, as the size hasn't sold out since.
+        snapshot(&connection, "2021-12-24T10:00:00.000Z", 11, "9", true);
+        snapshot(&connection, "2021-12-24T10:05:00.000Z", 11, "9", true);
+        snapshot(&connection, "2021-12-24T10:10:00.000Z", 11, "9", false);
+        snapshot(&connection, "2021-12-24T11:00:00.000Z", 11, "9", true);
+        snapshot(&connection, "2021-12-24T10:00:00.000Z", 12, "10", false);
+
+        assert_eq!(
+            periods(&connection, "store", "DUNK-LOW", None).unwrap(),
+            vec![
+                (
+                    "9".to_string(),
+                    vec![
+                        (
+                            "2021-12-24T10:00:00.000Z".to_string(),
+                            Some("2021-12-24T10:10:00.000Z".to_string())
+                        ),
+                        ("2021-12-24T11:00:00.000Z".to_string(), None),
+                    ]
+                ),
+                ("10".to_string(), vec![]),
+            ]
+        );
+
+        let sizes = periods(&connection, "Store", "dunk-low", Some("10")).unwrap();
+        assert_eq!(sizes, vec![("10".to_string(), vec![])]);
+        assert!(periods(&connection, "Store", "missing", None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        assert_eq!(csv("Dunk Low"), "Dunk Low");
+        assert_eq!(csv("9, 10"), "\"9, 10\"");
+        assert_eq!(csv("Dunk \"Panda\""), "\"Dunk \"\"Panda\"\"\"");
+        assert_eq!(csv("Line\nbreak"), "\"Line\nbreak\"");
+    }
+
+    #[test]
+    fn events_are_exported() {
+        let connection = database();
+        event(
+            &connection,
+            "2021-12-24T10:00:00.000Z",
+            "new",
+            "Dunk \"Panda\"",
+        );
+        event(
+            &connection,
+            "2021-12-25T10:00:00.000Z",
+            "restock",
+            "Air Max 90",
+        );
+
+        let lines = export(&connection, &Filters::default(), Format::Csv);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "time,store,event,product_id,handle,title,price,sizes"
+        );
+        assert!(lines[1].ends_with(
+            ",Store,new,1,\"dunk-\"\"panda\"\"\",\"Dunk \"\"Panda\"\"\",110.00,\"9, 10\""
+        ));
+
+        let filters = Filters {
+            from: Some("2021-12-25T00:00:00.000Z".into()),
+            ..Filters::default()
+        };
+        let lines = export(&connection, &filters, Format::Jsonl);
+        assert_eq!(lines.len(), 1);
+
+        let line = serde_json::from_str::<serde_json::Value>(&lines[0]).unwrap();
+        assert_eq!(line["time"], "2021-12-25T10:00:00.000Z");
+        assert_eq!(line["variants"][1]["id"], 12);
+    }
+
+    // The keyword is matched literally, so `%` and `_` aren't wildcards.
+    #[test]
+    fn keywords_are_escaped() {
+        let connection = database();
+        event(&connection, "2021-12-24T10:00:00.000Z", "new", "50% Off");
+        event(&connection, "2021-12-24T11:00:00.000Z", "new", "500 Pairs");
+        event(&connection, "2021-12-24T12:00:00.000Z", "new", "Dunk_Low");
+        event(&connection, "2021-12-24T13:00:00.000Z", "new", "Dunk Low");
+
+        let titles = |keyword: &str| {
+            let filters = Filters {
+                keyword: Some(keyword.into()),
+                ..Filters::default()
+            };
+
+            export(&connection, &filters, Format::Jsonl)
+                .iter()
+                .map(|line| {
+                    serde_json::from_str::<serde_json::Value>(line).unwrap()["title"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(titles("50%"), vec!["50% Off"]);
+        assert_eq!(titles("Dunk_"), vec!["Dunk_Low"]);
+        assert_eq!(titles("dunk"), vec!["Dunk_Low", "Dunk Low"]);
+    }
+
+    // Dates are in the user's timezone, and include the whole day when
+    // they're the end of the range.
+    #[test]
+    fn bounds() {
+        let midnight = |day| {
+            Local
+                .with_ymd_and_hms(2021, 12, day, 0, 0, 0)
+                .earliest()
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        assert_eq!(bound("2021-12-24", false), Some(midnight(24)));
+        assert_eq!(bound("2021-12-24", true), Some(midnight(25)));
+        assert_eq!(
+            bound("2021-12-24T10:00:00Z", true),
+            Some(time("2021-12-24T10:00:00Z"))
+        );
+
+        let week = Utc::now() - chrono::Duration::days(7);
+        let from = bound("7d", false).unwrap();
+        assert!((from - week).num_seconds().abs() < 60);
+
+        assert_eq!(bound("soon", false), None);
+        assert_eq!(bound("2021-13-01", true), None);
+    }
+
+    // The digests cover the day before they're sent.
+    fn day(connection: &Connection, stores: &[&str], top: usize) -> Summary {
+        let stores = stores
+            .iter()
+            .map(|store| store.to_string())
+            .collect::<Vec<_>>();
+
+        summary(
+            connection,
+            &stores,
+            time("2021-12-24T00:00:00.000Z"),
+            time("2021-12-25T00:00:00.000Z"),
+            top,
+        )
+        .unwrap()
+    }
+
+    fn hours(summary: &Summary, store: &str) -> f64 {
+        let store = summary.stores.iter().find(|s| s.name == store).unwrap();
+        store.password.as_secs_f64() / 3600.0
+    }
+
+    #[test]
+    fn password_uptime() {
+        let connection = database();
+
+        // The password page went up before the day started, and came
+        // back up at the end of it, until the next day. Only the last
+        // event before the day tells whether it was up when it started,
+        // and repeated events don't start a new period.
+        password(
+            &connection,
+            "Store",
+            "2021-12-23T20:00:00.000Z",
+            "password_down",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-23T22:00:00.000Z",
+            "password_up",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-24T02:00:00.000Z",
+            "password_down",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-24T10:00:00.000Z",
+            "password_up",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-24T10:30:00.000Z",
+            "password_up",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-24T11:00:00.000Z",
+            "password_down",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-24T23:00:00.000Z",
+            "password_up",
+        );
+        password(
+            &connection,
+            "Store",
+            "2021-12-25T01:00:00.000Z",
+            "password_down",
+        );
+
+        // This store's password page was up the whole day, and this one
+        // came down before it.
+        password(
+            &connection,
+            "Open",
+            "2021-12-20T00:00:00.000Z",
+            "password_up",
+        );
+        password(
+            &connection,
+            "Down",
+            "2021-12-20T00:00:00.000Z",
+            "password_up",
+        );
+        password(
+            &connection,
+            "Down",
+            "2021-12-23T00:00:00.000Z",
+            "password_down",
+        );
+
+        let summary = day(&connection, &["Store", "Open", "Down", "Missing"], 5);
+        assert_eq!(hours(&summary, "Store"), 4.0);
+        assert_eq!(hours(&summary, "Open"), 24.0);
+        assert_eq!(hours(&summary, "Down"), 0.0);
+        assert_eq!(hours(&summary, "Missing"), 0.0);
+    }
+
+    #[test]
+    fn top_restocks() {
+        let connection = database();
+        let restock = |store, at, title| product_event(&connection, store, at, "restock", title);
+
+        restock("Store", "2021-12-23T12:00:00.000Z", "Air Jordan 1");
+        restock("Store", "2021-12-23T13:00:00.000Z", "Air Jordan 1");
+        restock("Store", "2021-12-24T01:00:00.000Z", "Dunk Low");
+        restock("Store", "2021-12-24T02:00:00.000Z", "Dunk Low");
+        restock("Store", "2021-12-24T03:00:00.000Z", "Air Jordan 1");
+        restock("Store", "2021-12-24T04:00:00.000Z", "Air Max 90");
+        restock("Store", "2021-12-25T00:00:00.000Z", "Air Max 90");
+        event(
+            &connection,
+            "2021-12-24T05:00:00.000Z",
+            "new",
+            "Air Force 1",
+        );
+
+        // Stores that the channel doesn't receive events for are left
+        // out.
+        for _ in 0..3 {
+            restock("Other", "2021-12-24T06:00:00.000Z", "Blazer");
+        }
+
+        let summary = day(&connection, &["Store"], 2);
+        assert_eq!(summary.stores[0].new, 1);
+        assert_eq!(summary.stores[0].restocks, 4);
+
+        // Products that restocked as many times are sorted by their
+        // latest restock.
+        let restocked = summary
+            .restocked
+            .iter()
+            .map(|product| (product.handle.as_str(), product.restocks))
+            .collect::<Vec<_>>();
+        assert_eq!(restocked, vec![("dunk-low", 2), ("air-max-90", 1)]);
+    }
+}
@@ -0,0 +1,149 @@
+// These tests check who is pinged by each message, which depends on the
+// `mention` setting and on the keyword groups matching the product.
+
+#[cfg(test)]
+mod mention_test {
+    use crate::{
+        config::Config,
+        event::{Event, Store},
+        products::{Product, Variant},
+        render,
+        stores::{self, Keyword, Mention, Settings},
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn new_product(title: &str) -> Event {
+        Event::NewProduct {
+            store: Store {
+                name: "Store".into(),
+                url: "https://store.com".into(),
+                logo: "https://store.com/logo.png".into(),
+            },
+            product: Product {
+                title: title.into(),
+                variants: vec![Variant {
+                    id: 1,
+                    title: "9".into(),
+                    available: true,
+                    price: "110.00".into(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            collection: None,
+        }
+    }
+
+    fn roles(roles: &[&str]) -> Arc<Mention> {
+        Arc::new(Mention {
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    fn keyword(include: &str, mention: Option<Option<Arc<Mention>>>) -> Arc<Keyword> {
+        Arc::new(Keyword {
+            include: vec![include.into()],
+            exclude: vec![],
+            mention,
+        })
+    }
+
+    #[test]
+    fn keyword_matches() {
+        let keyword = Keyword {
+            include: vec!["jordan".into(), "1".into()],
+            exclude: vec!["kids".into()],
+            mention: None,
+        };
+
+        assert!(keyword.matches("Air Jordan 1 High"));
+        assert!(!keyword.matches("Air Jordan 4"));
+        assert!(!keyword.matches("Air Jordan 1 (Kids)"));
+    }
+
+    #[test]
+    fn keyword_overrides_mention() {
+        let mut settings = Settings::new();
+        settings.mention = Some(roles(&["1"]));
+        settings.keywords = vec![
+            keyword("jordan", Some(Some(roles(&["2"])))),
+            keyword("dunk", Some(None)),
+            keyword("max", None),
+        ];
+
+        let content = |title: &str| render::item(&new_product(title), &settings, None).unwrap();
+
+        let msg = content("Air Jordan 1");
+        assert_eq!(msg.content.as_deref(), Some("<@&2>"));
+        assert_eq!(msg.allowed_mentions.unwrap().roles, vec!["2"]);
+
+        // A group set to `null` pings no one, while one without a
+        // `mention` keeps the channel's.
+        let msg = content("Dunk Low");
+        assert_eq!(msg.content, None);
+        assert!(msg.allowed_mentions.is_none());
+
+        let msg = content("Air Max 1");
+        assert_eq!(msg.content.as_deref(), Some("<@&1>"));
+    }
+
+    #[test]
+    fn everyone_and_here() {
+        let mut settings = Settings::new();
+        settings.mention = Some(Arc::new(Mention {
+            here: true,
+            everyone: true,
+            ..Default::default()
+        }));
+
+        let msg = render::item(&new_product("Dunk Low"), &settings, None).unwrap();
+        let allowed = msg.allowed_mentions.unwrap();
+
+        assert_eq!(msg.content.as_deref(), Some("@everyone @here"));
+        assert_eq!(allowed.parse, vec!["everyone"]);
+        assert!(allowed.roles.is_empty() && allowed.users.is_empty());
+    }
+
+    #[test]
+    fn combine() {
+        let keywords = |channel: serde_json::Value| {
+            let config = serde_json::from_value::<Config>(json!({
+                "sites": [{ "name": "Store", "url": "https://store.com", "logo": "shopify" }],
+                "servers": [{
+                    "name": "Server",
+                    "settings": { "keywords": [{ "include": ["jordan"] }] },
+                    "channels": [{
+                        "name": "Channel",
+                        "url": "https://discord.com/api/webhooks/1/a",
+                        "settings": { "keywords": [channel] },
+                        "sites": [{ "name": "Store", "events": [{ "restock": true }] }],
+                    }],
+                }],
+            }))
+            .unwrap();
+
+            let (stores, _) = stores::get(config);
+            let channels = stores[0].restock.try_read().unwrap();
+
+            channels[0]
+                .settings
+                .keywords
+                .iter()
+                .map(|keyword| keyword.include.join(" "))
+                .collect::<Vec<_>>()
+        };
+
+        // The channel's groups are added to the server's, unless one of
+        // them has `combine` set to `false`.
+        assert_eq!(
+            keywords(json!({ "include": ["dunk"] })),
+            vec!["jordan", "dunk"]
+        );
+        assert_eq!(
+            keywords(json!({ "include": ["dunk"], "combine": false })),
+            vec!["dunk"]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+// This document contains tests for `Message::fit()`, which makes sure
+// that the messages sent by the monitor don't exceed any of Discord's
+// limits.
+
+#[cfg(test)]
+mod message_test {
+    use crate::message::*;
+
+    // Every embed is built with a footer and a timestamp, as they count
+    // towards the character limit and have to be kept when embeds are
+    // dropped, while `e()` adds as many fields as it's asked for.
+    fn m(embeds: Vec<Embed>) -> Message {
+        Message {
+            content: None,
+            embeds: Some(embeds),
+            username: None,
+            avatar_url: None,
+            allowed_mentions: None,
+        }
+    }
+
+    fn e(title: &str, fields: usize) -> Embed {
+        Embed {
+            title: Some(title.into()),
+            description: None,
+            url: None,
+            color: Some(0xffffff),
+            fields: if fields == 0 {
+                None
+            } else {
+                Some(
+                    (0..fields)
+                        .map(|i| f(&format!("Size {}", i), "ATC"))
+                        .collect(),
+                )
+            },
+            author: Some(Author {
+                name: "Store".into(),
+                url: None,
+                icon_url: None,
+            }),
+            footer: Some(Footer {
+                text: Some("Footer".into()),
+                icon_url: None,
+            }),
+            timestamp: Some("2021-12-21T00:00:00.000Z".into()),
+            image: None,
+            thumbnail: None,
+        }
+    }
+
+    fn f(name: &str, value: &str) -> Field {
+        Field {
+            name: name.into(),
+            value: value.into(),
+            inline: Some(true),
+        }
+    }
+
+    fn embeds(msg: &Message) -> &Vec<Embed> {
+        msg.embeds.as_ref().unwrap()
+    }
+
+    fn total(msg: &Message) -> usize {
+        embeds(msg).iter().map(Embed::length).sum()
+    }
+
+    #[test]
+    fn truncate_short() {
+        assert_eq!(truncate("abc".into(), 3), "abc");
+    }
+
+    #[test]
+    fn truncate_long() {
+        assert_eq!(truncate("abcd".into(), 3), "ab…");
+    }
+
+    #[test]
+    fn truncate_counts_characters() {
+        // Each of these characters takes up more than one byte.
+        assert_eq!(truncate("éééé".into(), 4), "éééé");
+    }
+
+    #[test]
+    fn untouched() {
+        let msg = m(vec![e("Title", 3)]).fit();
+
+        assert_eq!(embeds(&msg).len(), 1);
+        assert_eq!(embeds(&msg)[0].fields.as_ref().unwrap().len(), 3);
+        assert_eq!(embeds(&msg)[0].title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn title_at_limit() {
+        let title = "a".repeat(TITLE_LIMIT);
+        let msg = m(vec![e(&title, 0)]).fit();
+
+        assert_eq!(embeds(&msg)[0].title.as_ref(), Some(&title));
+    }
+
+    #[test]
+    fn title_over_limit() {
+        let msg = m(vec![e(&"a".repeat(TITLE_LIMIT + 1), 0)]).fit();
+        let title = embeds(&msg)[0].title.as_ref().unwrap();
+
+        assert_eq!(title.chars().count(), TITLE_LIMIT);
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn description_over_limit() {
+        let mut embed = e("", 0);
+        embed.description = Some("a".repeat(DESCRIPTION_LIMIT + 10));
+        let msg = m(vec![embed]).fit();
+
+        assert_eq!(
+            embeds(&msg)[0]
+                .description
+                .as_ref()
+                .unwrap()
+                .chars()
+                .count(),
+            DESCRIPTION_LIMIT
+        );
+    }
+
+    #[test]
+    fn field_over_limit() {
+        let mut embed = e("", 0);
+        embed.fields = Some(vec![f(
+            &"a".repeat(FIELD_NAME_LIMIT + 1),
+            &"b".repeat(FIELD_VALUE_LIMIT + 1),
+        )]);
+        let msg = m(vec![embed]).fit();
+        let field = &embeds(&msg)[0].fields.as_ref().unwrap()[0];
+
+        assert_eq!(field.name.chars().count(), FIELD_NAME_LIMIT);
+        assert_eq!(field.value.chars().count(), FIELD_VALUE_LIMIT);
+    }
+
+    #[test]
+    fn footer_and_author_over_limit() {
+        let mut embed = e("", 0);
+        embed.footer = Some(Footer {
+            text: Some("a".repeat(FOOTER_LIMIT + 1)),
+            icon_url: None,
+        });
+        embed.author = Some(Author {
+            name: "a".repeat(AUTHOR_LIMIT + 1),
+            url: None,
+            icon_url: None,
+        });
+        let msg = m(vec![embed]).fit();
+        let embed = &embeds(&msg)[0];
+
+        assert_eq!(
+            embed
+                .footer
+                .as_ref()
+                .unwrap()
+                .text
+                .as_ref()
+                .unwrap()
+                .chars()
+                .count(),
+            FOOTER_LIMIT
+        );
+        assert_eq!(
+            embed.author.as_ref().unwrap().name.chars().count(),
+            AUTHOR_LIMIT
+        );
+    }
+
+    #[test]
+    fn content_and_username_over_limit() {
+        let mut msg = m(vec![]);
+        msg.content = Some("a".repeat(CONTENT_LIMIT + 1));
+        msg.username = Some("a".repeat(USERNAME_LIMIT + 1));
+        let msg = msg.fit();
+
+        assert_eq!(msg.content.unwrap().chars().count(), CONTENT_LIMIT);
+        assert_eq!(msg.username.unwrap().chars().count(), USERNAME_LIMIT);
+    }
+
+    #[test]
+    fn fields_at_limit() {
+        let msg = m(vec![e("Title", FIELDS_LIMIT)]).fit();
+
+        assert_eq!(embeds(&msg).len(), 1);
+        assert_eq!(embeds(&msg)[0].fields.as_ref().unwrap().len(), FIELDS_LIMIT);
+    }
+
+    #[test]
+    fn fields_over_limit() {
+        let msg = m(vec![e("Title", FIELDS_LIMIT + 1)]).fit();
+        let embeds = embeds(&msg);
+
+        assert_eq!(embeds.len(), 2);
+        assert!(embeds
+            .iter()
+            .all(|embed| embed.fields.as_ref().unwrap().len() <= FIELDS_LIMIT));
+        assert_eq!(
+            embeds
+                .iter()
+                .map(|embed| embed.fields.as_ref().unwrap().len())
+                .sum::<usize>(),
+            FIELDS_LIMIT + 1
+        );
+    }
+
+    #[test]
+    fn split_embeds_look_like_one() {
+        let msg = m(vec![e("Title", 60)]).fit();
+        let embeds = embeds(&msg);
+        let last = embeds.len() - 1;
+
+        assert_eq!(embeds.len(), 3);
+        assert!(embeds[0].title.is_some() && embeds[0].author.is_some());
+        assert!(embeds[0].footer.is_none() && embeds[0].timestamp.is_none());
+        assert!(embeds[last].title.is_none() && embeds[last].author.is_none());
+        assert!(embeds[last].footer.is_some() && embeds[last].timestamp.is_some());
+        assert!(embeds.iter().all(|embed| embed.color == Some(0xffffff)));
+    }
+
+    #[test]
+    fn embeds_over_limit() {
+        let msg = m((0..EMBEDS_LIMIT + 1).map(|_| e("Title", 0)).collect()).fit();
+
+        assert_eq!(embeds(&msg).len(), EMBEDS_LIMIT);
+    }
+
+    #[test]
+    fn embeds_over_limit_keep_footer() {
+        // Each embed holds 24 fields, so the last 40 don't fit.
+        let mut embed = e("", 0);
+        embed.fields = Some((0..280).map(|_| f("", "a")).collect());
+        embed.image = Some(Image {
+            url: "https://store.com/image.png".into(),
+        });
+        let msg = m(vec![embed]).fit();
+        let embeds = embeds(&msg);
+        let last = &embeds[EMBEDS_LIMIT - 1];
+
+        assert_eq!(embeds.len(), EMBEDS_LIMIT);
+        assert!(last.footer.is_some() && last.timestamp.is_some() && last.image.is_some());
+        assert_eq!(
+            last.fields.as_ref().unwrap().last().unwrap().value,
+            "+40 more"
+        );
+    }
+
+    #[test]
+    fn total_at_limit() {
+        // "Title" (5), "Store" (5) and "Footer" (6) leave 5984
+        // characters, which is 16 fields with a 374 character value.
+        let mut embed = e("Title", 0);
+        embed.fields = Some((0..16).map(|_| f("", &"a".repeat(374))).collect());
+        let msg = m(vec![embed]).fit();
+
+        assert_eq!(total(&msg), TOTAL_LIMIT);
+        assert_eq!(embeds(&msg)[0].fields.as_ref().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn total_over_limit() {
+        let mut embed = e("Title", 0);
+        embed.fields = Some((0..20).map(|_| f("", &"a".repeat(1000))).collect());
+        let msg = m(vec![embed]).fit();
+        let fields = embeds(&msg)[0].fields.as_ref().unwrap();
+
+        assert!(total(&msg) <= TOTAL_LIMIT);
+        assert_eq!(fields.last().unwrap().value, "+15 more");
+    }
+
+    #[test]
+    fn total_over_limit_across_embeds() {
+        let msg = m(vec![e(&"a".repeat(TITLE_LIMIT), 200)]).fit();
+
+        assert!(total(&msg) <= TOTAL_LIMIT);
+        assert!(embeds(&msg).len() <= EMBEDS_LIMIT);
+        assert!(embeds(&msg)
+            .iter()
+            .all(|embed| embed.fields.as_ref().map_or(0, Vec::len) <= FIELDS_LIMIT));
+    }
+
+    #[test]
+    fn total_over_limit_without_fields() {
+        let mut embeds = vec![];
+
+        for _ in 0..2 {
+            let mut embed = e("", 0);
+            embed.description = Some("a".repeat(DESCRIPTION_LIMIT));
+            embeds.push(embed);
+        }
+
+        let msg = m(embeds).fit();
+
+        assert!(total(&msg) <= TOTAL_LIMIT);
+    }
+
+    #[test]
+    fn serializes() {
+        let msg = m(vec![e("Title", 30)]).fit();
+
+        assert!(serde_json::to_string(&msg).is_ok());
+    }
+}
@@ -0,0 +1,71 @@
+// This document contains tests for `Metrics`, which are served on
+// `/metrics` in the Prometheus text format and decide whether `/healthz`
+// reports the monitor as healthy.
+
+#[cfg(test)]
+mod metrics_test {
+    use crate::metrics::Metrics;
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    #[test]
+    fn metrics_are_rendered() {
+        let metrics = Metrics::default();
+        metrics.register("Store \"1\"");
+
+        metrics.poll("Store \"1\"", Duration::from_millis(200), Some(200));
+        metrics.poll("Store \"1\"", Duration::from_millis(700), Some(429));
+        metrics.poll("Store \"1\"", Duration::from_secs(20), None);
+        metrics.event("Store \"1\"", "restock");
+        metrics.webhook("Store \"1\"", "sent");
+        metrics.webhook("Store \"1\"", "sent");
+        metrics.webhook("Other", "failed");
+
+        let text = metrics.render();
+        let lines = text.lines().collect::<Vec<_>>();
+
+        // Every metric has a description and a type, and the names of the
+        // stores are escaped.
+        for line in [
+            "# TYPE shopify_monitor_polls_total counter",
+            "# TYPE shopify_monitor_poll_duration_seconds histogram",
+            "shopify_monitor_polls_total{store=\"Store \\\"1\\\"\"} 3",
+            "shopify_monitor_poll_duration_seconds_bucket{store=\"Store \\\"1\\\"\",le=\"0.25\"} 1",
+            "shopify_monitor_poll_duration_seconds_bucket{store=\"Store \\\"1\\\"\",le=\"0.75\"} 2",
+            "shopify_monitor_poll_duration_seconds_bucket{store=\"Store \\\"1\\\"\",le=\"10\"} 2",
+            "shopify_monitor_poll_duration_seconds_bucket{store=\"Store \\\"1\\\"\",le=\"+Inf\"} 3",
+            "shopify_monitor_poll_duration_seconds_count{store=\"Store \\\"1\\\"\"} 3",
+            "shopify_monitor_responses_total{store=\"Store \\\"1\\\"\",status=\"200\"} 1",
+            "shopify_monitor_responses_total{store=\"Store \\\"1\\\"\",status=\"429\"} 1",
+            "shopify_monitor_rate_limits_total{store=\"Store \\\"1\\\"\"} 1",
+            "shopify_monitor_store_online{store=\"Store \\\"1\\\"\"} 0",
+            "shopify_monitor_events_total{store=\"Store \\\"1\\\"\",event=\"restock\"} 1",
+            "shopify_monitor_webhooks_total{store=\"Other\",result=\"failed\"} 1",
+            "shopify_monitor_webhooks_total{store=\"Store \\\"1\\\"\",result=\"sent\"} 2",
+        ] {
+            assert!(lines.contains(&line), "`{}` is missing:\n{}", line, text);
+        }
+
+        assert_eq!(metrics.webhooks("sent"), 2);
+        assert_eq!(metrics.events(), 1);
+    }
+
+    // The monitor is healthy as long as one of its stores can be reached.
+    #[test]
+    fn health() {
+        let metrics = Metrics::default();
+        assert!(!metrics.healthy());
+
+        metrics.amount.store(2, Ordering::Relaxed);
+        assert!(metrics.healthy());
+
+        metrics.offline.store(1, Ordering::Relaxed);
+        assert!(metrics.healthy());
+        assert!(metrics
+            .render()
+            .lines()
+            .any(|line| line == "shopify_monitor_stores_offline 1"));
+
+        metrics.offline.store(2, Ordering::Relaxed);
+        assert!(!metrics.healthy());
+    }
+}
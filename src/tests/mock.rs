@@ -0,0 +1,424 @@
+// This document contains the fake servers used by the end-to-end tests
+// in `monitor.rs`, and by the tests of the poller, the watchlist, the
+// discovery and the webhooks: a Shopify store, which replies to every
+// request with a scripted sequence of responses, and a Discord webhook,
+// which records every message it receives. Both run in the test's own
+// process, on a random port, so that the tests don't need an Internet
+// connection.
+
+#![cfg(test)]
+
+use serde_json::{json, Value};
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task,
+    time::{sleep, Instant},
+};
+
+// This is how long `until()` waits for the monitor to do something
+// before the test fails.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct Reply {
+    pub status: u16,
+    pub body: String,
+    pub etag: Option<String>,
+}
+
+impl Reply {
+    // Like Shopify, the fake store tags its products with an `ETag`,
+    // which only changes when they do.
+    pub fn products(products: Value) -> Reply {
+        let body = json!({ "products": products }).to_string();
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+
+        Reply {
+            status: 200,
+            etag: Some(format!("\"{:x}\"", hasher.finish())),
+            body,
+        }
+    }
+
+    // Shopify replies with an HTML page while the password page is up.
+    pub fn password() -> Reply {
+        Reply {
+            status: 401,
+            body: "<html>Opening soon</html>".into(),
+            etag: None,
+        }
+    }
+
+    pub fn rate_limited() -> Reply {
+        Reply {
+            status: 429,
+            body: "".into(),
+            etag: None,
+        }
+    }
+
+    // Stores reply with this when the products haven't changed since
+    // the response whose `ETag` was sent back.
+    pub fn not_modified() -> Reply {
+        Reply {
+            status: 304,
+            body: "".into(),
+            etag: None,
+        }
+    }
+
+    // Product pages and sitemaps are used to find products that aren't
+    // listed by `/products.json` yet.
+    pub fn page(product: Value) -> Reply {
+        Reply {
+            status: 200,
+            body: product.to_string(),
+            etag: None,
+        }
+    }
+
+    pub fn sitemap(handles: &[&str]) -> Reply {
+        Reply {
+            status: 200,
+            body: handles
+                .iter()
+                .map(|handle| {
+                    format!(
+                        "<url><loc>https://store.com/products/{}</loc></url>",
+                        handle
+                    )
+                })
+                .collect(),
+            etag: None,
+        }
+    }
+
+    // This is used for errors whose body doesn't matter, such as a `404`
+    // for a page that isn't live yet.
+    pub fn status(status: u16) -> Reply {
+        Reply {
+            status,
+            body: "".into(),
+            etag: None,
+        }
+    }
+
+    pub fn malformed() -> Reply {
+        Reply {
+            status: 200,
+            body: "{\"products\": [".into(),
+            etag: None,
+        }
+    }
+
+    // These are the replies that can be sent by the fake webhook.
+    // Messages that are sent successfully are given an ID, as Discord
+    // does when `wait=true` is included.
+    pub fn sent(id: &str) -> Reply {
+        Reply {
+            status: 200,
+            body: json!({ "id": id }).to_string(),
+            etag: None,
+        }
+    }
+
+    pub fn empty() -> Reply {
+        Reply {
+            status: 204,
+            body: "".into(),
+            etag: None,
+        }
+    }
+
+    pub fn unknown_webhook() -> Reply {
+        Reply {
+            status: 404,
+            body: json!({ "message": "Unknown Webhook", "code": 10015 }).to_string(),
+            etag: None,
+        }
+    }
+
+    pub fn retry_after(seconds: f64) -> Reply {
+        Reply {
+            status: 429,
+            body: json!({
+                "message": "You are being rate limited.",
+                "retry_after": seconds,
+                "global": false,
+            })
+            .to_string(),
+            etag: None,
+        }
+    }
+}
+
+// The mock store replies with these products, which are written in the
+// same JSON format as Shopify's, including every field the monitor
+// requires, with only the values that change between tests as
+// parameters.
+pub fn p(id: u64, title: &str, updated_at: &str, variants: Vec<Value>) -> Value {
+    json!({
+        "id": id,
+        "title": title,
+        "handle": title.to_lowercase().replace(' ', "-"),
+        "updated_at": updated_at,
+        "vendor": "Nike",
+        "variants": variants,
+        "images": [],
+    })
+}
+
+pub fn v(id: u64, title: &str, available: bool) -> Value {
+    json!({
+        "id": id,
+        "title": title,
+        "available": available,
+        "price": "110.00",
+    })
+}
+
+// The replies are sent in order, and the last one is repeated once
+// there's only one left, so that the monitor can keep polling a store
+// after its script is over.
+#[derive(Clone)]
+struct Script {
+    replies: Arc<Mutex<VecDeque<Reply>>>,
+    fallback: Reply,
+}
+
+impl Script {
+    fn next(&self) -> Reply {
+        let mut replies = self.replies.lock().unwrap();
+
+        if replies.len() > 1 {
+            replies.pop_front().unwrap()
+        } else {
+            replies
+                .front()
+                .cloned()
+                .unwrap_or_else(|| self.fallback.clone())
+        }
+    }
+
+    fn push(&self, reply: Reply) {
+        self.replies.lock().unwrap().push_back(reply);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+
+    // The names of the headers are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Value,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Shopify {
+    pub url: String,
+    received: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Shopify {
+    pub async fn start(replies: Vec<Reply>) -> Shopify {
+        let script = Script {
+            replies: Arc::new(Mutex::new(replies.into())),
+            fallback: Reply::products(json!([])),
+        };
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let url = {
+            let script = script.clone();
+            let received = received.clone();
+
+            serve(move |request| {
+                received.lock().unwrap().push(request);
+                script.next()
+            })
+            .await
+        };
+
+        Shopify { url, received }
+    }
+
+    pub fn requests(&self) -> usize {
+        self.received.lock().unwrap().len()
+    }
+
+    pub fn received(&self) -> Vec<Request> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+pub struct Discord {
+    pub url: String,
+    script: Script,
+    received: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Discord {
+    pub async fn start() -> Discord {
+        let script = Script {
+            replies: Arc::new(Mutex::new(VecDeque::new())),
+            fallback: Reply::sent("1"),
+        };
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let url = {
+            let script = script.clone();
+            let received = received.clone();
+
+            serve(move |request| {
+                received.lock().unwrap().push(request);
+                script.next()
+            })
+            .await
+        };
+
+        Discord {
+            // The monitor only sends webhooks to URLs that look like
+            // Discord's, so its prefix is included in the query string,
+            // which the fake webhook ignores.
+            url: format!("{}/webhook?https://discord.com/api/webhooks/", url),
+            script,
+            received,
+        }
+    }
+
+    // Once the replies run out, messages are sent successfully.
+    pub fn push(&self, reply: Reply) {
+        self.script.push(reply);
+        self.script.push(Reply::sent("1"));
+    }
+
+    pub fn received(&self) -> Vec<Request> {
+        self.received.lock().unwrap().clone()
+    }
+
+    // These are the titles of the embeds that were sent or edited, in
+    // order.
+    pub fn titles(&self) -> Vec<String> {
+        self.received()
+            .iter()
+            .filter_map(|request| {
+                request.body["embeds"][0]["title"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect()
+    }
+}
+
+// This function waits for `check` to be true, failing the test if it
+// takes too long.
+pub async fn until(what: &str, check: impl Fn() -> bool) {
+    let start = Instant::now();
+
+    while !check() {
+        if start.elapsed() > TIMEOUT {
+            panic!("Timed out waiting for {}.", what);
+        }
+
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+// The servers only understand what the monitor sends them, so every
+// connection carries a single request, and is closed once it's answered.
+async fn serve(reply: impl Fn(Request) -> Reply + Send + Sync + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let reply = Arc::new(reply);
+
+    task::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let reply = reply.clone();
+
+            task::spawn(async move {
+                if let Some(request) = read(&mut stream).await {
+                    let Reply { status, body, etag } = reply(request);
+                    let etag = etag.map_or_else(String::new, |etag| format!("ETag: {}\r\n", etag));
+                    let response = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        etag,
+                        body
+                    );
+
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+
+    url
+}
+
+async fn read(stream: &mut TcpStream) -> Option<Request> {
+    let mut buffer = vec![];
+    let mut chunk = [0; 1024];
+
+    let end = loop {
+        if let Some(i) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..end]).to_string();
+    let mut lines = head.lines();
+    let mut line = lines.next()?.split(' ');
+    let method = line.next()?.to_string();
+    let target = line.next()?.to_string();
+
+    let headers = lines
+        .filter_map(|header| header.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect::<Vec<_>>();
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+
+    let mut body = buffer[end..].to_vec();
+
+    while body.len() < length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    Some(Request {
+        method,
+        target,
+        headers,
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    })
+}
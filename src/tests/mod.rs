@@ -6,3 +6,19 @@
 
 mod available_product;
 mod color;
+mod command;
+mod control;
+mod discovery;
+mod dry_run;
+mod event;
+mod history;
+mod mention;
+mod message;
+mod metrics;
+mod mock;
+mod monitor;
+mod poller;
+mod schedule;
+mod template;
+mod watchlist;
+mod webhook;
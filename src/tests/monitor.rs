@@ -0,0 +1,327 @@
+// This document contains end-to-end tests for `monitor::run()`, which
+// is pointed at the fake Shopify store and Discord webhook in `mock.rs`
+// to check exactly which messages it sends in each scenario.
+
+#[cfg(test)]
+mod monitor_test {
+    use crate::{
+        config::Config,
+        control::Controller,
+        event::Events,
+        history::History,
+        metrics::Metrics,
+        monitor, stores,
+        tests::mock::{p, until, v, Discord, Reply, Shopify},
+    };
+    use serde_json::{json, Value};
+    use std::{sync::Arc, time::Duration};
+    use tokio::{sync::watch, task::JoinHandle, time::timeout};
+
+    struct Monitor {
+        handle: JoinHandle<bool>,
+        shutdown: watch::Sender<bool>,
+    }
+
+    impl Monitor {
+        // The monitor is stopped as it would be by Ctrl-C, returning
+        // whether it was stopped by it.
+        async fn stop(self) -> bool {
+            self.shutdown.send(true).unwrap();
+            timeout(Duration::from_secs(10), self.handle)
+                .await
+                .expect("The monitor didn't stop.")
+                .unwrap()
+        }
+    }
+
+    // The store is polled every 50ms, and its only channel receives
+    // every kind of event, using the settings it's given.
+    async fn start(shopify: &Shopify, discord: &Discord, settings: Value) -> Monitor {
+        let config = serde_json::from_value::<Config>(json!({
+            "sites": [{
+                "name": "Store",
+                "url": shopify.url,
+                "logo": "https://store.com/logo.png",
+                "delay": 50,
+            }],
+            "servers": [{
+                "name": "Server",
+                "settings": settings,
+                "channels": [{
+                    "name": "Channel",
+                    "url": discord.url,
+                    "sites": [{
+                        "name": "Store",
+                        "events": [{
+                            "restock": true,
+                            "password_up": true,
+                            "password_down": true,
+                        }],
+                    }],
+                }],
+            }],
+        }))
+        .unwrap();
+
+        let (stores, builder) = stores::get(config);
+        let (controller, added) = Controller::new(builder);
+        let (shutdown, stop) = watch::channel(false);
+
+        let handle = tokio::spawn(monitor::run(
+            stores,
+            Arc::new(Metrics::default()),
+            controller,
+            Arc::new(Events::default()),
+            Arc::new(History::new(None)),
+            None,
+            None,
+            added,
+            stop,
+        ));
+
+        Monitor { handle, shutdown }
+    }
+
+    // This returns the value of the field with the given name in the
+    // embed sent by a request.
+    fn field<'a>(body: &'a Value, name: &str) -> Option<&'a str> {
+        body["embeds"][0]["fields"]
+            .as_array()?
+            .iter()
+            .find(|field| field["name"] == name)?["value"]
+            .as_str()
+    }
+
+    #[tokio::test]
+    async fn new_product_and_restock() {
+        let dunk = |updated_at, available| {
+            p(
+                1,
+                "Dunk Low",
+                updated_at,
+                vec![v(11, "9", available), v(12, "10", false)],
+            )
+        };
+        let jordan = p(2, "Air Jordan 1", "a", vec![v(21, "9", true)]);
+
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([dunk("a", false)])),
+            Reply::products(json!([dunk("a", false), jordan])),
+            Reply::products(json!([dunk("b", true), jordan])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+        let monitor = start(&shopify, &discord, json!({ "sizes": true })).await;
+
+        until("two webhooks", || discord.received().len() == 2).await;
+        assert!(monitor.stop().await);
+
+        let received = discord.received();
+        assert_eq!(discord.titles(), vec!["Air Jordan 1", "Dunk Low"]);
+        assert_eq!(field(&received[0].body, "Event"), Some("New Product"));
+        assert_eq!(field(&received[1].body, "Event"), Some("Restock"));
+
+        // Only the size that restocked is listed.
+        assert!(field(&received[1].body, "Size 9").is_some());
+        assert_eq!(field(&received[1].body, "Size 10"), None);
+        assert_eq!(
+            received[1].body["embeds"][0]["url"],
+            format!("{}/products/dunk-low", shopify.url)
+        );
+    }
+
+    #[tokio::test]
+    async fn password_page() {
+        let products = Reply::products(json!([p(1, "Dunk Low", "a", vec![])]));
+
+        let shopify = Shopify::start(vec![
+            products.clone(),
+            Reply::password(),
+            Reply::password(),
+            products,
+        ])
+        .await;
+        let discord = Discord::start().await;
+        let monitor = start(&shopify, &discord, json!({})).await;
+
+        until("two webhooks", || discord.received().len() == 2).await;
+        assert!(monitor.stop().await);
+
+        // The password page being up for two requests in a row is only
+        // announced once.
+        assert_eq!(
+            discord.titles(),
+            vec!["Password Page Up!", "Password Page Down!"]
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_are_ignored() {
+        let dunk = p(1, "Dunk Low", "a", vec![v(11, "9", true)]);
+        let jordan = p(2, "Air Jordan 1", "a", vec![v(21, "9", true)]);
+
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([dunk])),
+            Reply::rate_limited(),
+            Reply::malformed(),
+            Reply::products(json!([dunk, jordan])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+        let monitor = start(&shopify, &discord, json!({})).await;
+
+        // Neither the rate limit nor the invalid JSON should make the
+        // monitor think the products were removed and added again.
+        until("a few more requests", || shopify.requests() >= 8).await;
+        assert!(monitor.stop().await);
+
+        assert_eq!(discord.titles(), vec!["Air Jordan 1"]);
+    }
+
+    #[tokio::test]
+    async fn sold_out_edits_message() {
+        let dunk = |updated_at, available| {
+            p(
+                1,
+                "Dunk Low",
+                updated_at,
+                vec![v(11, "9", true), v(12, "10", available)],
+            )
+        };
+
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([])),
+            Reply::products(json!([dunk("a", true)])),
+            Reply::products(json!([dunk("b", false)])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+        discord.push(Reply::sent("42"));
+        let monitor = start(&shopify, &discord, json!({ "sizes": true, "edit": true })).await;
+
+        until("the message to be edited", || discord.received().len() == 2).await;
+        assert!(monitor.stop().await);
+
+        let received = discord.received();
+        assert_eq!(received[0].method, "POST");
+        assert_eq!(received[1].method, "PATCH");
+        assert!(received[1].target.starts_with("/webhook/messages/42?"));
+
+        // The size that sold out is struck through, rather than removed.
+        assert_eq!(field(&received[1].body, "Event"), Some("New Product"));
+        assert!(field(&received[1].body, "~~Size 10~~").is_some());
+        assert!(field(&received[1].body, "Size 9").is_some());
+    }
+
+    #[tokio::test]
+    async fn restock_with_mention_is_sent_again() {
+        let dunk = |updated_at, available| {
+            p(
+                1,
+                "Dunk Low",
+                updated_at,
+                vec![v(11, "9", true), v(12, "10", available)],
+            )
+        };
+
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([])),
+            Reply::products(json!([dunk("a", false)])),
+            Reply::products(json!([dunk("b", true)])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+        discord.push(Reply::sent("42"));
+        let settings = json!({ "edit": true, "mention": { "roles": ["123"] } });
+        let monitor = start(&shopify, &discord, settings).await;
+
+        until("the restock to be sent", || discord.received().len() == 2).await;
+        assert!(monitor.stop().await);
+
+        // Editing the first message wouldn't ping the role.
+        let received = discord.received();
+        assert_eq!(received[1].method, "POST");
+        assert_eq!(received[1].body["content"], "<@&123>");
+        assert_eq!(field(&received[1].body, "Event"), Some("Restock"));
+    }
+
+    #[tokio::test]
+    async fn empty_reply_is_not_edited() {
+        let dunk = |updated_at, available| {
+            p(
+                1,
+                "Dunk Low",
+                updated_at,
+                vec![v(11, "9", true), v(12, "10", available)],
+            )
+        };
+
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([])),
+            Reply::products(json!([dunk("a", true)])),
+            Reply::products(json!([dunk("b", false)])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+
+        // Without an ID, there's no message to edit once a size sells
+        // out.
+        discord.push(Reply::empty());
+        let monitor = start(&shopify, &discord, json!({ "edit": true })).await;
+
+        until("a few more requests", || shopify.requests() >= 6).await;
+        assert!(monitor.stop().await);
+
+        let received = discord.received();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].method, "POST");
+    }
+
+    #[tokio::test]
+    async fn rate_limited_webhook_is_retried() {
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([])),
+            Reply::products(json!([p(1, "Dunk Low", "a", vec![v(11, "9", true)])])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+        discord.push(Reply::retry_after(0.1));
+        let monitor = start(&shopify, &discord, json!({})).await;
+
+        until("the webhook to be sent again", || {
+            discord.received().len() == 2
+        })
+        .await;
+        assert!(monitor.stop().await);
+
+        let received = discord.received();
+        assert_eq!(received[0].body, received[1].body);
+    }
+
+    #[tokio::test]
+    async fn invalid_webhook_stops_monitor() {
+        let shopify = Shopify::start(vec![
+            Reply::products(json!([])),
+            Reply::products(json!([p(1, "Dunk Low", "a", vec![v(11, "9", true)])])),
+            Reply::products(json!([
+                p(1, "Dunk Low", "a", vec![v(11, "9", true)]),
+                p(2, "Air Jordan 1", "a", vec![v(21, "9", true)]),
+            ])),
+        ])
+        .await;
+        let discord = Discord::start().await;
+        discord.push(Reply::unknown_webhook());
+        let monitor = start(&shopify, &discord, json!({})).await;
+
+        // Once its only webhook is found to be invalid, the store stops
+        // being monitored, and so does the monitor, without being asked
+        // to.
+        let stopped = timeout(Duration::from_secs(10), monitor.handle)
+            .await
+            .expect("The monitor didn't stop.")
+            .unwrap();
+
+        assert!(!stopped);
+        assert_eq!(discord.titles(), vec!["Dunk Low"]);
+    }
+}
@@ -0,0 +1,77 @@
+// This document contains tests for `Poller`, which fetches a store's
+// products from the fake Shopify store in `mock.rs`, skipping them when
+// they haven't changed and fetching every page of each collection.
+
+#[cfg(test)]
+mod poller_test {
+    use crate::{
+        poller::Poller,
+        tests::mock::{p, v, Reply, Shopify},
+    };
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn unchanged_products_are_skipped() {
+        let dunk = Reply::products(json!([p(1, "Dunk Low", "a", vec![v(11, "9", true)])]));
+        let jordan = Reply::products(json!([p(2, "Air Jordan 1", "a", vec![v(21, "9", true)])]));
+
+        let dunk_etag = dunk.etag.clone();
+
+        let shopify = Shopify::start(vec![dunk.clone(), dunk, Reply::not_modified(), jordan]).await;
+        let mut poller = Poller::new(&shopify.url);
+
+        let mut unchanged = vec![];
+        for _ in 0..4 {
+            let res = poller.poll().await.unwrap();
+            assert_eq!(res.unchanged, res.products.is_err());
+            unchanged.push(res.unchanged);
+        }
+
+        assert_eq!(unchanged, vec![false, true, true, false]);
+
+        // The `ETag` of the first response is sent back with every
+        // request after it, as the second one had the same products and
+        // the third one didn't have a body.
+        let etag = dunk_etag.unwrap();
+        let received = shopify.received();
+        assert_eq!(received[0].header("if-none-match"), None);
+        for request in &received[1..] {
+            assert_eq!(request.header("if-none-match"), Some(etag.as_str()));
+        }
+    }
+
+    #[tokio::test]
+    async fn collections_are_paginated() {
+        let page = |ids: Vec<u64>| {
+            Reply::products(Value::Array(
+                ids.into_iter()
+                    .map(|id| p(id, "Dunk Low", "a", vec![]))
+                    .collect(),
+            ))
+        };
+
+        // The first collection has a full page, so its second page is
+        // fetched too, while the product they share is only counted once.
+        let shopify = Shopify::start(vec![
+            page((1..=250).collect()),
+            page(vec![251]),
+            page(vec![1, 300]),
+            Reply::not_modified(),
+            Reply::not_modified(),
+            Reply::not_modified(),
+        ])
+        .await;
+        let mut poller =
+            Poller::new(&shopify.url).collections(vec!["footwear".into(), "sale".into()]);
+
+        let res = poller.poll().await.unwrap();
+        assert_eq!(res.products.unwrap().len(), 252);
+        assert_eq!(res.collections[&1], "footwear");
+        assert_eq!(res.collections[&251], "footwear");
+        assert_eq!(res.collections[&300], "sale");
+
+        let res = poller.poll().await.unwrap();
+        assert!(res.unchanged);
+        assert_eq!(shopify.requests(), 6);
+    }
+}
@@ -0,0 +1,76 @@
+// These tests check the delays chosen by schedules at given times, which
+// is why they use `at`, as `delay` depends on the current time and adds
+// jitter.
+
+#[cfg(test)]
+mod schedule_test {
+    use crate::{config, schedule::Schedule};
+    use chrono::prelude::*;
+
+    fn schedule() -> Schedule {
+        let schedule = serde_json::from_str::<config::Schedule>(
+            r#"{
+                "windows": [
+                    { "days": ["Thu"], "from": "10:55", "to": "11:30", "delay": 500 },
+                    { "from": "23:00", "to": "07:00", "delay": 60000 }
+                ],
+                "boost": { "delay": 1000, "duration": 300 }
+            }"#,
+        )
+        .unwrap();
+
+        Schedule::parse(&schedule).unwrap()
+    }
+
+    fn t(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn windows() {
+        let schedule = schedule();
+
+        assert_eq!(schedule.at(5000, false, Weekday::Thu, t("11:00")), 500);
+        assert_eq!(schedule.at(5000, false, Weekday::Fri, t("11:00")), 5000);
+        assert_eq!(schedule.at(5000, false, Weekday::Thu, t("11:30")), 5000);
+
+        // The overnight window goes past midnight.
+        assert_eq!(schedule.at(5000, false, Weekday::Sat, t("23:30")), 60000);
+        assert_eq!(schedule.at(5000, false, Weekday::Sun, t("06:59")), 60000);
+    }
+
+    #[test]
+    fn boost() {
+        let schedule = schedule();
+
+        assert_eq!(schedule.at(5000, true, Weekday::Mon, t("12:00")), 1000);
+        assert_eq!(schedule.at(5000, true, Weekday::Sun, t("02:00")), 1000);
+
+        // Boosts never slow a store down.
+        assert_eq!(schedule.at(5000, true, Weekday::Thu, t("11:00")), 500);
+    }
+
+    // Each mistake is tested on its own, so that one of them being caught
+    // doesn't hide the other not being checked.
+    fn error(schedule: &str) -> String {
+        let schedule = serde_json::from_str::<config::Schedule>(schedule).unwrap();
+
+        Schedule::parse(&schedule).unwrap_err()
+    }
+
+    #[test]
+    fn invalid_offset() {
+        assert_eq!(error(r#"{ "offset": "0100" }"#), "Invalid offset: `0100`!");
+        assert_eq!(error(r#"{ "offset": "+01" }"#), "Invalid offset: `+01`!");
+    }
+
+    #[test]
+    fn invalid_time() {
+        assert_eq!(
+            error(
+                r#"{ "offset": "+01:00", "windows": [{ "from": "25:00", "to": "01:00", "delay": 1 }] }"#
+            ),
+            "Invalid time: `25:00`!"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+// These tests check how templates are parsed, which is when mistakes in
+// them are reported, and how their placeholders are filled in.
+
+#[cfg(test)]
+mod template_test {
+    use crate::{
+        config,
+        template::{render, Template},
+    };
+    use std::collections::HashMap;
+
+    fn parse(template: &str) -> Result<Template, String> {
+        Template::parse(&serde_json::from_str::<config::Template>(template).unwrap())
+    }
+
+    #[test]
+    fn unknown_placeholder() {
+        assert_eq!(
+            parse(r#"{ "title": "{product.name}" }"#).unwrap_err(),
+            "Unknown placeholder `{product.name}`!"
+        );
+    }
+
+    #[test]
+    fn escaped_braces() {
+        let template = parse(r#"{ "title": "{{{product.title}}}" }"#).unwrap();
+        let mut values = HashMap::new();
+        values.insert("product.title", "Dunk Low".to_string());
+
+        assert_eq!(
+            render(template.title.as_ref().unwrap(), &values),
+            "{Dunk Low}"
+        );
+    }
+
+    #[test]
+    fn unmatched_braces() {
+        assert_eq!(
+            parse(r#"{ "title": "{product.title" }"#).unwrap_err(),
+            "Unclosed placeholder in `{product.title`!"
+        );
+        assert_eq!(
+            parse(r#"{ "title": "product.title}" }"#).unwrap_err(),
+            "Unmatched `}` in `product.title}`!"
+        );
+    }
+
+    #[test]
+    fn variant_placeholder_outside_size() {
+        assert_eq!(
+            parse(r#"{ "description": "{atc_url}" }"#).unwrap_err(),
+            "`{atc_url}` can only be used in `size`!"
+        );
+        assert!(
+            parse(r#"{ "size": { "name": "{variant.title}", "value": "{atc_url}" } }"#).is_ok()
+        );
+    }
+
+    #[test]
+    fn missing_values_are_empty() {
+        assert_eq!(render("{store.name}!", &HashMap::new()), "!");
+    }
+}
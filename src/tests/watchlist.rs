@@ -0,0 +1,99 @@
+// This document contains tests for `Watchlist`, which polls the pages
+// of products that aren't listed on the store yet, announcing them as
+// soon as they're live.
+
+#[cfg(test)]
+mod watchlist_test {
+    use crate::{
+        event::{Event, Store},
+        stores,
+        tests::mock::{Reply, Shopify},
+        watchlist::Watchlist,
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn watchlist_finds_products() {
+        // Product pages list prices in cents, and images without a
+        // scheme.
+        let page = |available| {
+            Reply::page(json!({
+                "id": 1,
+                "title": "Dunk Low",
+                "handle": "dunk-low",
+                "vendor": "Nike",
+                "variants": [
+                    { "id": 11, "title": "9", "available": true, "price": 11000, "inventory_quantity": 3 },
+                    { "id": 12, "title": "10", "available": available, "price": 11000 },
+                ],
+                "images": ["//cdn.shopify.com/dunk-low.png"],
+            }))
+        };
+
+        let shopify = Shopify::start(vec![Reply::status(404), page(false), page(true)]).await;
+        let store = Store {
+            name: "Store".into(),
+            url: shopify.url.clone(),
+            logo: "https://store.com/logo.png".into(),
+        };
+        let handle = stores::handle(
+            &format!("{}/products/dunk-low?variant=11", shopify.url),
+            "products",
+        );
+        let mut watchlist = Watchlist::new(&shopify.url, store, vec![handle]);
+
+        assert!(watchlist.poll().await.is_empty());
+
+        match watchlist.poll().await.as_slice() {
+            [Event::NewProduct { product, .. }] => {
+                assert_eq!(product.handle, "dunk-low");
+                assert_eq!(product.variants[0].price, "110.00");
+                assert_eq!(product.variants[0].inventory_quantity, Some(3));
+                assert_eq!(
+                    product.images[0].src,
+                    "https://cdn.shopify.com/dunk-low.png"
+                );
+            }
+            events => panic!("Expected a new product, found {:?}.", events),
+        }
+
+        match watchlist.poll().await.as_slice() {
+            [Event::Restock { restocked, .. }] => assert_eq!(restocked, &vec![12]),
+            events => panic!("Expected a restock, found {:?}.", events),
+        }
+    }
+
+    #[tokio::test]
+    async fn watchlist_waits_for_every_product() {
+        let page = Reply::page(json!({
+            "id": 1,
+            "title": "Dunk Low",
+            "handle": "dunk-low",
+            "vendor": "Nike",
+            "variants": [{ "id": 11, "title": "9", "available": true, "price": 11000 }],
+            "images": [],
+        }));
+
+        // The product was already live, so it isn't announced once the
+        // request that failed goes through, and it isn't announced again
+        // after its page was taken down for a moment either.
+        let shopify = Shopify::start(vec![
+            Reply::status(500),
+            page.clone(),
+            Reply::status(404),
+            page,
+        ])
+        .await;
+        let store = Store {
+            name: "Store".into(),
+            url: shopify.url.clone(),
+            logo: "https://store.com/logo.png".into(),
+        };
+        let mut watchlist = Watchlist::new(&shopify.url, store, vec!["dunk-low".into()]);
+
+        for _ in 0..4 {
+            assert!(watchlist.poll().await.is_empty());
+        }
+        assert_eq!(shopify.requests(), 4);
+    }
+}
@@ -0,0 +1,45 @@
+// This document contains tests for the requests sent by `webhook.rs`,
+// which are received by the fake Discord webhook in `mock.rs`.
+
+#[cfg(test)]
+mod webhook_test {
+    use crate::{
+        message::Message,
+        tests::mock::{Discord, Reply},
+        webhook::{self, Status},
+    };
+    use std::sync::Arc;
+
+    fn m(content: &str) -> Arc<Message> {
+        Arc::new(Message {
+            content: Some(content.into()),
+            embeds: None,
+            username: None,
+            avatar_url: None,
+            allowed_mentions: None,
+        })
+    }
+
+    // Webhooks that post in a thread have its ID in their query string,
+    // which has to be kept when the message is edited.
+    #[tokio::test]
+    async fn thread_messages_are_edited() {
+        let discord = Discord::start().await;
+        discord.push(Reply::sent("42"));
+        let url = format!("{}&thread_id=7", discord.url);
+
+        let status = webhook::send(url.clone(), m("Sent")).await;
+        assert_eq!(status, Status::Success(Some("42".into())));
+
+        let status = webhook::edit(url, "42".into(), m("Edited")).await;
+        assert!(matches!(status, Status::Success(_)));
+
+        let received = discord.received();
+        let query = "?https://discord.com/api/webhooks/&thread_id=7";
+        assert_eq!(received[0].method, "POST");
+        assert_eq!(received[0].target, format!("/webhook{}&wait=true", query));
+        assert_eq!(received[1].method, "PATCH");
+        assert_eq!(received[1].target, format!("/webhook/messages/42{}", query));
+        assert_eq!(received[1].body["content"], "Edited");
+    }
+}
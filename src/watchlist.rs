@@ -0,0 +1,88 @@
+// This module polls the products on a store's watchlist, which are the
+// ones whose release is known in advance. Their pages are usually live
+// before they're added to `/products.json`, so each of them is fetched
+// through `/products/{handle}.js` instead, and the products it finds are
+// compared using the same rules as the rest of the store, so that they
+// cause the same events and messages.
+
+// While a product is on the watchlist, the events `/products.json` finds
+// for it are ignored, as otherwise every restock would be sent twice.
+
+use crate::{
+    diff::Detector,
+    event::{Event, Store},
+    hidden,
+    poller::Poller,
+    products::Product,
+};
+use std::collections::{HashMap, HashSet};
+
+pub struct Watchlist {
+    url: String,
+    poller: Poller,
+    handles: Vec<String>,
+
+    // This holds the last version of each product that could be found,
+    // so that a request failing, or a product's page being taken down for
+    // a moment, doesn't make it look like it was removed and added again.
+    products: HashMap<String, Product>,
+
+    // These are the handles the store gave an answer for, whether or not
+    // their product was live. Until every one of them has, the products
+    // aren't compared, as the ones that were already live could be
+    // announced as new once their first successful request goes through.
+    resolved: HashSet<String>,
+    detector: Detector,
+}
+
+impl Watchlist {
+    pub fn new(url: &str, store: Store, handles: Vec<String>) -> Watchlist {
+        Watchlist {
+            url: url.trim_end_matches('/').into(),
+            poller: Poller::new(url),
+            handles,
+            products: HashMap::new(),
+            resolved: HashSet::new(),
+            detector: Detector::new(store),
+        }
+    }
+
+    // Every product is requested once, returning the events caused by
+    // the ones that changed. Products that aren't live yet are only
+    // announced once they are, as new products, while the ones that
+    // already were when the monitor started aren't.
+    pub async fn poll(&mut self) -> Vec<Event> {
+        for handle in self.handles.iter() {
+            match self.poller.product(handle).await {
+                Ok(Some(product)) => {
+                    self.products.insert(handle.clone(), product);
+                    self.resolved.insert(handle.clone());
+                }
+                Ok(None) => {
+                    self.resolved.insert(handle.clone());
+                }
+                Err(e) => {
+                    hidden!("Failed to GET {}/products/{}.js: {}", self.url, handle, e);
+                }
+            }
+        }
+
+        if !self
+            .handles
+            .iter()
+            .all(|handle| self.resolved.contains(handle))
+        {
+            return vec![];
+        }
+
+        let current = self
+            .handles
+            .iter()
+            .filter_map(|handle| self.products.get(handle).cloned())
+            .collect::<Vec<_>>();
+
+        // The password page is found through `/products.json`, so it's
+        // never reported by the watchlist.
+        self.detector.detect(200, Some(&current))
+    }
+}
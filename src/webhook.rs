@@ -2,7 +2,7 @@
 // a parameter and sends a POST Request  to a webhook URL argument.
 
 use crate::{hidden, message::Message};
-use reqwest::Client;
+use reqwest::{Client, Response};
 use serde::Deserialize;
 /* use serde_json::to_string_pretty; */
 use std::sync::Arc;
@@ -15,42 +15,104 @@ pub async fn send(url: String, msg: Arc<Message>) -> Status {
     // was wrong.
     /* hidden!("{}", to_string_pretty(&*msg).unwrap()); */
 
+    // Adding `wait=true` to the query string makes Discord reply with
+    // the message it created (and a `200` status code) instead of an
+    // empty `204` response, which is how the program finds out the ID
+    // it needs to edit the message later on.
+    let link = format!(
+        "{}{}wait=true",
+        url,
+        if url.contains('?') { '&' } else { '?' }
+    );
+
     let client = Client::new();
-    let req = client.post(url.clone()).json(&*msg).send().await;
-
-    if let Ok(res) = req {
-        hidden!("Sent webhook to {}!, Status: {}", url, res.status());
-
-        // I have to include the `return` keyword here as the compiler
-        // will complain if I don't (as it expects the `if let`
-        // statement after it to return too)
-        return match res.status().as_u16() {
-            200 | 204 => Status::Success,
-            201 | 404 => Status::Invalid,
-            429 => {
-                if let Ok(info) = res.json::<RateLimit>().await {
-                    Status::RateLimit(Some(info.retry_after))
-                } else {
-                    Status::RateLimit(None)
-                }
-            }
-            _ => Status::Unknown,
-        };
-    } else if let Err(e) = req {
-        hidden!("Error sending webhook to {}: {}", url, e);
+    let req = client.post(link).json(&*msg).send().await;
+
+    match req {
+        Ok(res) => {
+            hidden!("Sent webhook to {}!, Status: {}", url, res.status());
+            status(res).await
+        }
+        Err(e) => {
+            hidden!("Error sending webhook to {}: {}", url, e);
+            Status::Unknown
+        }
+    }
+}
+
+// Messages sent through a webhook can be edited by the same webhook
+// using the `/messages/{id}` endpoint, which accepts the same body as
+// the one used to send them. The endpoint goes before the query string,
+// which has to be kept, as `thread_id` tells Discord which thread the
+// message is in.
+pub async fn edit(url: String, id: String, msg: Arc<Message>) -> Status {
+    let link = match url.split_once('?') {
+        Some((path, query)) => format!("{}/messages/{}?{}", path, id, query),
+        None => format!("{}/messages/{}", url, id),
     };
 
-    Status::Unknown
+    let client = Client::new();
+    let req = client.patch(link).json(&*msg).send().await;
+
+    match req {
+        Ok(res) => {
+            hidden!(
+                "Edited message {} on {}!, Status: {}",
+                id,
+                url,
+                res.status()
+            );
+            status(res).await
+        }
+        Err(e) => {
+            hidden!("Error editing message {} on {}: {}", id, url, e);
+            Status::Unknown
+        }
+    }
+}
+
+// Both requests are answered in the same way, so the logic that
+// interprets Discord's response is shared.
+async fn status(res: Response) -> Status {
+    match res.status().as_u16() {
+        200 => {
+            if let Ok(sent) = res.json::<Sent>().await {
+                Status::Success(Some(sent.id))
+            } else {
+                Status::Success(None)
+            }
+        }
+        204 => Status::Success(None),
+        201 | 404 => Status::Invalid,
+        429 => {
+            if let Ok(info) = res.json::<RateLimit>().await {
+                Status::RateLimit(Some(info.retry_after))
+            } else {
+                Status::RateLimit(None)
+            }
+        }
+        _ => Status::Unknown,
+    }
 }
 
 #[derive(PartialEq, Debug)]
 pub enum Status {
-    Success,
+    // The ID of the message is included when Discord returns it, which
+    // should always be the case as `send()` includes `wait=true`.
+    Success(Option<String>),
     Invalid,
     RateLimit(Option<f64>),
     Unknown,
 }
 
+// Only the message's ID is needed, so the rest of the object returned
+// by Discord is ignored.
+#[derive(Deserialize)]
+pub struct Sent {
+    pub id: String,
+}
+
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct RateLimit {
     pub message: String,